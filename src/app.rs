@@ -2,10 +2,16 @@ use leptos::prelude::*;
 use leptos_meta::{provide_meta_context, MetaTags, Stylesheet, Title};
 use leptos_router::{
     components::{Route, Router, Routes},
-    StaticSegment,
+    ParamSegment, StaticSegment,
 };
 
-use crate::ui::{components::header::Header, pages::home::HomePage};
+use crate::ui::{
+    components::header::Header,
+    pages::{
+        admin::AdminPage, analytics::AnalyticsPage, block::BlockPage, home::HomePage,
+        login::LoginPage, transaction::TransactionPage,
+    },
+};
 
 pub fn shell(options: LeptosOptions) -> impl IntoView {
     view! {
@@ -46,6 +52,12 @@ pub fn App() -> impl IntoView {
             <main>
                 <Routes fallback=|| "Page not found.".into_view()>
                     <Route path=StaticSegment("") view=HomePage/>
+                    <Route path=(StaticSegment("contract"), ParamSegment("address")) view=HomePage/>
+                    <Route path=(StaticSegment("contract"), ParamSegment("address"), StaticSegment("analytics")) view=AnalyticsPage/>
+                    <Route path=(StaticSegment("block"), ParamSegment("height_or_hash")) view=BlockPage/>
+                    <Route path=(StaticSegment("tx"), ParamSegment("hash")) view=TransactionPage/>
+                    <Route path=StaticSegment("admin") view=AdminPage/>
+                    <Route path=StaticSegment("login") view=LoginPage/>
                 </Routes>
             </main>
         </Router>
@@ -0,0 +1,6 @@
+//! Command line client, e.g. `compactsee-cli tail <address>`, see [`compactsee::cli`].
+
+#[tokio::main]
+async fn main() -> std::process::ExitCode {
+    compactsee::cli::run().await
+}
@@ -0,0 +1,224 @@
+use std::{process::ExitCode, time::Duration};
+
+use clap::Args;
+use futures::StreamExt;
+use midnight_node_ledger_helpers::DefaultDB;
+use serde_json::Value;
+
+use super::{ConnectionArgs, EXIT_CONNECTION, EXIT_USAGE};
+use crate::{
+    core::delivery,
+    domain::{
+        diff::diff,
+        path::JsonPath,
+        predicate::{parse_value, Condition, Predicate},
+        Event, SubscriptionError,
+    },
+};
+
+#[derive(Args)]
+pub struct ExpectArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+    /// How long to wait for the condition, e.g. `120s`, `2m` or `500ms`
+    #[arg(long, value_parser = parse_duration, default_value = "60s")]
+    pub within: Duration,
+    /// JSONPath-style selector into the decoded state. Ledger fields have no names on chain, only positions in
+    /// declaration order, so the first field is `$[0]` and a path like `$.counter` never matches
+    #[arg(long)]
+    pub state_path: JsonPath,
+    /// The value equals this JSON value or string
+    #[arg(long, group = "condition")]
+    pub equals: Option<String>,
+    /// The value differs from this JSON value or string
+    #[arg(long, group = "condition")]
+    pub not_equals: Option<String>,
+    #[arg(long, group = "condition")]
+    pub greater_than: Option<f64>,
+    #[arg(long, group = "condition")]
+    pub less_than: Option<f64>,
+    /// The value, as text, contains this string
+    #[arg(long, group = "condition")]
+    pub contains: Option<String>,
+}
+
+impl ExpectArgs {
+    fn predicate(&self) -> Predicate {
+        let condition = if let Some(value) = &self.equals {
+            Condition::Equals(parse_value(value))
+        } else if let Some(value) = &self.not_equals {
+            Condition::NotEquals(parse_value(value))
+        } else if let Some(bound) = self.greater_than {
+            Condition::GreaterThan(bound)
+        } else if let Some(bound) = self.less_than {
+            Condition::LessThan(bound)
+        } else if let Some(needle) = &self.contains {
+            Condition::Contains(needle.clone())
+        } else {
+            Condition::Exists
+        };
+        Predicate::new(self.state_path.clone(), condition)
+    }
+}
+
+/// Waits until the predicate holds on the contract state. Exits 0 when it does and 1 when time runs out.
+pub async fn run(args: ExpectArgs) -> ExitCode {
+    let predicate = args.predicate();
+    let indexer = match args
+        .connection
+        .indexer(args.within.as_secs().saturating_add(1))
+    {
+        Ok(indexer) => indexer,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let address = match indexer.parse_address(&args.connection.address) {
+        Ok(address) => address,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let latest = match indexer.find_contract::<DefaultDB>(&address).await {
+        Ok(latest) => latest,
+        Err(e) => {
+            eprintln!("{}", e);
+            return match e {
+                SubscriptionError::IndexerUnavailable(_) => ExitCode::from(EXIT_CONNECTION),
+                _ => ExitCode::from(EXIT_USAGE),
+            };
+        }
+    };
+
+    let initial = latest.decoded_state.clone().unwrap_or(Value::Null);
+    if initial.is_array() && predicate.path.starts_with_field() {
+        eprintln!(
+            "warning: ledger fields are positional, {} never matches, the first field is $[0]",
+            predicate.path
+        );
+    }
+    if predicate.holds(&initial) {
+        println!("ok: {}", predicate);
+        return ExitCode::SUCCESS;
+    }
+
+    let (tx, mut rx) = delivery::channel(delivery::DEFAULT_CAPACITY);
+    {
+        let indexer = indexer.clone();
+        tokio::spawn(async move {
+            let _ = indexer
+                .subscribe_to_contract::<DefaultDB>(address, tx)
+                .await;
+        });
+    }
+
+    let mut current = initial.clone();
+    let outcome = tokio::time::timeout(args.within, async {
+        while let Some(event) = rx.next().await {
+            match event {
+                Event::ContractEvent(event) => {
+                    current = event.decoded_state.unwrap_or(Value::Null);
+                    if predicate.holds(&current) {
+                        return Ok(());
+                    }
+                }
+                Event::Error(e) => return Err(e.to_string()),
+                Event::Disconnect | Event::ServerShuttingDown => break,
+                Event::Lagged { .. } | Event::TimeLeft(_) | Event::Alert(_) => {}
+            }
+        }
+        Err("indexer closed the subscription".to_string())
+    })
+    .await;
+
+    match outcome {
+        Ok(Ok(())) => {
+            println!("ok: {}", predicate);
+            ExitCode::SUCCESS
+        }
+        Ok(Err(e)) => {
+            eprintln!("{}", e);
+            ExitCode::from(EXIT_CONNECTION)
+        }
+        Err(_) => {
+            eprintln!(
+                "failed: {} did not hold within {:?}",
+                predicate, args.within
+            );
+            let actual = predicate.path.select(&current);
+            eprintln!(
+                "actual: {}",
+                serde_json::to_string(&actual).unwrap_or_default()
+            );
+            let changes = diff(&initial, &current);
+            if changes.is_empty() {
+                eprintln!("state did not change");
+            } else {
+                eprintln!("state changes since start:");
+                for change in changes {
+                    eprintln!("  {}", change);
+                }
+            }
+            ExitCode::from(EXIT_USAGE)
+        }
+    }
+}
+
+/// `120s`, `2m`, `1h`, `500ms` or a plain number of seconds.
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", input))?;
+    let seconds = |per_unit: u64| {
+        number
+            .checked_mul(per_unit)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("duration '{}' is too long", input))
+    };
+    match unit {
+        "" | "s" => Ok(Duration::from_secs(number)),
+        "ms" => Ok(Duration::from_millis(number)),
+        "m" => seconds(60),
+        "h" => seconds(3600),
+        _ => Err(format!("unknown duration unit '{}' in '{}'", unit, input)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations_with_units() {
+        assert_eq!(parse_duration("120s"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration(" 90 "), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn rejects_unknown_units_and_missing_numbers() {
+        assert!(parse_duration("5d").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("-1s").is_err());
+    }
+
+    #[test]
+    fn rejects_durations_that_overflow() {
+        assert!(parse_duration(&format!("{}h", u64::MAX / 60)).is_err());
+        assert!(parse_duration(&format!("{}m", u64::MAX)).is_err());
+        assert_eq!(
+            parse_duration(&format!("{}s", u64::MAX)),
+            Ok(Duration::from_secs(u64::MAX))
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Headless client that follows contract events from the terminal, without the web server. Built as the
+//! `compactsee-cli` binary.
+
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::{
+    core::{
+        config::{ledger_network_id, IndexerEndpoint, LogConfig},
+        contract_indexer::ContractIndexer,
+        event_store::EventStore,
+        telemetry,
+    },
+    domain::network::Network,
+};
+
+mod expect;
+mod tail;
+
+/// The address or arguments are wrong, the contract does not exist or an expectation failed.
+const EXIT_USAGE: u8 = 1;
+/// The indexer could not be reached or dropped the connection.
+const EXIT_CONNECTION: u8 = 2;
+
+#[derive(Parser)]
+#[command(
+    name = "compactsee-cli",
+    version,
+    about = "Follow Midnight contract events from the terminal"
+)]
+struct Cli {
+    /// Log indexer traffic to stderr, filtered by COMPACTSEE_LOG or RUST_LOG
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print contract events as they arrive
+    Tail(tail::TailArgs),
+    /// Wait until a condition on the decoded state holds, for use in test pipelines
+    Expect(expect::ExpectArgs),
+}
+
+#[derive(Args)]
+pub struct ConnectionArgs {
+    /// Contract address, with or without the network prefix
+    pub address: String,
+    #[arg(long, default_value = "testnet")]
+    pub network: Network,
+    /// Indexer GraphQL websocket, defaults to the public indexer of the network
+    #[arg(long)]
+    pub indexer_ws: Option<String>,
+    /// Indexer GraphQL HTTP endpoint, defaults to the public indexer of the network
+    #[arg(long)]
+    pub indexer_http: Option<String>,
+}
+
+impl ConnectionArgs {
+    /// Websocket and HTTP endpoints of the indexer, the public ones of the network unless overridden.
+    pub fn endpoints(&self) -> Result<(String, String), String> {
+        let known = IndexerEndpoint::known(self.network);
+        let ws_url = self
+            .indexer_ws
+            .clone()
+            .or_else(|| known.as_ref().map(|endpoint| endpoint.ws_url.clone()));
+        let http_url = self
+            .indexer_http
+            .clone()
+            .or_else(|| known.as_ref().map(|endpoint| endpoint.http_url.clone()));
+        match (ws_url, http_url) {
+            (Some(ws_url), Some(http_url)) => Ok((ws_url, http_url)),
+            _ => Err(format!(
+                "no public indexer for {}, pass --indexer-ws and --indexer-http",
+                self.network
+            )),
+        }
+    }
+
+    pub fn indexer(&self, timeout: u64) -> Result<ContractIndexer, String> {
+        let (ws_url, http_url) = self.endpoints()?;
+        Ok(ContractIndexer::new(
+            ledger_network_id(self.network),
+            ws_url,
+            http_url,
+            EventStore::in_memory(),
+            timeout,
+        ))
+    }
+}
+
+/// Runs the command given on the command line. Logs only go to stderr, and only with `--verbose`, since stdout
+/// carries the events.
+pub async fn run() -> ExitCode {
+    let cli = Cli::parse();
+    let telemetry = cli
+        .verbose
+        .then(|| telemetry::init_stderr(&LogConfig::from_env()));
+
+    let exit_code = match cli.command {
+        Command::Tail(args) => tail::run(args).await,
+        Command::Expect(args) => expect::run(args).await,
+    };
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown();
+    }
+    exit_code
+}
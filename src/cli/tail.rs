@@ -0,0 +1,166 @@
+use std::{process::ExitCode, time::Duration};
+
+use clap::Args;
+use futures::StreamExt;
+use midnight_node_ledger_helpers::DefaultDB;
+
+use super::{ConnectionArgs, EXIT_CONNECTION, EXIT_USAGE};
+use crate::{
+    core::{api::v1::EventV1, chain_head::ChainHeadTracker, delivery, event_store::now_millis},
+    domain::{indexer::format_timestamp, ContractEvent, Event, SubscriptionError},
+};
+
+/// How often `--until` looks at the chain head, so a contract nobody calls does not keep the command waiting.
+const UNTIL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Args)]
+pub struct TailArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+    /// Print one JSON object per line instead of a human readable summary
+    #[arg(long)]
+    pub json: bool,
+    /// Replay the contract's actions starting at this block height
+    #[arg(long)]
+    pub from_block: Option<u64>,
+    /// Stop once the chain, or an event, is past this block height
+    #[arg(long)]
+    pub until: Option<u64>,
+    /// Stop after printing this many events
+    #[arg(long)]
+    pub count: Option<usize>,
+    /// Stop after this many seconds
+    #[arg(long)]
+    pub timeout: Option<u64>,
+}
+
+pub async fn run(args: TailArgs) -> ExitCode {
+    let indexer = match args.connection.indexer(args.timeout.unwrap_or(u64::MAX)) {
+        Ok(indexer) => indexer,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let address = match indexer.parse_address(&args.connection.address) {
+        Ok(address) => address,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let latest = match indexer.find_contract::<DefaultDB>(&address).await {
+        Ok(latest) => latest,
+        Err(e) => return fail(&e),
+    };
+
+    let (tx, mut rx) = delivery::channel(delivery::DEFAULT_CAPACITY);
+    // replaying from a block already includes the latest action
+    if args.from_block.is_none() {
+        let _ = tx.send(Event::ContractEvent(latest));
+    }
+    let subscription = {
+        let indexer = indexer.clone();
+        let from_block = args.from_block;
+        tokio::spawn(async move {
+            indexer
+                .subscribe_to_contract_from::<DefaultDB>(address, from_block, tx)
+                .await
+        })
+    };
+
+    // the chain head tells when `--until` has passed even if the contract is quiet
+    let chain_head = args.until.and_then(|_| {
+        let (ws_url, _) = args.connection.endpoints().ok()?;
+        Some(ChainHeadTracker::spawn(
+            args.connection.network,
+            ws_url,
+            indexer.metrics().clone(),
+            indexer.shutdown_token(),
+        ))
+    });
+    let mut until_poll = tokio::time::interval(UNTIL_POLL_INTERVAL);
+
+    let mut printed = 0;
+    loop {
+        let event = tokio::select! {
+            event = rx.next() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            _ = until_poll.tick(), if chain_head.is_some() => {
+                let height = chain_head
+                    .as_ref()
+                    .and_then(|chain_head| chain_head.status().block)
+                    .map(|block| block.height);
+                if let (Some(until), Some(height)) = (args.until, height) {
+                    if height > until {
+                        return ExitCode::SUCCESS;
+                    }
+                }
+                continue;
+            }
+        };
+        match event {
+            Event::ContractEvent(event) => {
+                let height = event.transaction.as_ref().map(|t| t.block.height);
+                if let (Some(until), Some(height)) = (args.until, height) {
+                    if height > until {
+                        return ExitCode::SUCCESS;
+                    }
+                }
+                print_event(&event, args.json);
+                printed += 1;
+                if args.count.is_some_and(|count| printed >= count) {
+                    return ExitCode::SUCCESS;
+                }
+            }
+            Event::Lagged { dropped } => {
+                eprintln!("warning: skipped {} events, output is too slow", dropped)
+            }
+            Event::Error(e) => return fail(&e),
+            // the timeout ran out
+            Event::Disconnect | Event::ServerShuttingDown => return ExitCode::SUCCESS,
+            Event::TimeLeft(_) | Event::Alert(_) => {}
+        }
+    }
+
+    // the subscription ended without being asked to, the indexer went away
+    match subscription.await {
+        Ok(Ok(())) => eprintln!("indexer closed the subscription"),
+        Ok(Err(e)) => eprintln!("{}", e),
+        Err(e) => eprintln!("{}", e),
+    }
+    ExitCode::from(EXIT_CONNECTION)
+}
+
+fn fail(e: &SubscriptionError) -> ExitCode {
+    eprintln!("{}", e);
+    match e {
+        SubscriptionError::IndexerUnavailable(_) => ExitCode::from(EXIT_CONNECTION),
+        _ => ExitCode::from(EXIT_USAGE),
+    }
+}
+
+pub fn print_event(event: &ContractEvent, json: bool) {
+    if json {
+        match serde_json::to_string(&EventV1::new(event.sequence, now_millis(), event)) {
+            Ok(line) => println!("{}", line),
+            Err(e) => eprintln!("could not encode event {}", e),
+        }
+        return;
+    }
+    match &event.transaction {
+        Some(transaction) => println!(
+            "{}  block {:<8} {:<7} tx {}",
+            format_timestamp(transaction.block.timestamp),
+            transaction.block.height,
+            event.kind(),
+            transaction.hash
+        ),
+        None => println!("{:<7} {}", event.kind(), event.address),
+    }
+    for line in event.display_state().lines() {
+        println!("    {}", line);
+    }
+}
@@ -0,0 +1,546 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{error, warn};
+
+use crate::{
+    core::{
+        config::SmtpConfig,
+        contract_indexer::IndexedEvent,
+        delivery::EventSink,
+        event_store::{now_millis, EventStore, StoredEvent},
+        persist::{load_json, save_json},
+        webhooks::WebhookRegistry,
+    },
+    domain::{
+        address::ContractAddress,
+        alert::{Alert, AlertCondition, AlertRule, AlertSink, NewAlertRule},
+        ActionKind, ContractEvent, Event, SubscriptionError,
+    },
+};
+
+/// How often inactivity rules are checked.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const ALERT_LOG_SIZE: usize = 500;
+const ALERT_BUFFER: usize = 256;
+
+#[derive(Debug)]
+pub enum AlertError {
+    InvalidSink(String),
+    /// The contract could not be put on the watchlist.
+    Subscription(SubscriptionError),
+}
+
+impl fmt::Display for AlertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertError::InvalidSink(e) => write!(f, "{}", e),
+            AlertError::Subscription(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AlertError {}
+
+impl From<SubscriptionError> for AlertError {
+    fn from(e: SubscriptionError) -> Self {
+        AlertError::Subscription(e)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Saved {
+    next_id: u64,
+    rules: Vec<AlertRule>,
+}
+
+/// What the engine remembers about a contract between events.
+#[derive(Default)]
+struct Tracked {
+    /// Whether the latest call was looked up in the event store, which only happens once per contract so calls
+    /// recorded before a restart count too.
+    loaded: bool,
+    state: Option<Value>,
+    /// Milliseconds since the unix epoch of the latest call, taken from its block when known.
+    last_call_at: Option<u64>,
+    /// Inactivity rules that already fired since the latest call.
+    idle_fired: HashSet<u64>,
+}
+
+#[derive(Default)]
+struct Rules {
+    next_id: u64,
+    rules: BTreeMap<u64, AlertRule>,
+    contracts: HashMap<String, Tracked>,
+    alerts: VecDeque<Alert>,
+}
+
+/// Evaluates alert rules on every event the indexer records and sends firing alerts to their sinks.
+#[derive(Clone)]
+pub struct AlertEngine {
+    rules: Arc<RwLock<Rules>>,
+    fired: broadcast::Sender<Alert>,
+    /// Delivers to webhook sinks, signed and retried like webhook payloads.
+    webhooks: WebhookRegistry,
+    smtp: Option<SmtpConfig>,
+    /// Where the state before an event and the latest call come from when the engine has not seen the contract
+    /// since it started.
+    event_store: EventStore,
+    path: Option<PathBuf>,
+}
+
+impl AlertEngine {
+    /// Loads the rules saved in `data_dir`, if any. Fired alerts are only kept in memory.
+    pub fn open(
+        data_dir: Option<&Path>,
+        smtp: Option<SmtpConfig>,
+        event_store: EventStore,
+        webhooks: WebhookRegistry,
+    ) -> Self {
+        let path = data_dir.map(|dir| dir.join("alerts.json"));
+        let saved: Saved = path.as_deref().map(load_json).unwrap_or_default();
+        let rules = Rules {
+            next_id: saved.next_id,
+            rules: saved.rules.into_iter().map(|rule| (rule.id, rule)).collect(),
+            ..Rules::default()
+        };
+        Self {
+            rules: Arc::new(RwLock::new(rules)),
+            fired: broadcast::channel(ALERT_BUFFER).0,
+            webhooks,
+            smtp,
+            event_store,
+            path,
+        }
+    }
+
+    pub fn add_rule(
+        &self,
+        address: &ContractAddress,
+        request: NewAlertRule,
+    ) -> Result<AlertRule, AlertError> {
+        for sink in &request.sinks {
+            match sink {
+                AlertSink::Log => {}
+                AlertSink::Webhook { url } => match reqwest::Url::parse(url) {
+                    Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {}
+                    _ => {
+                        return Err(AlertError::InvalidSink(format!(
+                            "'{}' is not an http(s) URL",
+                            url
+                        )))
+                    }
+                },
+                AlertSink::Email { to } => {
+                    if self.smtp.is_none() {
+                        return Err(AlertError::InvalidSink(
+                            "Email alerts need COMPACTSEE_SMTP to be set".to_string(),
+                        ));
+                    }
+                    if to.parse::<lettre::Address>().is_err() {
+                        return Err(AlertError::InvalidSink(format!(
+                            "'{}' is not an email address",
+                            to
+                        )));
+                    }
+                }
+            }
+        }
+        let rule = {
+            let mut rules = self.rules.write().unwrap_or_else(PoisonError::into_inner);
+            let id = rules.next_id;
+            rules.next_id += 1;
+            let rule = AlertRule {
+                id,
+                address: address.to_string(),
+                condition: request.condition,
+                sinks: request.sinks,
+                created_at: now_millis(),
+            };
+            rules.rules.insert(id, rule.clone());
+            rule
+        };
+        self.save();
+        Ok(rule)
+    }
+
+    /// Returns `false` if there is no rule with this id.
+    pub fn remove_rule(&self, id: u64) -> bool {
+        let removed = self
+            .rules
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .rules
+            .remove(&id)
+            .is_some();
+        if removed {
+            self.save();
+        }
+        removed
+    }
+
+    pub fn rules(&self) -> Vec<AlertRule> {
+        self.rules
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .rules
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Most recent first.
+    pub fn alerts(&self) -> Vec<Alert> {
+        self.rules
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .alerts
+            .iter()
+            .rev()
+            .cloned()
+            .collect()
+    }
+
+    /// Alerts fired from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Alert> {
+        self.fired.subscribe()
+    }
+
+    /// Sends alerts on one contract to a client until it goes away.
+    pub async fn forward_to(&self, address: &ContractAddress, tx: EventSink) {
+        let address = address.to_string();
+        let mut fired = self.subscribe();
+        loop {
+            match fired.recv().await {
+                Ok(alert) if alert.address == address => {
+                    if tx.send(Event::Alert(alert)).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Skipped {} alerts for a client of {}", skipped, address)
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let saved = {
+            let rules = self.rules.read().unwrap_or_else(PoisonError::into_inner);
+            Saved {
+                next_id: rules.next_id,
+                rules: rules.rules.values().cloned().collect(),
+            }
+        };
+        if let Err(e) = save_json(path, &saved) {
+            error!("Could not save alert rules {}", e);
+        }
+    }
+
+    /// Evaluates rules on the indexer's feed and checks inactivity rules periodically, for as long as the server runs.
+    pub fn spawn(&self, mut feed: broadcast::Receiver<IndexedEvent>) {
+        let engine = self.clone();
+        tokio::spawn(async move {
+            let mut idle_check = tokio::time::interval(IDLE_CHECK_INTERVAL);
+            loop {
+                tokio::select! {
+                    received = feed.recv() => match received {
+                        Ok(indexed) => {
+                            for (rule, alert) in engine.evaluate(&indexed.address, &indexed.stored.event) {
+                                engine.fire(rule, alert);
+                            }
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            error!("Alert engine fell behind, {} events not evaluated", skipped)
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = idle_check.tick() => {
+                        for (rule, alert) in engine.check_idle(now_millis()) {
+                            engine.fire(rule, alert);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    fn evaluate(&self, contract: &ContractAddress, event: &ContractEvent) -> Vec<(AlertRule, Alert)> {
+        let address = contract.to_string();
+        let mut rules = self.rules.write().unwrap_or_else(PoisonError::into_inner);
+        let Rules {
+            rules, contracts, ..
+        } = &mut *rules;
+        let tracked = self.tracked(contracts, contract);
+        if tracked.state.is_none() {
+            tracked.state = event
+                .sequence
+                .and_then(|sequence| self.event_store.before(contract, sequence))
+                .and_then(|stored| stored.event.decoded_state);
+        }
+        let transaction_hash = event.transaction.as_ref().map(|t| t.hash.clone());
+
+        let fired = rules
+            .values()
+            .filter(|rule| rule.address == address)
+            .filter_map(|rule| {
+                let message = rule.condition.fires(tracked.state.as_ref(), event)?;
+                Some((
+                    rule.clone(),
+                    Alert {
+                        rule_id: rule.id,
+                        address: address.clone(),
+                        condition: rule.condition.clone(),
+                        message,
+                        transaction_hash: transaction_hash.clone(),
+                        fired_at: now_millis(),
+                    },
+                ))
+            })
+            .collect();
+
+        if event.decoded_state.is_some() {
+            tracked.state = event.decoded_state.clone();
+        }
+        if event.kind() == ActionKind::Call {
+            let called_at = event
+                .transaction
+                .as_ref()
+                .map(|t| t.block.timestamp)
+                .unwrap_or_else(now_millis);
+            tracked.last_call_at = Some(tracked.last_call_at.unwrap_or_default().max(called_at));
+            tracked.idle_fired.clear();
+        }
+        fired
+    }
+
+    /// What the engine knows about the contract, starting from its latest recorded call.
+    fn tracked<'a>(
+        &self,
+        contracts: &'a mut HashMap<String, Tracked>,
+        contract: &ContractAddress,
+    ) -> &'a mut Tracked {
+        let tracked = contracts.entry(contract.to_string()).or_default();
+        if !tracked.loaded {
+            tracked.loaded = true;
+            tracked.last_call_at = self
+                .event_store
+                .latest_call(contract)
+                .map(|stored| called_at(&stored));
+        }
+        tracked
+    }
+
+    fn check_idle(&self, now: u64) -> Vec<(AlertRule, Alert)> {
+        let mut rules = self.rules.write().unwrap_or_else(PoisonError::into_inner);
+        let Rules {
+            rules, contracts, ..
+        } = &mut *rules;
+        let mut fired = Vec::new();
+        for rule in rules.values() {
+            let AlertCondition::NoCallsFor { seconds } = rule.condition else {
+                continue;
+            };
+            let tracked = match ContractAddress::parse(&rule.address) {
+                Ok(contract) => self.tracked(contracts, &contract),
+                Err(_) => contracts.entry(rule.address.clone()).or_default(),
+            };
+            // without any call seen yet, count from when the rule was created
+            let since = tracked.last_call_at.unwrap_or(rule.created_at).max(rule.created_at);
+            let idle_secs = now.saturating_sub(since) / 1000;
+            if idle_secs >= seconds && tracked.idle_fired.insert(rule.id) {
+                fired.push((
+                    rule.clone(),
+                    Alert {
+                        rule_id: rule.id,
+                        address: rule.address.clone(),
+                        condition: rule.condition.clone(),
+                        message: format!("No calls for {}s", idle_secs),
+                        transaction_hash: None,
+                        fired_at: now,
+                    },
+                ));
+            }
+        }
+        fired
+    }
+
+    fn fire(&self, rule: AlertRule, alert: Alert) {
+        {
+            let mut rules = self.rules.write().unwrap_or_else(PoisonError::into_inner);
+            rules.alerts.push_back(alert.clone());
+            if rules.alerts.len() > ALERT_LOG_SIZE {
+                rules.alerts.pop_front();
+            }
+        }
+        // no receivers just means no browser is looking at the contract
+        let _ = self.fired.send(alert.clone());
+
+        for sink in rule.sinks {
+            let engine = self.clone();
+            let alert = alert.clone();
+            tokio::spawn(async move {
+                if let Err(e) = engine.deliver(&sink, &alert).await {
+                    error!("Could not send alert {} to {} {}", alert.rule_id, sink, e);
+                }
+            });
+        }
+    }
+
+    async fn deliver(&self, sink: &AlertSink, alert: &Alert) -> Result<(), String> {
+        match sink {
+            AlertSink::Log => {
+                warn!("Alert on {}: {}", alert.address, alert.message);
+                Ok(())
+            }
+            AlertSink::Webhook { url } => {
+                self.webhooks.send_alert(url, alert);
+                Ok(())
+            }
+            AlertSink::Email { to } => {
+                let smtp = self.smtp.as_ref().ok_or("email is not configured")?;
+                let email = lettre::Message::builder()
+                    .from(smtp.from.parse().map_err(|e| format!("{}", e))?)
+                    .to(to.parse().map_err(|e| format!("{}", e))?)
+                    .subject(format!("[compactsee] {}", alert.condition))
+                    .body(format!(
+                        "{}\n\nContract: {}\nTransaction: {}\n",
+                        alert.message,
+                        alert.address,
+                        alert.transaction_hash.as_deref().unwrap_or("-")
+                    ))
+                    .map_err(|e| e.to_string())?;
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+                    .port(smtp.port)
+                    .build()
+                    .send(email)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+/// When a recorded call happened, taken from its block when known.
+fn called_at(stored: &StoredEvent) -> u64 {
+    stored
+        .event
+        .transaction
+        .as_ref()
+        .map_or(stored.received_at, |t| t.block.timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        core::secret_box::SecretBox,
+        domain::{path::JsonPath, predicate::Condition, predicate::Predicate},
+    };
+
+    const ADDRESS: &str = "00cc2f4e37bb554c344c04aff7ad746d8df129a4985d3908b509712b4cd721f163";
+
+    fn call(state: Value) -> ContractEvent {
+        ContractEvent::test_call()
+            .with_raw_state(&state.to_string())
+            .with_address(ADDRESS)
+            .with_state(state)
+    }
+
+    /// Appends to the store the way the indexer does, so the event carries its sequence.
+    fn record(store: &EventStore, address: &ContractAddress, state: Value) -> ContractEvent {
+        let mut event = call(state);
+        event.sequence = Some(store.append(address, &event, 0).sequence());
+        event
+    }
+
+    fn engine_with(store: &EventStore, condition: AlertCondition) -> AlertEngine {
+        let webhooks = WebhookRegistry::open(None, SecretBox::generate());
+        let engine = AlertEngine::open(None, None, store.clone(), webhooks);
+        engine
+            .add_rule(
+                &ContractAddress::parse(ADDRESS).unwrap(),
+                NewAlertRule {
+                    address: ADDRESS.to_string(),
+                    condition,
+                    sinks: Vec::new(),
+                },
+            )
+            .unwrap();
+        engine
+    }
+
+    #[test]
+    fn compares_with_the_recorded_state_after_a_restart() {
+        let store = EventStore::in_memory();
+        let address = ContractAddress::parse(ADDRESS).unwrap();
+        record(&store, &address, json!([5]));
+        let decrease = record(&store, &address, json!([3]));
+
+        // a fresh engine has not seen the contract, the store has
+        let engine = engine_with(
+            &store,
+            AlertCondition::Decreased {
+                path: JsonPath::parse("$[0]").unwrap(),
+            },
+        );
+        let fired = engine.evaluate(&address, &decrease);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].1.message, "$[0] decreased from 5 to 3");
+    }
+
+    #[test]
+    fn counts_inactivity_from_the_recorded_call_after_a_restart() {
+        let store = EventStore::in_memory();
+        let address = ContractAddress::parse(ADDRESS).unwrap();
+        // recorded without a block, so it counts from when it was received
+        record(&store, &address, json!([1]));
+        let called_at = now_millis();
+
+        // the rule is older than the engine, whose first check runs right away
+        let engine = engine_with(&store, AlertCondition::NoCallsFor { seconds: 60 });
+        for rule in engine.rules.write().unwrap().rules.values_mut() {
+            rule.created_at = 0;
+        }
+        assert!(engine.check_idle(called_at + 30_000).is_empty());
+        assert_eq!(engine.check_idle(called_at + 61_000).len(), 1);
+    }
+
+    #[test]
+    fn matches_fires_once_until_the_predicate_stops_holding() {
+        let store = EventStore::in_memory();
+        let address = ContractAddress::parse(ADDRESS).unwrap();
+        let engine = engine_with(
+            &store,
+            AlertCondition::Matches(Predicate::new(
+                JsonPath::parse("$[0]").unwrap(),
+                Condition::GreaterThan(10.0),
+            )),
+        );
+        let fired: Vec<usize> = [5, 11, 12, 13, 4, 20]
+            .into_iter()
+            .map(|n| {
+                let event = record(&store, &address, json!([n]));
+                engine.evaluate(&address, &event).len()
+            })
+            .collect();
+        assert_eq!(fired, [0, 1, 0, 0, 0, 1]);
+    }
+}
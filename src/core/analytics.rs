@@ -0,0 +1,109 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{
+    core::event_store::EventStore,
+    domain::{
+        address::ContractAddress,
+        analytics::{AnalyticsBuilder, ContractAnalytics, Interval},
+    },
+};
+
+/// Analytics of the contracts asked about, kept between requests so each one only aggregates the events
+/// recorded since the one before.
+#[derive(Clone)]
+pub struct Analytics {
+    event_store: EventStore,
+    contracts: Arc<Mutex<HashMap<(String, Interval), SharedAggregate>>>,
+}
+
+type SharedAggregate = Arc<Mutex<Aggregate>>;
+
+struct Aggregate {
+    builder: AnalyticsBuilder,
+    /// Sequence number of the first event not added yet.
+    next_sequence: u64,
+}
+
+impl Analytics {
+    pub fn new(event_store: EventStore) -> Self {
+        Self {
+            event_store,
+            contracts: Arc::default(),
+        }
+    }
+
+    /// Catches up with the contract's history on a blocking thread, the first time from the start.
+    pub async fn contract(
+        &self,
+        address: ContractAddress,
+        interval: Interval,
+    ) -> io::Result<ContractAnalytics> {
+        let aggregate = self
+            .contracts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry((address.hex().to_string(), interval))
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(Aggregate {
+                    builder: AnalyticsBuilder::new(interval),
+                    next_sequence: 0,
+                }))
+            })
+            .clone();
+        let event_store = self.event_store.clone();
+        tokio::task::spawn_blocking(move || {
+            // held while catching up, so concurrent requests do not add the same events twice
+            let mut aggregate = aggregate.lock().unwrap_or_else(PoisonError::into_inner);
+            let (events, _) = event_store.page(&address, aggregate.next_sequence, usize::MAX)?;
+            for stored in events {
+                aggregate.builder.add(&stored.event);
+                aggregate.next_sequence = stored.sequence + 1;
+            }
+            Ok(aggregate.builder.finish(address.to_string()))
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::ContractEvent;
+
+    const ADDRESS: &str = "00cc2f4e37bb554c344c04aff7ad746d8df129a4985d3908b509712b4cd721f163";
+
+    fn event(height: u64) -> ContractEvent {
+        ContractEvent::test_call()
+            .at_height(height)
+            .with_raw_state(&format!("{:02x}", height))
+            .with_address(ADDRESS)
+    }
+
+    #[test]
+    fn only_adds_events_recorded_since_the_last_request() {
+        let address = ContractAddress::parse(ADDRESS).unwrap();
+        let event_store = EventStore::in_memory();
+        let analytics = Analytics::new(event_store.clone());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let contract = |interval| {
+            runtime
+                .block_on(analytics.contract(address.clone(), interval))
+                .unwrap()
+        };
+
+        assert_eq!(contract(Interval::Hour).events, 0);
+        event_store.append(&address, &event(1), 0);
+        event_store.append(&address, &event(2), 0);
+        assert_eq!(contract(Interval::Hour).events, 2);
+        event_store.append(&address, &event(3), 0);
+        let hourly = contract(Interval::Hour);
+        assert_eq!((hourly.events, hourly.blocks.len()), (3, 3));
+        assert_eq!(hourly.last_event_at, Some(3000));
+        assert_eq!(contract(Interval::Day).events, 3);
+    }
+}
@@ -0,0 +1,106 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use crate::{
+    core::{alerts::AlertError, auth::AuthError, webhooks::WebhookError},
+    domain::{address::AddressError, limit::LimitExceeded, SubscriptionError},
+};
+
+pub mod v1;
+
+/// Public JSON API. Its schemas are versioned separately from the types the UI uses.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(ErrorBody {
+                error: self.message,
+            }),
+        )
+            .into_response()
+    }
+}
+
+impl From<AddressError> for ApiError {
+    fn from(e: AddressError) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, e.to_string())
+    }
+}
+
+impl From<SubscriptionError> for ApiError {
+    fn from(e: SubscriptionError) -> Self {
+        let status = match e {
+            SubscriptionError::InvalidAddress(_) => StatusCode::BAD_REQUEST,
+            SubscriptionError::ContractNotFound { .. } => StatusCode::NOT_FOUND,
+            SubscriptionError::IndexerUnavailable(_) => StatusCode::BAD_GATEWAY,
+            SubscriptionError::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+            SubscriptionError::LimitExceeded(LimitExceeded::OriginNotAllowed { .. }) => {
+                StatusCode::FORBIDDEN
+            }
+            SubscriptionError::LimitExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+        };
+        ApiError::new(status, e.to_string())
+    }
+}
+
+impl From<WebhookError> for ApiError {
+    fn from(e: WebhookError) -> Self {
+        match e {
+            WebhookError::InvalidUrl(_) => ApiError::new(StatusCode::BAD_REQUEST, e.to_string()),
+            WebhookError::Subscription(e) => e.into(),
+        }
+    }
+}
+
+impl From<AlertError> for ApiError {
+    fn from(e: AlertError) -> Self {
+        match e {
+            AlertError::InvalidSink(_) => ApiError::new(StatusCode::BAD_REQUEST, e.to_string()),
+            AlertError::Subscription(e) => e.into(),
+        }
+    }
+}
+
+impl From<AuthError> for ApiError {
+    fn from(e: AuthError) -> Self {
+        let status = match e {
+            AuthError::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            AuthError::TooManyAttempts => StatusCode::TOO_MANY_REQUESTS,
+            AuthError::UsernameTaken(_) | AuthError::LastAccount => StatusCode::CONFLICT,
+            AuthError::UnknownAccount(_) => StatusCode::NOT_FOUND,
+            AuthError::Hash(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AuthError::InvalidName(_) | AuthError::PasswordTooShort => StatusCode::BAD_REQUEST,
+        };
+        ApiError::new(status, e.to_string())
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(e: std::io::Error) -> Self {
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
@@ -0,0 +1,477 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{delete, get},
+    Json, Router,
+};
+use futures::{Stream, StreamExt};
+use midnight_node_ledger_helpers::DefaultDB;
+use serde::{Deserialize, Serialize};
+use tracing::{info_span, Instrument};
+
+use crate::{
+    core::{
+        api::ApiError,
+        app_state::AppState,
+        delivery,
+        event_store::{now_millis, StoredEvent},
+        export,
+        metrics::Subscriber,
+    },
+    domain::{
+        alert::{Alert, AlertRule, NewAlertRule},
+        analytics::{ContractAnalytics, Interval},
+        auth::{ApiKey, CreatedApiKey, NewApiKey},
+        connection::SessionOrigin,
+        export::ExportFormat,
+        network::Network,
+        webhook::{DeadLetter, DeliveryAttempt, NewWebhook, WatchedContract, Webhook},
+        ContractEvent, Event, SubscriptionError,
+    },
+};
+
+/// Sent by an SSE client that reconnects, with the `id` of the last event it received.
+const LAST_EVENT_ID: &str = "last-event-id";
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/contracts/{address}/events", get(contract_events))
+        .route("/contracts/{address}/state", get(contract_state))
+        .route("/contracts/{address}/stream", get(contract_stream))
+        .route("/contracts/{address}/export", get(contract_export))
+        .route("/contracts/{address}/analytics", get(contract_analytics))
+        .route("/watchlist", get(list_watchlist).post(watch_contract))
+        .route("/watchlist/{address}", delete(unwatch_contract))
+        .route("/webhooks", get(list_webhooks).post(register_webhook))
+        .route("/webhooks/{id}", delete(remove_webhook))
+        .route("/webhooks/deliveries", get(webhook_deliveries))
+        .route("/webhooks/dead-letters", get(webhook_dead_letters))
+        .route("/alerts", get(list_alerts))
+        .route("/alerts/rules", get(list_alert_rules).post(add_alert_rule))
+        .route("/alerts/rules/{id}", delete(remove_alert_rule))
+        .route("/keys", get(list_api_keys).post(create_api_key))
+        .route("/keys/{id}", delete(revoke_api_key))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventV1 {
+    /// Position in the contract's history, absent when looking up a contract whose latest action the server
+    /// has not recorded yet.
+    pub sequence: Option<u64>,
+    pub received_at: u64,
+    pub kind: String,
+    pub address: String,
+    pub transaction_hash: Option<String>,
+    pub block_height: Option<u64>,
+    pub block_hash: Option<String>,
+    pub block_timestamp: Option<u64>,
+    /// Circuit a call ran.
+    pub entry_point: Option<String>,
+    pub state_hex: String,
+    pub decoded_state: Option<serde_json::Value>,
+}
+
+impl EventV1 {
+    pub fn new(sequence: Option<u64>, received_at: u64, event: &ContractEvent) -> Self {
+        let transaction = event.transaction.as_ref();
+        Self {
+            sequence,
+            received_at,
+            kind: event.kind().to_string(),
+            address: event.address.clone(),
+            transaction_hash: transaction.map(|t| t.hash.clone()),
+            block_height: transaction.map(|t| t.block.height),
+            block_hash: transaction.map(|t| t.block.hash.clone()),
+            block_timestamp: transaction.map(|t| t.block.timestamp),
+            entry_point: event.entry_point_name(),
+            state_hex: event.state.clone(),
+            decoded_state: event.decoded_state.clone(),
+        }
+    }
+}
+
+impl From<&StoredEvent> for EventV1 {
+    fn from(stored: &StoredEvent) -> Self {
+        EventV1::new(Some(stored.sequence), stored.received_at, &stored.event)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventsPageV1 {
+    pub address: String,
+    /// Number of events recorded for the contract, including ones no longer held in memory.
+    pub total: u64,
+    pub offset: u64,
+    pub limit: usize,
+    pub events: Vec<EventV1>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StateV1 {
+    pub network: Network,
+    pub event: EventV1,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LaggedV1 {
+    pub dropped: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StreamErrorV1 {
+    pub error: String,
+}
+
+/// Body of every webhook POST.
+#[derive(Debug, Serialize)]
+pub struct WebhookPayloadV1 {
+    pub webhook_id: u64,
+    pub event: EventV1,
+}
+
+/// A fired alert, as shown to SSE clients and posted to alert webhooks.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertV1 {
+    pub rule_id: u64,
+    pub address: String,
+    pub condition: String,
+    pub message: String,
+    pub transaction_hash: Option<String>,
+    pub fired_at: u64,
+}
+
+impl From<&Alert> for AlertV1 {
+    fn from(alert: &Alert) -> Self {
+        Self {
+            rule_id: alert.rule_id,
+            address: alert.address.clone(),
+            condition: alert.condition.to_string(),
+            message: alert.message.clone(),
+            transaction_hash: alert.transaction_hash.clone(),
+            fired_at: alert.fired_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WatchRequestV1 {
+    pub address: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PageParams {
+    #[serde(default)]
+    offset: u64,
+    limit: Option<usize>,
+}
+
+async fn contract_events(
+    State(app_state): State<AppState>,
+    Path(address): Path<String>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<EventsPageV1>, ApiError> {
+    let address = app_state.contract_indexer.parse_address(&address)?;
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let store = app_state.contract_indexer.event_store().clone();
+    let paged = address.clone();
+    let (events, total) =
+        tokio::task::spawn_blocking(move || store.page(&paged, params.offset, limit))
+            .await
+            .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))??;
+    Ok(Json(EventsPageV1 {
+        address: address.to_string(),
+        total,
+        offset: params.offset,
+        limit,
+        events: events.iter().map(EventV1::from).collect(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    format: Option<String>,
+}
+
+async fn contract_export(
+    State(app_state): State<AppState>,
+    Path(address): Path<String>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, ApiError> {
+    let address = app_state.contract_indexer.parse_address(&address)?;
+    let format = match params.format {
+        Some(format) => format
+            .parse::<ExportFormat>()
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?,
+        None => ExportFormat::Json,
+    };
+    let filename = format!("{}.{}", address.hex(), format.extension());
+    let chunks = export::export(
+        app_state.contract_indexer.event_store().clone(),
+        address,
+        format,
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsParams {
+    interval: Option<String>,
+}
+
+async fn contract_analytics(
+    State(app_state): State<AppState>,
+    Path(address): Path<String>,
+    Query(params): Query<AnalyticsParams>,
+) -> Result<Json<ContractAnalytics>, ApiError> {
+    let address = app_state.contract_indexer.parse_address(&address)?;
+    let interval = match params.interval {
+        Some(interval) => interval
+            .parse::<Interval>()
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?,
+        None => Interval::Hour,
+    };
+    let analytics = app_state
+        .analytics
+        .contract(address, interval)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(analytics))
+}
+
+async fn contract_state(
+    State(app_state): State<AppState>,
+    Path(address): Path<String>,
+) -> Result<Json<StateV1>, ApiError> {
+    let indexer = &app_state.contract_indexer;
+    let address = indexer.parse_address(&address)?;
+    let latest = indexer.find_contract::<DefaultDB>(&address).await?;
+    Ok(Json(StateV1 {
+        network: indexer.network(),
+        event: EventV1::new(latest.sequence, now_millis(), &latest),
+    }))
+}
+
+async fn contract_stream(
+    State(app_state): State<AppState>,
+    Path(address): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, ApiError> {
+    let indexer = app_state.contract_indexer.clone();
+    let address = indexer.parse_address(&address)?;
+    let limits = &app_state.limits;
+    let client = limits.client_ip(&headers, peer);
+    limits
+        .check_origin(&headers)
+        .map_err(SubscriptionError::LimitExceeded)?;
+    let permits = limits
+        .open_session(client)
+        .and_then(|session| Ok((session, limits.subscribe(client)?)))
+        .map_err(SubscriptionError::LimitExceeded)?;
+    let latest = indexer.find_contract::<DefaultDB>(&address).await?;
+    // browsers' EventSource reconnects on its own, sending the id of the last event it saw
+    if headers.contains_key(LAST_EVENT_ID) {
+        indexer
+            .metrics()
+            .reconnected(indexer.network(), Subscriber::Api);
+    }
+
+    let (tx, rx) = delivery::channel(delivery::DEFAULT_CAPACITY);
+    let _ = tx.send(Event::ContractEvent(latest));
+    let session = indexer
+        .connections()
+        .session(SessionOrigin::Api, indexer.network());
+    session.set_address(&address);
+    let delivered = session.clone();
+    let active = indexer.metrics().session(SessionOrigin::Api);
+    let alerts = app_state.alerts.clone();
+    let span = info_span!(
+        "session",
+        id = session.id(),
+        origin = %SessionOrigin::Api,
+        network = %indexer.network(),
+        address = %address,
+    );
+    let task = async move {
+        let _permits = permits;
+        let _active = active;
+        let forward_alerts = alerts.forward_to(&address, tx.clone());
+        let disconnect = tx.clone();
+        tokio::select! {
+            result = indexer.subscribe_to_contract::<DefaultDB>(address.clone(), tx) => {
+                if let Err(e) = result {
+                    session.failed(&e);
+                }
+            }
+            _ = forward_alerts => {}
+            _ = session.terminated() => {
+                let _ = disconnect.send(Event::Disconnect);
+            }
+        }
+    };
+    tokio::spawn(task.instrument(span));
+
+    let metrics = app_state.contract_indexer.metrics().clone();
+    let stream = rx
+        .inspect(move |event| match event {
+            Event::ContractEvent(_) => delivered.delivered(),
+            Event::Lagged { dropped } => metrics.events_dropped(*dropped),
+            _ => {}
+        })
+        .filter_map(|event| async move {
+            let sse_event = match event {
+                Event::ContractEvent(event) => event
+                    .sequence
+                    .map_or_else(SseEvent::default, |sequence| {
+                        SseEvent::default().id(sequence.to_string())
+                    })
+                    .event("contract_action")
+                    .json_data(EventV1::new(event.sequence, now_millis(), &event)),
+                Event::Lagged { dropped } => SseEvent::default()
+                    .event("lagged")
+                    .json_data(LaggedV1 { dropped }),
+                Event::Error(e) => SseEvent::default()
+                    .event("error")
+                    .json_data(StreamErrorV1 {
+                        error: e.to_string(),
+                    }),
+                Event::Alert(alert) => SseEvent::default()
+                    .event("alert")
+                    .json_data(AlertV1::from(&alert)),
+                Event::Disconnect => Ok(SseEvent::default().event("disconnect").data("")),
+                Event::ServerShuttingDown => {
+                    Ok(SseEvent::default().event("shutting_down").data(""))
+                }
+                Event::TimeLeft(_) => return None,
+            };
+            sse_event.ok().map(Ok)
+        });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn list_watchlist(State(app_state): State<AppState>) -> Json<Vec<WatchedContract>> {
+    Json(app_state.watchlist.list())
+}
+
+async fn watch_contract(
+    State(app_state): State<AppState>,
+    Json(request): Json<WatchRequestV1>,
+) -> Result<StatusCode, ApiError> {
+    app_state.watch(&request.address).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn unwatch_contract(
+    State(app_state): State<AppState>,
+    Path(address): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let address = app_state.contract_indexer.parse_address(&address)?;
+    if app_state.watchlist.remove(&address) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::new(StatusCode::NOT_FOUND, "Contract is not watched."))
+    }
+}
+
+async fn list_webhooks(State(app_state): State<AppState>) -> Json<Vec<Webhook>> {
+    Json(app_state.webhooks.list())
+}
+
+async fn register_webhook(
+    State(app_state): State<AppState>,
+    Json(request): Json<NewWebhook>,
+) -> Result<(StatusCode, Json<Webhook>), ApiError> {
+    let webhook = app_state.add_webhook(request).await?;
+    Ok((StatusCode::CREATED, Json(webhook)))
+}
+
+async fn remove_webhook(
+    State(app_state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    if app_state.webhooks.remove(id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::new(StatusCode::NOT_FOUND, "No webhook with this id."))
+    }
+}
+
+async fn webhook_deliveries(State(app_state): State<AppState>) -> Json<Vec<DeliveryAttempt>> {
+    Json(app_state.webhooks.deliveries())
+}
+
+async fn webhook_dead_letters(State(app_state): State<AppState>) -> Json<Vec<DeadLetter>> {
+    Json(app_state.webhooks.dead_letters())
+}
+
+async fn list_alerts(State(app_state): State<AppState>) -> Json<Vec<AlertV1>> {
+    Json(app_state.alerts.alerts().iter().map(AlertV1::from).collect())
+}
+
+async fn list_alert_rules(State(app_state): State<AppState>) -> Json<Vec<AlertRule>> {
+    Json(app_state.alerts.rules())
+}
+
+async fn add_alert_rule(
+    State(app_state): State<AppState>,
+    Json(request): Json<NewAlertRule>,
+) -> Result<(StatusCode, Json<AlertRule>), ApiError> {
+    let rule = app_state.add_alert_rule(request).await?;
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+async fn remove_alert_rule(
+    State(app_state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    if app_state.alerts.remove_rule(id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::new(StatusCode::NOT_FOUND, "No alert rule with this id."))
+    }
+}
+
+async fn list_api_keys(State(app_state): State<AppState>) -> Json<Vec<ApiKey>> {
+    Json(app_state.auth.keys())
+}
+
+async fn create_api_key(
+    State(app_state): State<AppState>,
+    Json(request): Json<NewApiKey>,
+) -> Result<(StatusCode, Json<CreatedApiKey>), ApiError> {
+    let key = app_state.auth.create_key(request)?;
+    Ok((StatusCode::CREATED, Json(key)))
+}
+
+async fn revoke_api_key(
+    State(app_state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    if app_state.auth.revoke_key(id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::new(StatusCode::NOT_FOUND, "No API key with this id."))
+    }
+}
@@ -1,9 +1,74 @@
-use crate::core::contract_indexer::ContractIndexer;
+use crate::{
+    core::{
+        alerts::{AlertEngine, AlertError},
+        analytics::Analytics,
+        auth::Auth,
+        chain_head::ChainHeadTracker,
+        contract_indexer::ContractIndexer,
+        health::HealthChecker,
+        limits::ConnectionLimits,
+        watchlist::Watchlist,
+        webhooks::{WebhookError, WebhookRegistry},
+    },
+    domain::{
+        address::ContractAddress,
+        alert::{AlertRule, NewAlertRule},
+        webhook::{NewWebhook, Webhook},
+        SubscriptionError,
+    },
+};
 use axum::extract::FromRef;
-use leptos::config::LeptosOptions;
+use leptos::{
+    config::LeptosOptions,
+    prelude::{use_context, ServerFnError},
+};
+use midnight_node_ledger_helpers::DefaultDB;
 
 #[derive(Clone, FromRef)]
 pub struct AppState {
     pub leptos_options: LeptosOptions,
     pub contract_indexer: ContractIndexer,
+    pub chain_head: ChainHeadTracker,
+    pub watchlist: Watchlist,
+    pub webhooks: WebhookRegistry,
+    pub alerts: AlertEngine,
+    pub analytics: Analytics,
+    pub health: HealthChecker,
+    pub auth: Auth,
+    pub limits: ConnectionLimits,
+}
+
+impl AppState {
+    /// Puts a contract on the watchlist after making sure it exists.
+    pub async fn watch(&self, address: &str) -> Result<ContractAddress, SubscriptionError> {
+        let address = self
+            .contract_indexer
+            .parse_address(address)
+            .map_err(SubscriptionError::InvalidAddress)?;
+        if !self.watchlist.contains(&address) {
+            self.contract_indexer
+                .find_contract::<DefaultDB>(&address)
+                .await?;
+            self.watchlist.add(address.clone());
+        }
+        Ok(address)
+    }
+
+    /// Registers a webhook, watching its contract if nobody did yet.
+    pub async fn add_webhook(&self, request: NewWebhook) -> Result<Webhook, WebhookError> {
+        let address = self.watch(&request.address).await?;
+        self.webhooks.register(&address, request)
+    }
+
+    /// Adds an alert rule, watching its contract so inactivity is noticed even without a browser.
+    pub async fn add_alert_rule(&self, request: NewAlertRule) -> Result<AlertRule, AlertError> {
+        let address = self.watch(&request.address).await?;
+        self.alerts.add_rule(&address, request)
+    }
+}
+
+/// The server's state inside a server function, where it is provided as context.
+pub fn app_state() -> Result<AppState, ServerFnError> {
+    use_context::<AppState>()
+        .ok_or_else(|| ServerFnError::ServerError("Could not extract app state".to_string()))
 }
@@ -0,0 +1,771 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    hash::Hash,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+
+use crate::{
+    core::{
+        api::ApiError,
+        event_store::now_millis,
+        persist::{load_json, save_json},
+    },
+    domain::auth::{Account, ApiKey, CreatedApiKey, NewApiKey, Scope},
+};
+
+const KEY_PREFIX: &str = "cs_";
+/// Characters of a secret kept in the clear so keys can be told apart.
+const SHOWN_PREFIX_LEN: usize = 10;
+const SESSION_COOKIE: &str = "compactsee_session";
+const SESSION_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+const MIN_PASSWORD_LEN: usize = 8;
+/// Failed sign-ins are forgotten after this long.
+const LOGIN_WINDOW: Duration = Duration::from_secs(15 * 60);
+/// Failed sign-ins to one account from one client within [`LOGIN_WINDOW`] before it refuses more. Not for the
+/// account as a whole, or anyone could lock the admins out by guessing wrong on purpose.
+const LOGIN_ATTEMPTS_PER_ACCOUNT: usize = 5;
+/// Failed sign-ins within [`LOGIN_WINDOW`] before a client is refused, for any account.
+const LOGIN_ATTEMPTS_PER_CLIENT: usize = 20;
+
+#[derive(Debug)]
+pub enum AuthError {
+    InvalidName(String),
+    PasswordTooShort,
+    UsernameTaken(String),
+    UnknownAccount(String),
+    /// Removing the account would leave nobody able to sign in.
+    LastAccount,
+    InvalidCredentials,
+    TooManyAttempts,
+    Hash(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::InvalidName(name) => write!(
+                f,
+                "'{}' is not a valid name, use letters, digits, '-' and '_'",
+                name
+            ),
+            AuthError::PasswordTooShort => {
+                write!(f, "passwords need at least {} characters", MIN_PASSWORD_LEN)
+            }
+            AuthError::UsernameTaken(name) => write!(f, "there already is an account '{}'", name),
+            AuthError::UnknownAccount(name) => write!(f, "there is no account '{}'", name),
+            AuthError::LastAccount => write!(f, "the last account cannot be removed"),
+            AuthError::InvalidCredentials => write!(f, "wrong username or password"),
+            AuthError::TooManyAttempts => write!(f, "too many failed sign-ins, try again later"),
+            AuthError::Hash(e) => write!(f, "could not hash the password: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredKey {
+    key: ApiKey,
+    /// Hex SHA-256 of the secret, the secret itself is never stored.
+    hash: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SavedKeys {
+    next_id: u64,
+    keys: Vec<StoredKey>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredAccount {
+    account: Account,
+    /// Argon2 hash in PHC string format.
+    password_hash: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SavedAccounts {
+    accounts: Vec<StoredAccount>,
+}
+
+struct LoginSession {
+    username: String,
+    expires: Instant,
+}
+
+#[derive(Default)]
+struct Credentials {
+    next_key_id: u64,
+    keys: BTreeMap<u64, StoredKey>,
+    accounts: BTreeMap<String, StoredAccount>,
+    /// Admin page sign-ins by cookie token, only kept in memory.
+    sessions: HashMap<String, LoginSession>,
+}
+
+/// Verified against when there is no such account, so signing in takes as long as for a wrong password and does
+/// not tell which usernames exist.
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    Argon2::default()
+        .hash_password(random_token().as_bytes(), &SaltString::generate(&mut OsRng))
+        .map(|hash| hash.to_string())
+        .unwrap_or_default()
+});
+
+/// Failed sign-ins within the last [`LOGIN_WINDOW`], oldest first.
+#[derive(Default)]
+struct Failures {
+    by_client: HashMap<IpAddr, VecDeque<Instant>>,
+    by_account: HashMap<(String, IpAddr), VecDeque<Instant>>,
+}
+
+impl Failures {
+    fn throttled(&mut self, client: IpAddr, username: &str) -> bool {
+        let now = Instant::now();
+        forget_old(&mut self.by_client, now);
+        forget_old(&mut self.by_account, now);
+        self.by_client
+            .get(&client)
+            .is_some_and(|failures| failures.len() >= LOGIN_ATTEMPTS_PER_CLIENT)
+            || self
+                .by_account
+                .get(&(username.to_string(), client))
+                .is_some_and(|failures| failures.len() >= LOGIN_ATTEMPTS_PER_ACCOUNT)
+    }
+
+    fn failed(&mut self, client: IpAddr, username: &str) {
+        let now = Instant::now();
+        self.by_client.entry(client).or_default().push_back(now);
+        self.by_account
+            .entry((username.to_string(), client))
+            .or_default()
+            .push_back(now);
+    }
+}
+
+/// Drops failures older than [`LOGIN_WINDOW`], and whoever has none left so the maps do not grow forever.
+fn forget_old<K: Eq + Hash>(failures: &mut HashMap<K, VecDeque<Instant>>, now: Instant) {
+    failures.retain(|_, recent| {
+        while recent
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= LOGIN_WINDOW)
+        {
+            recent.pop_front();
+        }
+        !recent.is_empty()
+    });
+}
+
+/// API keys and the local accounts that sign in to the admin page.
+#[derive(Clone)]
+pub struct Auth {
+    credentials: Arc<RwLock<Credentials>>,
+    failures: Arc<Mutex<Failures>>,
+    keys_path: Option<PathBuf>,
+    accounts_path: Option<PathBuf>,
+    /// Let reads through without a key.
+    public_read: bool,
+}
+
+impl Auth {
+    /// Loads the keys and accounts saved in `data_dir`, if any. Without accounts, `COMPACTSEE_ADMIN_PASSWORD`
+    /// creates the first one, named `admin`.
+    pub fn open(data_dir: Option<&Path>, public_read: bool) -> Self {
+        let keys_path = data_dir.map(|dir| dir.join("api_keys.json"));
+        let accounts_path = data_dir.map(|dir| dir.join("accounts.json"));
+        let keys: SavedKeys = keys_path.as_deref().map(load_json).unwrap_or_default();
+        let accounts: SavedAccounts = accounts_path.as_deref().map(load_json).unwrap_or_default();
+        let auth = Self {
+            credentials: Arc::new(RwLock::new(Credentials {
+                next_key_id: keys.next_id,
+                keys: keys
+                    .keys
+                    .into_iter()
+                    .map(|stored| (stored.key.id, stored))
+                    .collect(),
+                accounts: accounts
+                    .accounts
+                    .into_iter()
+                    .map(|stored| (stored.account.username.clone(), stored))
+                    .collect(),
+                sessions: HashMap::new(),
+            })),
+            failures: Arc::default(),
+            keys_path,
+            accounts_path,
+            public_read,
+        };
+        if auth.accounts().is_empty() {
+            match std::env::var("COMPACTSEE_ADMIN_PASSWORD") {
+                Ok(password) => match auth.add_account("admin".to_string(), password) {
+                    Ok(_) => info!("Created the admin account"),
+                    Err(e) => error!("Could not create the admin account {}", e),
+                },
+                Err(_) => warn!(
+                    "There are no accounts, set COMPACTSEE_ADMIN_PASSWORD to create the first one"
+                ),
+            }
+        }
+        auth
+    }
+
+    pub fn public_read(&self) -> bool {
+        self.public_read
+    }
+
+    /// Oldest first.
+    pub fn keys(&self) -> Vec<ApiKey> {
+        self.credentials
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys
+            .values()
+            .map(|stored| stored.key.clone())
+            .collect()
+    }
+
+    pub fn create_key(&self, request: NewApiKey) -> Result<CreatedApiKey, AuthError> {
+        let name = request.name.trim().to_string();
+        if !valid_name(&name) {
+            return Err(AuthError::InvalidName(name));
+        }
+        let secret = format!("{}{}", KEY_PREFIX, random_token());
+        let key = {
+            let mut credentials = self
+                .credentials
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            let id = credentials.next_key_id;
+            credentials.next_key_id += 1;
+            let key = ApiKey {
+                id,
+                name,
+                scope: request.scope,
+                prefix: secret[..SHOWN_PREFIX_LEN].to_string(),
+                created_at: now_millis(),
+                last_used_at: None,
+            };
+            credentials.keys.insert(
+                id,
+                StoredKey {
+                    key: key.clone(),
+                    hash: hash_secret(&secret),
+                },
+            );
+            key
+        };
+        info!("Created {} API key {} ({})", key.scope, key.id, key.name);
+        self.save_keys();
+        Ok(CreatedApiKey { key, secret })
+    }
+
+    /// Returns `false` if there is no such key.
+    pub fn revoke_key(&self, id: u64) -> bool {
+        let revoked = self
+            .credentials
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys
+            .remove(&id);
+        if revoked.is_some() {
+            info!("Revoked API key {}", id);
+            self.save_keys();
+        }
+        revoked.is_some()
+    }
+
+    fn key_scope(&self, secret: &str) -> Option<Scope> {
+        let hash = hash_secret(secret);
+        let (id, scope) = self
+            .credentials
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys
+            .values()
+            .find(|stored| constant_time_eq(stored.hash.as_bytes(), hash.as_bytes()))
+            .map(|stored| (stored.key.id, stored.key.scope))?;
+        // only kept in memory until the keys are saved next, a write per request is not worth it
+        if let Some(stored) = self
+            .credentials
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys
+            .get_mut(&id)
+        {
+            stored.key.last_used_at = Some(now_millis());
+        }
+        Some(scope)
+    }
+
+    fn save_keys(&self) {
+        if let Some(path) = &self.keys_path {
+            let saved = {
+                let credentials = self
+                    .credentials
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner);
+                SavedKeys {
+                    next_id: credentials.next_key_id,
+                    keys: credentials.keys.values().cloned().collect(),
+                }
+            };
+            if let Err(e) = save_json(path, &saved) {
+                error!("Could not save API keys {}", e);
+            }
+        }
+    }
+
+    /// Sorted by username.
+    pub fn accounts(&self) -> Vec<Account> {
+        self.credentials
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .accounts
+            .values()
+            .map(|stored| stored.account.clone())
+            .collect()
+    }
+
+    pub fn add_account(&self, username: String, password: String) -> Result<Account, AuthError> {
+        let username = username.trim().to_string();
+        if !valid_name(&username) {
+            return Err(AuthError::InvalidName(username));
+        }
+        if password.chars().count() < MIN_PASSWORD_LEN {
+            return Err(AuthError::PasswordTooShort);
+        }
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| AuthError::Hash(e.to_string()))?
+            .to_string();
+        let account = Account {
+            username: username.clone(),
+            created_at: now_millis(),
+        };
+        {
+            let mut credentials = self
+                .credentials
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            if credentials.accounts.contains_key(&username) {
+                return Err(AuthError::UsernameTaken(username));
+            }
+            credentials.accounts.insert(
+                username,
+                StoredAccount {
+                    account: account.clone(),
+                    password_hash,
+                },
+            );
+        }
+        self.save_accounts();
+        Ok(account)
+    }
+
+    /// Also signs the account out everywhere.
+    pub fn remove_account(&self, username: &str) -> Result<(), AuthError> {
+        {
+            let mut credentials = self
+                .credentials
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            if !credentials.accounts.contains_key(username) {
+                return Err(AuthError::UnknownAccount(username.to_string()));
+            }
+            if credentials.accounts.len() == 1 {
+                return Err(AuthError::LastAccount);
+            }
+            credentials.accounts.remove(username);
+            credentials
+                .sessions
+                .retain(|_, session| session.username != username);
+        }
+        info!("Removed account {}", username);
+        self.save_accounts();
+        Ok(())
+    }
+
+    fn save_accounts(&self) {
+        if let Some(path) = &self.accounts_path {
+            let saved = SavedAccounts {
+                accounts: self
+                    .credentials
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .accounts
+                    .values()
+                    .cloned()
+                    .collect(),
+            };
+            if let Err(e) = save_json(path, &saved) {
+                error!("Could not save accounts {}", e);
+            }
+        }
+    }
+
+    /// Returns the token for the session cookie. After too many failed sign-ins from `client`, or from `client` for
+    /// the account, refuses without looking at the password.
+    pub async fn login(
+        &self,
+        client: IpAddr,
+        username: &str,
+        password: &str,
+    ) -> Result<String, AuthError> {
+        let username = username.trim();
+        if self
+            .failures
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .throttled(client, username)
+        {
+            warn!("Refused sign-in to {} from {}", username, client);
+            return Err(AuthError::TooManyAttempts);
+        }
+        let password_hash = self
+            .credentials
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .accounts
+            .get(username)
+            .map(|stored| stored.password_hash.clone());
+        let password = password.to_string();
+        // argon2 is slow on purpose, too slow to run on the async runtime
+        let verified = tokio::task::spawn_blocking(move || match password_hash {
+            Some(password_hash) => verify_password(&password_hash, &password),
+            None => {
+                let _ = verify_password(&DUMMY_PASSWORD_HASH, &password);
+                Err(AuthError::InvalidCredentials)
+            }
+        })
+        .await
+        .map_err(|e| AuthError::Hash(e.to_string()))?;
+        if let Err(e) = verified {
+            if matches!(e, AuthError::InvalidCredentials) {
+                self.failures
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .failed(client, username);
+            }
+            return Err(e);
+        }
+
+        let token = random_token();
+        let mut credentials = self
+            .credentials
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        credentials
+            .sessions
+            .retain(|_, session| session.expires > now);
+        credentials.sessions.insert(
+            token.clone(),
+            LoginSession {
+                username: username.to_string(),
+                expires: now + SESSION_TTL,
+            },
+        );
+        info!("{} signed in", username);
+        Ok(token)
+    }
+
+    pub fn logout(&self, headers: &HeaderMap) {
+        if let Some(token) = session_token(headers) {
+            self.credentials
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .sessions
+                .remove(&token);
+        }
+    }
+
+    /// The account signed in with the request's session cookie.
+    pub fn signed_in(&self, headers: &HeaderMap) -> Option<String> {
+        let token = session_token(headers)?;
+        let credentials = self
+            .credentials
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        credentials
+            .sessions
+            .get(&token)
+            .filter(|session| session.expires > Instant::now())
+            .map(|session| session.username.clone())
+    }
+
+    /// Scope granted by an API key, or admin for a signed in account.
+    fn scope_of(&self, headers: &HeaderMap) -> Option<Scope> {
+        let key = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or_else(|| {
+                headers
+                    .get("x-api-key")
+                    .and_then(|value| value.to_str().ok())
+            });
+        match key {
+            Some(key) => self.key_scope(key.trim()),
+            None => self.signed_in(headers).map(|_| Scope::Admin),
+        }
+    }
+}
+
+/// `Set-Cookie` value signing the browser in.
+pub fn session_cookie(token: &str) -> String {
+    format!(
+        "{}={}; Path=/; HttpOnly; Secure; SameSite=Strict; Max-Age={}",
+        SESSION_COOKIE,
+        token,
+        SESSION_TTL.as_secs()
+    )
+}
+
+/// `Set-Cookie` value signing the browser out.
+pub fn cleared_session_cookie() -> String {
+    format!(
+        "{}=; Path=/; HttpOnly; Secure; SameSite=Strict; Max-Age=0",
+        SESSION_COOKIE
+    )
+}
+
+fn session_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, token)| token.to_string())
+}
+
+fn verify_password(password_hash: &str, password: &str) -> Result<(), AuthError> {
+    let parsed = PasswordHash::new(password_hash).map_err(|e| AuthError::Hash(e.to_string()))?;
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .map_err(|_| AuthError::InvalidCredentials)
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Compares without returning early on the first difference. Only the lengths can leak.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Scope a request needs, `None` for the public site and signing in.
+pub fn required_scope(method: &Method, path: &str, public_read: bool) -> Option<Scope> {
+    if path == "/admin" || path.starts_with("/admin/") || path.starts_with("/api/v1/keys") {
+        return Some(Scope::Admin);
+    }
+    let is_read = matches!(*method, Method::GET | Method::HEAD);
+    if path.starts_with("/api/v1/contracts/") && is_read {
+        return (!public_read).then_some(Scope::Read);
+    }
+    // the watchlist, webhooks and alert rules, reading them included since webhooks carry secrets
+    if path.starts_with("/api/v1/") {
+        return Some(Scope::Watch);
+    }
+    // the server functions behind the public pages, the contract event stream included
+    if path.starts_with("/api/") {
+        return (!public_read).then_some(Scope::Read);
+    }
+    None
+}
+
+/// Middleware enforcing [`required_scope`], sending browsers without a session to the login page.
+pub async fn authorize(State(auth): State<Auth>, request: Request, next: Next) -> Response {
+    let Some(required) = required_scope(request.method(), request.uri().path(), auth.public_read)
+    else {
+        return next.run(request).await;
+    };
+    match auth.scope_of(request.headers()) {
+        Some(scope) if scope.allows(required) => next.run(request).await,
+        Some(scope) => ApiError::new(
+            StatusCode::FORBIDDEN,
+            format!("this needs the {} scope, the key has {}", required, scope),
+        )
+        .into_response(),
+        // the admin page itself, as opposed to its server functions
+        None if request.method() == Method::GET && !request.uri().path().contains("/api/") => {
+            Redirect::to("/login").into_response()
+        }
+        None => ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            format!("this needs an API key with the {} scope", required),
+        )
+        .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, last])
+    }
+
+    fn login(
+        auth: &Auth,
+        client: IpAddr,
+        username: &str,
+        password: &str,
+    ) -> Result<String, AuthError> {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(auth.login(client, username, password))
+    }
+
+    #[test]
+    fn reads_only_need_a_key_when_they_are_not_public() {
+        let get = Method::GET;
+        for public_read in [true, false] {
+            let read = (!public_read).then_some(Scope::Read);
+            assert_eq!(required_scope(&get, "/", public_read), None);
+            assert_eq!(required_scope(&get, "/login", public_read), None);
+            assert_eq!(
+                required_scope(&Method::POST, "/auth/login", public_read),
+                None
+            );
+            assert_eq!(
+                required_scope(&get, "/api/v1/contracts/00/events", public_read),
+                read
+            );
+            assert_eq!(
+                required_scope(&Method::POST, "/api/get_block", public_read),
+                read
+            );
+            assert_eq!(
+                required_scope(&get, "/api/connect_to_contract", public_read),
+                read
+            );
+        }
+    }
+
+    #[test]
+    fn changes_and_administration_always_need_a_key() {
+        for public_read in [true, false] {
+            assert_eq!(
+                required_scope(&Method::GET, "/api/v1/webhooks", public_read),
+                Some(Scope::Watch)
+            );
+            assert_eq!(
+                required_scope(&Method::POST, "/api/v1/contracts/00/watch", public_read),
+                Some(Scope::Watch)
+            );
+            assert_eq!(
+                required_scope(&Method::GET, "/api/v1/keys", public_read),
+                Some(Scope::Admin)
+            );
+            assert_eq!(
+                required_scope(&Method::GET, "/admin", public_read),
+                Some(Scope::Admin)
+            );
+            assert_eq!(
+                required_scope(&Method::POST, "/admin/api/logout", public_read),
+                Some(Scope::Admin)
+            );
+        }
+    }
+
+    #[test]
+    fn session_cookies_are_secure() {
+        for cookie in [session_cookie("token"), cleared_session_cookie()] {
+            assert!(cookie.contains("; Secure;"), "{}", cookie);
+            assert!(cookie.contains("; HttpOnly;"), "{}", cookie);
+        }
+    }
+
+    #[test]
+    fn failed_sign_ins_throttle_the_account_and_the_client() {
+        let auth = Auth::open(None, true);
+        for _ in 0..LOGIN_ATTEMPTS_PER_ACCOUNT {
+            assert!(matches!(
+                login(&auth, ip(1), "nobody", "password"),
+                Err(AuthError::InvalidCredentials)
+            ));
+        }
+        assert!(matches!(
+            login(&auth, ip(1), "nobody", "password"),
+            Err(AuthError::TooManyAttempts)
+        ));
+        // somebody else guessing wrong does not lock the account owner out
+        assert!(matches!(
+            login(&auth, ip(2), "nobody", "password"),
+            Err(AuthError::InvalidCredentials)
+        ));
+
+        for attempt in LOGIN_ATTEMPTS_PER_ACCOUNT..LOGIN_ATTEMPTS_PER_CLIENT {
+            let username = format!("nobody{}", attempt);
+            assert!(matches!(
+                login(&auth, ip(1), &username, "password"),
+                Err(AuthError::InvalidCredentials)
+            ));
+        }
+        assert!(matches!(
+            login(&auth, ip(1), "somebody", "password"),
+            Err(AuthError::TooManyAttempts)
+        ));
+        assert!(matches!(
+            login(&auth, ip(2), "somebody", "password"),
+            Err(AuthError::InvalidCredentials)
+        ));
+    }
+
+    #[test]
+    fn signing_in_opens_a_session() {
+        let auth = Auth::open(None, true);
+        auth.add_account("alice".to_string(), "correct horse".to_string())
+            .unwrap();
+        assert!(matches!(
+            login(&auth, ip(1), "alice", "wrong horse"),
+            Err(AuthError::InvalidCredentials)
+        ));
+        let token = login(&auth, ip(1), " alice ", "correct horse").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            format!("{}={}", SESSION_COOKIE, token).parse().unwrap(),
+        );
+        assert_eq!(auth.signed_in(&headers).as_deref(), Some("alice"));
+        assert_eq!(auth.scope_of(&headers), Some(Scope::Admin));
+    }
+}
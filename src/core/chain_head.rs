@@ -0,0 +1,197 @@
+use std::{
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+use tracing::{error, info, instrument, trace, warn};
+
+use crate::{
+    core::{
+        event_store::now_millis,
+        graphql::{self, IndexerError, WsReceiver, WsSender},
+        metrics::{Metrics, Subscriber},
+    },
+    domain::{
+        indexer::{BlockHeader, ChainHead},
+        network::Network,
+    },
+};
+
+/// Blocks are produced every few seconds, anything older than this means the indexer is behind.
+pub const LAG_THRESHOLD_SECS: u64 = 60;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+struct BlocksData {
+    blocks: BlockHeader,
+}
+
+#[derive(Default)]
+struct Head {
+    block: Option<BlockHeader>,
+    connected: bool,
+}
+
+/// Follows the chain head of one network through a `blocks` subscription that lives as long as the server.
+#[derive(Clone)]
+pub struct ChainHeadTracker {
+    network: Network,
+    head: Arc<RwLock<Head>>,
+    metrics: Metrics,
+    shutdown: CancellationToken,
+    stopped: CancellationToken,
+}
+
+impl ChainHeadTracker {
+    /// Follows the head until `shutdown` is cancelled.
+    pub fn spawn(
+        network: Network,
+        indexer_ws: String,
+        metrics: Metrics,
+        shutdown: CancellationToken,
+    ) -> Self {
+        let tracker = Self {
+            network,
+            head: Arc::new(RwLock::new(Head::default())),
+            metrics,
+            shutdown,
+            stopped: CancellationToken::new(),
+        };
+        let background = tracker.clone();
+        tokio::spawn(async move {
+            let _stopped = background.stopped.clone().drop_guard();
+            while !background.shutdown.is_cancelled() {
+                if let Err(e) = background.follow(&indexer_ws).await {
+                    error!(
+                        "Blocks subscription for {} failed {}",
+                        background.network, e
+                    );
+                }
+                background
+                    .head
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .connected = false;
+                tokio::select! {
+                    _ = background.shutdown.cancelled() => break,
+                    _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+                }
+                background
+                    .metrics
+                    .reconnected(background.network, Subscriber::ChainHead);
+            }
+        });
+        tracker
+    }
+
+    /// Resolves once shutdown closed the subscription and no reconnect will follow.
+    pub fn stopped(&self) -> WaitForCancellationFuture<'_> {
+        self.stopped.cancelled()
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    pub fn status(&self) -> ChainHead {
+        let head = self.head.read().unwrap_or_else(PoisonError::into_inner);
+        let seconds_since_block = head
+            .block
+            .as_ref()
+            .map(|block| now_millis().saturating_sub(block.timestamp) / 1000);
+        ChainHead {
+            network: self.network,
+            block: head.block.clone(),
+            seconds_since_block,
+            lagging: !head.connected
+                || seconds_since_block.is_none_or(|secs| secs > LAG_THRESHOLD_SECS),
+        }
+    }
+
+    #[instrument(name = "chain_head", skip_all, fields(network = %self.network))]
+    async fn follow(&self, indexer_ws: &str) -> Result<(), IndexerError> {
+        let (mut ws_sender, mut ws_receiver) = graphql::connect_ws(indexer_ws)
+            .await
+            .inspect_err(|_| self.metrics.handshake_failed(self.network))?;
+        let _subscribed = self.metrics.subscription(self.network);
+        let result = self.follow_blocks(&mut ws_sender, &mut ws_receiver).await;
+        // on every way out, a malformed frame included
+        if let Err(e) = graphql::close(&mut ws_sender, "chain-head").await {
+            // the indexer may have closed the connection first
+            info!(
+                "Could not close blocks subscription of {} cleanly {}",
+                self.network, e
+            );
+        }
+        result
+    }
+
+    /// Follows the blocks on an open connection until it ends or the server shuts down. Leaves closing the
+    /// connection to the caller.
+    async fn follow_blocks(
+        &self,
+        ws_sender: &mut WsSender,
+        ws_receiver: &mut WsReceiver,
+    ) -> Result<(), IndexerError> {
+        let query = "subscription Blocks { blocks { hash height timestamp } }";
+        graphql::subscribe(ws_sender, "chain-head", query, json!({})).await?;
+        info!("Following chain head of {}", self.network);
+
+        loop {
+            let msg = tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                msg = ws_receiver.next() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+            };
+            match msg? {
+                Message::Text(text) => match graphql::next_payload::<BlocksData>(&text)? {
+                    Some(data) => {
+                        let mut head = self.head.write().unwrap_or_else(PoisonError::into_inner);
+                        head.block = Some(data.blocks);
+                        head.connected = true;
+                    }
+                    None => {
+                        warn!("Unexpected blocks frame");
+                        trace!("Blocks frame {}", text);
+                    }
+                },
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_once_shutdown_is_cancelled() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let shutdown = CancellationToken::new();
+            // nothing listens there, so the tracker is waiting to reconnect
+            let tracker = ChainHeadTracker::spawn(
+                Network::TestNet,
+                "ws://127.0.0.1:9".to_string(),
+                Metrics::new(),
+                shutdown.clone(),
+            );
+            let stopped = tokio::time::timeout(Duration::from_millis(200), tracker.stopped());
+            assert!(stopped.await.is_err());
+
+            shutdown.cancel();
+            let stopped = tokio::time::timeout(Duration::from_secs(5), tracker.stopped());
+            assert!(stopped.await.is_ok());
+        });
+    }
+}
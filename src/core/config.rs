@@ -0,0 +1,224 @@
+use midnight_node_ledger_helpers::NetworkId;
+
+use crate::domain::network::Network;
+
+/// Where to reach the indexer of a network.
+#[derive(Debug, Clone)]
+pub struct IndexerEndpoint {
+    pub network: Network,
+    pub ws_url: String,
+    pub http_url: String,
+}
+
+impl IndexerEndpoint {
+    /// Public indexer for the network, or the default port of a local indexer for undeployed networks.
+    pub fn known(network: Network) -> Option<Self> {
+        let (ws_url, http_url) = match network {
+            Network::TestNet => (
+                "wss://indexer.testnet-02.midnight.network/api/v1/graphql/ws",
+                "https://indexer.testnet-02.midnight.network/api/v1/graphql",
+            ),
+            Network::Undeployed => (
+                "ws://localhost:8088/api/v1/graphql/ws",
+                "http://localhost:8088/api/v1/graphql",
+            ),
+            Network::DevNet | Network::MainNet => return None,
+        };
+        Some(Self {
+            network,
+            ws_url: ws_url.to_string(),
+            http_url: http_url.to_string(),
+        })
+    }
+}
+
+pub fn ledger_network_id(network: Network) -> NetworkId {
+    match network {
+        Network::Undeployed => NetworkId::Undeployed,
+        Network::DevNet => NetworkId::DevNet,
+        Network::TestNet => NetworkId::TestNet,
+        Network::MainNet => NetworkId::MainNet,
+    }
+}
+
+/// Mail server for alert emails, usually a local stand-in such as Mailpit during development.
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub from: String,
+}
+
+impl SmtpConfig {
+    /// Reads `COMPACTSEE_SMTP` (`host:port`) and `COMPACTSEE_SMTP_FROM`, `None` when email is not set up.
+    pub fn from_env() -> Option<Self> {
+        let server = std::env::var("COMPACTSEE_SMTP").ok()?;
+        let (host, port) = match server.rsplit_once(':') {
+            Some((host, port)) => (host.to_string(), port.parse().ok()?),
+            None => (server, 25),
+        };
+        Some(Self {
+            host,
+            port,
+            from: std::env::var("COMPACTSEE_SMTP_FROM")
+                .unwrap_or_else(|_| "compactsee@localhost".to_string()),
+        })
+    }
+}
+
+/// Limits on what a single client, and all clients together, may open.
+#[derive(Debug, Clone)]
+pub struct LimitConfig {
+    pub max_sessions_per_client: usize,
+    /// Across every client, the server's own watchlist subscriptions are not counted against it.
+    pub max_subscriptions: usize,
+    pub max_subscribes_per_minute: usize,
+    /// Origins pages may connect from, any origin when empty.
+    pub allowed_origins: Vec<String>,
+    /// Take the client address from `Fly-Client-IP` or `X-Forwarded-For`, only safe behind a proxy that sets them.
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for LimitConfig {
+    fn default() -> Self {
+        Self {
+            max_sessions_per_client: 8,
+            max_subscriptions: 256,
+            max_subscribes_per_minute: 20,
+            allowed_origins: Vec::new(),
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+impl LimitConfig {
+    /// Reads `COMPACTSEE_MAX_SESSIONS_PER_IP`, `COMPACTSEE_MAX_SUBSCRIPTIONS`, `COMPACTSEE_MAX_SUBSCRIBES_PER_MINUTE`,
+    /// `COMPACTSEE_ALLOWED_ORIGINS` (comma separated) and `COMPACTSEE_TRUST_FORWARDED_FOR`, keeping the defaults for unset ones.
+    pub fn from_env() -> Self {
+        let number = |name: &str| std::env::var(name).ok().and_then(|value| value.parse().ok());
+        let defaults = Self::default();
+        Self {
+            max_sessions_per_client: number("COMPACTSEE_MAX_SESSIONS_PER_IP")
+                .unwrap_or(defaults.max_sessions_per_client),
+            max_subscriptions: number("COMPACTSEE_MAX_SUBSCRIPTIONS")
+                .unwrap_or(defaults.max_subscriptions),
+            max_subscribes_per_minute: number("COMPACTSEE_MAX_SUBSCRIBES_PER_MINUTE")
+                .unwrap_or(defaults.max_subscribes_per_minute),
+            allowed_origins: std::env::var("COMPACTSEE_ALLOWED_ORIGINS")
+                .map(|origins| {
+                    origins
+                        .split(',')
+                        .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                        .filter(|origin| !origin.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            trust_forwarded_for: std::env::var("COMPACTSEE_TRUST_FORWARDED_FOR")
+                .is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true")),
+        }
+    }
+}
+
+/// Used when neither `COMPACTSEE_LOG` nor `RUST_LOG` is set, or the directives do not parse.
+pub const DEFAULT_LOG_FILTER: &str =
+    "info,midnight_node_ledger_helpers=info,midnight_ledger_prototype=info";
+
+/// Spans exported over OTLP unless `COMPACTSEE_OTLP_FILTER` says otherwise: the server's own, not its dependencies'.
+pub const DEFAULT_OTLP_FILTER: &str = "compactsee=debug";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One human readable line per event, prefixed with its spans.
+    Text,
+    /// One JSON object per line, with the spans' fields, for log aggregators.
+    Json,
+}
+
+/// Which events are logged and how they are written, and which spans are exported.
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// `EnvFilter` directives, e.g. `info,compactsee::core::contract_indexer=trace` to see indexer payloads.
+    pub filter: String,
+    pub format: LogFormat,
+    /// Directives for the spans exported over OTLP, `None` when export is off.
+    pub otlp_filter: Option<String>,
+}
+
+impl LogConfig {
+    /// Reads `COMPACTSEE_LOG`, falling back to `RUST_LOG`, and `COMPACTSEE_LOG_FORMAT` (`text` or `json`). Spans are
+    /// exported when `OTEL_EXPORTER_OTLP_ENDPOINT` or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` is set, filtered by
+    /// `COMPACTSEE_OTLP_FILTER`.
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let otlp = var("OTEL_EXPORTER_OTLP_ENDPOINT").is_some()
+            || var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").is_some();
+        Self {
+            filter: var("COMPACTSEE_LOG")
+                .or_else(|| var("RUST_LOG"))
+                .unwrap_or_else(|| DEFAULT_LOG_FILTER.to_string()),
+            format: match var("COMPACTSEE_LOG_FORMAT") {
+                Some(format) if format.eq_ignore_ascii_case("json") => LogFormat::Json,
+                _ => LogFormat::Text,
+            },
+            otlp_filter: otlp.then(|| {
+                var("COMPACTSEE_OTLP_FILTER").unwrap_or_else(|| DEFAULT_OTLP_FILTER.to_string())
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_config(vars: &[(&str, &str)]) -> LogConfig {
+        LogConfig::from_vars(|name| {
+            vars.iter()
+                .find(|(set, _)| *set == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn logs_fall_back_to_rust_log_and_then_the_default() {
+        let config = log_config(&[]);
+        assert_eq!(config.filter, DEFAULT_LOG_FILTER);
+        assert_eq!(config.format, LogFormat::Text);
+        assert_eq!(log_config(&[("RUST_LOG", "debug")]).filter, "debug");
+        assert_eq!(
+            log_config(&[("RUST_LOG", "debug"), ("COMPACTSEE_LOG", "warn")]).filter,
+            "warn"
+        );
+        assert_eq!(
+            log_config(&[("COMPACTSEE_LOG_FORMAT", "JSON")]).format,
+            LogFormat::Json
+        );
+    }
+
+    #[test]
+    fn spans_are_only_exported_with_an_otlp_endpoint() {
+        assert_eq!(
+            log_config(&[("COMPACTSEE_OTLP_FILTER", "trace")]).otlp_filter,
+            None
+        );
+        for endpoint in [
+            "OTEL_EXPORTER_OTLP_ENDPOINT",
+            "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+        ] {
+            let endpoint = (endpoint, "http://localhost:4318");
+            assert_eq!(
+                log_config(&[endpoint]).otlp_filter.as_deref(),
+                Some(DEFAULT_OTLP_FILTER)
+            );
+            assert_eq!(
+                log_config(&[endpoint, ("COMPACTSEE_OTLP_FILTER", "trace")])
+                    .otlp_filter
+                    .as_deref(),
+                Some("trace")
+            );
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError, RwLock, Weak,
+    },
+};
+
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+
+use crate::{
+    core::event_store::now_millis,
+    domain::{
+        address::ContractAddress,
+        connection::{ConnectionStats, SessionInfo, SessionOrigin, SubscriptionInfo},
+        network::Network,
+    },
+};
+
+type Entries = Arc<RwLock<HashMap<u64, Weak<Tracked>>>>;
+type Consumers = Box<dyn Fn() -> usize + Send + Sync>;
+
+struct Tracked {
+    id: u64,
+    /// Only sessions have an origin.
+    origin: Option<SessionOrigin>,
+    /// Only subscriptions have consumers.
+    consumers: Option<Consumers>,
+    network: Network,
+    started_at: u64,
+    address: RwLock<Option<String>>,
+    stats: Mutex<ConnectionStats>,
+    cancel: CancellationToken,
+    entries: Entries,
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.entries
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.id);
+    }
+}
+
+/// A session or subscription listed by the registry until the last clone is dropped.
+#[derive(Clone)]
+pub struct Tracker(Arc<Tracked>);
+
+impl Tracker {
+    pub fn id(&self) -> u64 {
+        self.0.id
+    }
+
+    pub fn set_address(&self, address: &ContractAddress) {
+        *self
+            .0
+            .address
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(address.to_string());
+    }
+
+    pub fn delivered(&self) {
+        let mut stats = self.0.stats.lock().unwrap_or_else(PoisonError::into_inner);
+        stats.events_delivered += 1;
+        stats.last_event_at = Some(now_millis());
+    }
+
+    pub fn failed(&self, error: impl fmt::Display) {
+        self.0
+            .stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .last_error = Some(error.to_string());
+    }
+
+    /// Resolves once an administrator terminated the connection.
+    pub fn terminated(&self) -> WaitForCancellationFuture<'_> {
+        self.0.cancel.cancelled()
+    }
+}
+
+/// Every browser session and indexer subscription currently open, so they can be inspected and terminated.
+#[derive(Clone, Default)]
+pub struct ConnectionRegistry {
+    next_id: Arc<AtomicU64>,
+    sessions: Entries,
+    subscriptions: Entries,
+}
+
+impl ConnectionRegistry {
+    pub fn session(&self, origin: SessionOrigin, network: Network) -> Tracker {
+        self.track(&self.sessions, Some(origin), None, network, None)
+    }
+
+    /// `consumers` counts what reads the subscription's events while it is listed.
+    pub fn subscription(
+        &self,
+        address: &ContractAddress,
+        network: Network,
+        consumers: impl Fn() -> usize + Send + Sync + 'static,
+    ) -> Tracker {
+        self.track(
+            &self.subscriptions,
+            None,
+            Some(Box::new(consumers)),
+            network,
+            Some(address.to_string()),
+        )
+    }
+
+    fn track(
+        &self,
+        entries: &Entries,
+        origin: Option<SessionOrigin>,
+        consumers: Option<Consumers>,
+        network: Network,
+        address: Option<String>,
+    ) -> Tracker {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let tracked = Arc::new(Tracked {
+            id,
+            origin,
+            consumers,
+            network,
+            started_at: now_millis(),
+            address: RwLock::new(address),
+            stats: Mutex::new(ConnectionStats::default()),
+            cancel: CancellationToken::new(),
+            entries: entries.clone(),
+        });
+        entries
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, Arc::downgrade(&tracked));
+        Tracker(tracked)
+    }
+
+    pub fn sessions(&self) -> Vec<SessionInfo> {
+        live(&self.sessions)
+            .iter()
+            .filter_map(|tracked| {
+                Some(SessionInfo {
+                    id: tracked.id,
+                    origin: tracked.origin?,
+                    address: tracked
+                        .address
+                        .read()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .clone(),
+                    network: tracked.network,
+                    started_at: tracked.started_at,
+                    stats: tracked
+                        .stats
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .clone(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn subscriptions(&self) -> Vec<SubscriptionInfo> {
+        live(&self.subscriptions)
+            .iter()
+            .map(|tracked| SubscriptionInfo {
+                id: tracked.id,
+                address: tracked
+                    .address
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone()
+                    .unwrap_or_default(),
+                clients: tracked
+                    .consumers
+                    .as_ref()
+                    .map_or(0, |consumers| consumers()),
+                network: tracked.network,
+                started_at: tracked.started_at,
+                stats: tracked
+                    .stats
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone(),
+            })
+            .collect()
+    }
+
+    /// Returns `false` if no such session is open.
+    pub fn terminate_session(&self, id: u64) -> bool {
+        terminate(&self.sessions, id)
+    }
+
+    /// Returns `false` if no such subscription is open.
+    pub fn terminate_subscription(&self, id: u64) -> bool {
+        terminate(&self.subscriptions, id)
+    }
+}
+
+/// Oldest first. The lock is released before returning, dropping the last clone of an entry removes it.
+fn live(entries: &Entries) -> Vec<Arc<Tracked>> {
+    let mut live: Vec<Arc<Tracked>> = entries
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .values()
+        .filter_map(Weak::upgrade)
+        .collect();
+    live.sort_by_key(|tracked| tracked.id);
+    live
+}
+
+fn terminate(entries: &Entries, id: u64) -> bool {
+    let tracked = entries
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&id)
+        .and_then(Weak::upgrade);
+    match tracked {
+        Some(tracked) => {
+            tracked.cancel.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    const ADDRESS: &str = "00cc2f4e37bb554c344c04aff7ad746d8df129a4985d3908b509712b4cd721f163";
+
+    #[test]
+    fn subscriptions_report_their_own_consumers() {
+        let registry = ConnectionRegistry::default();
+        let address = ContractAddress::parse(ADDRESS).unwrap();
+        let consumers = Arc::new(AtomicUsize::new(2));
+        let counted = consumers.clone();
+        let subscription = registry.subscription(&address, Network::TestNet, move || {
+            counted.load(Ordering::Relaxed)
+        });
+        // a session on the same contract is not one of the subscription's consumers
+        let session = registry.session(SessionOrigin::Api, Network::TestNet);
+        session.set_address(&address);
+
+        let clients = || registry.subscriptions()[0].clients;
+        assert_eq!(clients(), 2);
+        consumers.store(0, Ordering::Relaxed);
+        assert_eq!(clients(), 0);
+
+        drop(subscription);
+        assert!(registry.subscriptions().is_empty());
+        assert_eq!(registry.sessions().len(), 1);
+    }
+}
@@ -1,118 +1,374 @@
-use std::time::Duration;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
-use crate::domain::{ContractEvent, Event};
-use futures::channel::mpsc::Sender;
+use crate::{
+    core::{
+        connections::{ConnectionRegistry, Tracker},
+        delivery::EventSink,
+        event_store::{ActionPositions, Appended, EventStore, StoredEvent},
+        graphql::{
+            self, BlockOffset, ContractActionQuery, ContractActionsData, GraphqlClient, WsReceiver,
+            WsSender,
+        },
+        metrics::Metrics,
+        telemetry,
+    },
+    domain::{
+        address::{AddressError, ContractAddress},
+        diff::diff,
+        indexer::Transaction,
+        network::Network,
+        state::StateNode,
+        ContractEvent, Event, SubscriptionError,
+    },
+};
 use futures_util::{SinkExt, StreamExt};
-use leptos::{error::Error, prelude::ServerFnErrorErr};
-use midnight_node_ledger_helpers::{deserialize, ContractState, NetworkId, DB};
-use serde_json::json;
-use tokio_tungstenite::{
-    connect_async,
-    tungstenite::{self, client::IntoClientRequest, http::HeaderValue, Message},
+use leptos::error::Error;
+use midnight_node_ledger_helpers::{
+    deserialize, AlignedValue, ContractState, NetworkId, Proof, StateValue,
+    Transaction as LedgerTransaction, DB,
 };
-use tokio_util::sync::CancellationToken;
-use tracing::{error, info};
+use serde_json::json;
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+use tracing::{debug, debug_span, error, field, info, info_span, instrument, trace, warn, Span};
+
+/// Events buffered for consumers of the feed, a consumer that falls further behind loses the oldest.
+const FEED_BUFFER: usize = 1024;
+
+/// An event recorded for the first time, whichever session or subscription saw it.
+#[derive(Debug, Clone)]
+pub struct IndexedEvent {
+    pub address: ContractAddress,
+    pub stored: StoredEvent,
+}
 
 #[derive(Clone)]
 pub struct ContractIndexer {
     network_id: NetworkId,
     indexer_ws: String,
-    timeout: u64,
+    graphql: GraphqlClient,
+    event_store: EventStore,
+    feed: broadcast::Sender<IndexedEvent>,
+    metrics: Metrics,
+    connections: ConnectionRegistry,
+    /// Seconds, shared by every clone so it can be changed while the server runs.
+    timeout: Arc<AtomicU64>,
+    /// Cancelled once the server starts shutting down, shared by every clone.
+    shutdown: CancellationToken,
 }
 
 impl ContractIndexer {
-    pub fn new(network_id: NetworkId, indexer_ws: String, timeout: u64) -> Self {
+    pub fn new(
+        network_id: NetworkId,
+        indexer_ws: String,
+        indexer_http: String,
+        event_store: EventStore,
+        timeout: u64,
+    ) -> Self {
         Self {
             network_id,
             indexer_ws,
-            timeout,
+            graphql: GraphqlClient::new(indexer_http),
+            event_store,
+            feed: broadcast::channel(FEED_BUFFER).0,
+            metrics: Metrics::new(),
+            connections: ConnectionRegistry::default(),
+            timeout: Arc::new(AtomicU64::new(timeout)),
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    /// Same indexer with subscriptions that give up after `timeout` seconds instead, unaffected by [`Self::set_timeout`].
+    pub fn with_timeout(&self, timeout: u64) -> Self {
+        Self {
+            timeout: Arc::new(AtomicU64::new(timeout)),
+            ..self.clone()
         }
     }
 
+    pub fn timeout(&self) -> u64 {
+        self.timeout.load(Ordering::Relaxed)
+    }
+
+    /// Applies to open subscriptions as well, they end once they have been open for longer.
+    pub fn set_timeout(&self, timeout: u64) {
+        self.timeout.store(timeout, Ordering::Relaxed);
+    }
+
+    pub fn network(&self) -> Network {
+        Network::from_id(self.network_id as u8).expect("ledger network ids map onto Network")
+    }
+
+    pub fn graphql(&self) -> &GraphqlClient {
+        &self.graphql
+    }
+
+    pub fn event_store(&self) -> &EventStore {
+        &self.event_store
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub fn connections(&self) -> &ConnectionRegistry {
+        &self.connections
+    }
+
+    /// Cancelling the token ends every subscription cleanly and refuses new ones.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    pub fn shutting_down(&self) -> WaitForCancellationFuture<'_> {
+        self.shutdown.cancelled()
+    }
+
+    /// Every event recorded from now on, across all contracts.
+    pub fn feed(&self) -> broadcast::Receiver<IndexedEvent> {
+        self.feed.subscribe()
+    }
+
+    /// Stores an event and publishes it on the feed unless it was seen before. Returns its sequence number
+    /// either way.
+    fn record(
+        &self,
+        contract_address: &ContractAddress,
+        event: &ContractEvent,
+        position: usize,
+    ) -> u64 {
+        let appended = self.event_store.append(contract_address, event, position);
+        let sequence = appended.sequence();
+        if let Appended::Recorded(stored) = appended {
+            // no receivers just means nothing follows the feed
+            let _ = self.feed.send(IndexedEvent {
+                address: contract_address.clone(),
+                stored: *stored,
+            });
+        }
+        sequence
+    }
+
+    /// Parses user input into an address on the network this indexer serves.
+    pub fn parse_address(&self, input: &str) -> Result<ContractAddress, AddressError> {
+        ContractAddress::parse(input)?.for_network(self.network())
+    }
+
+    /// Confirms the contract exists and returns its latest action, so it can be shown before the subscription yields anything.
+    pub async fn find_contract<D>(
+        &self,
+        contract_address: &ContractAddress,
+    ) -> Result<ContractEvent, SubscriptionError>
+    where
+        D: DB + Clone + Send + Sync,
+    {
+        if self.is_shutting_down() {
+            return Err(SubscriptionError::ShuttingDown);
+        }
+        match self.latest_contract_action::<D>(contract_address).await {
+            // only looked at, the subscription that usually follows records it
+            Ok(Some(mut event)) => {
+                event.sequence = self.event_store.sequence_of(contract_address, &event);
+                Ok(event)
+            }
+            Ok(None) => Err(SubscriptionError::ContractNotFound {
+                network: self.network(),
+            }),
+            Err(e) => {
+                error!("Contract lookup failed {}", e);
+                Err(SubscriptionError::IndexerUnavailable(e.to_string()))
+            }
+        }
+    }
+
+    async fn latest_contract_action<D>(
+        &self,
+        contract_address: &ContractAddress,
+    ) -> Result<Option<ContractEvent>, Error>
+    where
+        D: DB + Clone + Send + Sync,
+    {
+        let query = ContractActionQuery::new(contract_address.prefixed(self.network()));
+        match self.graphql.contract_action(&query).await? {
+            Some(mut event) => {
+                self.metrics.event_received(self.network());
+                self.decode_state::<D>(&mut event)?;
+                Ok(Some(event))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Fills in the decoded state of an event, leaving it empty if the ledger cannot parse it.
+    pub fn decode_state<D>(&self, event: &mut ContractEvent) -> Result<(), Error>
+    where
+        D: DB + Clone + Send + Sync,
+    {
+        let tx_raw = info_span!("hex_decode", chars = event.state.len())
+            .in_scope(|| hex::decode(&event.state))
+            .inspect_err(|_| {
+                self.metrics.decode_failed(self.network());
+            })?;
+        let started = Instant::now();
+        let state = info_span!("deserialize_state", bytes = tx_raw.len()).in_scope(|| {
+            deserialize::<ContractState<D>, _>(std::io::Cursor::new(tx_raw), self.network_id)
+        });
+        match state {
+            Ok(state) => {
+                let decoded = state_node(&state.data).to_json();
+                trace!("Decoded state {}", decoded);
+                event.decoded_state = Some(decoded);
+                self.metrics
+                    .event_decoded(self.network(), started.elapsed());
+            }
+            Err(e) => {
+                self.metrics.decode_failed(self.network());
+                warn!("Could not deserialize contract state {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes the raw transaction, when it was requested, and the state of every contract action in it.
+    pub fn decode_transaction<D>(&self, transaction: &mut Transaction) -> Result<(), Error>
+    where
+        D: DB + Clone + Send + Sync,
+    {
+        if let Some(raw) = &transaction.raw {
+            let tx_raw = hex::decode(raw)?;
+            let decoded =
+                info_span!("deserialize_transaction", bytes = tx_raw.len()).in_scope(|| {
+                    deserialize::<LedgerTransaction<Proof, D>, _>(
+                        std::io::Cursor::new(tx_raw),
+                        self.network_id,
+                    )
+                });
+            match decoded {
+                Ok(decoded) => transaction.decoded = Some(format!("{:#?}", decoded)),
+                Err(e) => warn!(
+                    "Could not deserialize transaction {} {}",
+                    transaction.hash, e
+                ),
+            }
+        }
+        for action in transaction.contract_actions.iter_mut() {
+            self.decode_state::<D>(action)?;
+        }
+        Ok(())
+    }
+
     pub async fn subscribe_to_contract<D>(
         &self,
-        contract_address: String,
-        mut tx: Sender<Event>,
+        contract_address: ContractAddress,
+        tx: EventSink,
     ) -> Result<(), Error>
     where
         D: DB + Clone + Send + Sync,
     {
-        // we need to prepend the network id to the contract. if its testnet, its 02
-        // might change in the future if midnight tries to address it
-        let contract_addr = format!("0{}{}", self.network_id as u8, contract_address);
-        info!("contract address is {}", contract_addr.clone());
-
-        let timeout_token = CancellationToken::new();
-        let timeout_token_clone = timeout_token.clone();
-        let timeout = self.timeout;
-
-        // Kill the socket connection set by timeout. We want to avoid a resource leak if the user closes browser
-        // Not sure if there is a cleaner way to do this
-        tokio::spawn(async move {
-            tokio::time::sleep(Duration::from_secs(timeout)).await;
-            timeout_token_clone.cancel();
-        });
+        self.subscribe_to_contract_from::<D>(contract_address, None, tx)
+            .await
+    }
 
-        let mut request = self.indexer_ws.clone().into_client_request()?;
-        request.headers_mut().insert(
-            "Sec-WebSocket-Protocol",
-            HeaderValue::from_static("graphql-ws"),
-        );
-        let (ws_stream, response) = connect_async(request).await?;
-        let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+    /// Like [`Self::subscribe_to_contract`], replaying the contract's actions from `from_block` onwards.
+    #[instrument(
+        name = "subscription",
+        skip_all,
+        fields(address = %contract_address, network = %self.network(), id = field::Empty)
+    )]
+    pub async fn subscribe_to_contract_from<D>(
+        &self,
+        contract_address: ContractAddress,
+        from_block: Option<u64>,
+        tx: EventSink,
+    ) -> Result<(), Error>
+    where
+        D: DB + Clone + Send + Sync,
+    {
+        let (mut ws_sender, mut ws_receiver) = graphql::connect_ws(&self.indexer_ws)
+            .await
+            .inspect_err(|_| self.metrics.handshake_failed(self.network()))?;
+        let _subscribed = self.metrics.subscription(self.network());
+        let consumers = tx.clone();
+        let tracker = self
+            .connections
+            .subscription(&contract_address, self.network(), move || {
+                consumers.consumers()
+            });
+        Span::current().record("id", tracker.id());
 
-        let init_message = json!({
-            "type": "connection_init"
-        });
-        info!("Sending connection_init message");
-        ws_sender
-            .send(Message::Text(init_message.to_string()))
-            .await?;
+        let result = self
+            .follow_actions::<D>(
+                &contract_address,
+                from_block,
+                &tx,
+                &tracker,
+                &mut ws_sender,
+                &mut ws_receiver,
+            )
+            .await;
+        // on every way out, a failed frame included, so the indexer does not keep streaming to nobody
+        if let Err(e) = graphql::close(&mut ws_sender, "contract-sync").await {
+            // the indexer may have closed the connection first
+            info!(
+                "Could not close subscription to {} cleanly {}",
+                contract_address, e
+            );
+        }
+        result
+    }
 
-        let _message = ws_receiver.next().await.ok_or_else(|| {
-            ServerFnErrorErr::ServerError("No response from web socket connection_init".to_string())
-        })?;
-        info!("Received connection_init message");
+    /// Subscribes to the contract's actions on an open connection and hands them on until the subscription
+    /// should end. Leaves closing the connection to the caller.
+    async fn follow_actions<D>(
+        &self,
+        contract_address: &ContractAddress,
+        from_block: Option<u64>,
+        tx: &EventSink,
+        tracker: &Tracker,
+        ws_sender: &mut WsSender,
+        ws_receiver: &mut WsReceiver,
+    ) -> Result<(), Error>
+    where
+        D: DB + Clone + Send + Sync,
+    {
+        // the indexer expects the network id prepended to the contract. if its testnet, its 02
+        // might change in the future if midnight tries to address it
+        let contract_addr = contract_address.prefixed(self.network());
+        debug!("Subscribing to {}", contract_addr);
 
         let subscription_query = format!(
             r#"
-                subscription ContractSync {{
-                    contractActions(address: "{}") {{
-                        __typename
-                        ... on ContractDeploy {{
-                            address
-                            state
-                            chainState
-                        }}
-                        ... on ContractCall {{
-                            address
-                            state
-                            chainState
-                        }}
-                        ... on ContractUpdate {{
-                            address
-                            state
-                            chainState
-                        }}
-                    }}
+                subscription ContractSync($address: HexEncoded!, $offset: BlockOffset) {{
+                    contractActions(address: $address, offset: $offset) {{ {} }}
                 }}
             "#,
-            contract_addr
+            graphql::contract_action_fields()
         );
+        graphql::subscribe(
+            ws_sender,
+            "contract-sync",
+            &subscription_query,
+            json!({
+                "address": contract_addr,
+                "offset": from_block.map(BlockOffset::Height),
+            }),
+        )
+        .await?;
 
-        info!("Sending subscribe message");
-        let start_message = json!({
-            "id": "contract-sync",
-            "type": "subscribe",
-            "payload": {
-                "query": subscription_query
-            }
-        });
-
-        ws_sender
-            .send(Message::Text(start_message.to_string()))
-            .await?;
-
+        // only kept while the diff span is recorded, to tell how much each action changed
+        let mut previous_state = None;
+        let mut positions = ActionPositions::default();
         let mut ping_to_midnight = 0;
         let mut total_ellapsed_time = 0;
         let ping_interval = tokio::time::interval(std::time::Duration::from_secs(1));
@@ -121,12 +377,23 @@ impl ContractIndexer {
             tokio::select! {
             _ = ping_interval.tick() => {
                     total_ellapsed_time += 1;
-                    tx.try_send(Event::TimeLeft(timeout- total_ellapsed_time))?;
+                    // Kill the socket connection set by timeout. We want to avoid a resource leak if the user closes browser.
+                    // Read on every tick, so a timeout changed at runtime applies here too
+                    let timeout = self.timeout();
+                    if total_ellapsed_time >= timeout {
+                        info!("Operation cancelled after timeout");
+                        let _ = tx.send(Event::Disconnect);
+                        break;
+                    }
+                    if tx.send(Event::TimeLeft(timeout.saturating_sub(total_ellapsed_time))).is_err() {
+                        info!("Client went away, closing subscription");
+                        break;
+                    }
                     // Send periodic ping to keep connection alive
                     ping_to_midnight += 1;
                     if ping_to_midnight == 30 {
                         ping_to_midnight = 0;
-                        info!("sending tick");
+                        debug!("Pinging the indexer");
                         if let Err(e) = ws_sender.send(Message::Ping(vec![])).await {
                         error!("Failed to send ping: {}", e);
                         break;
@@ -135,72 +402,47 @@ impl ContractIndexer {
 
                 }
 
-            _ = timeout_token.cancelled() => {
-                    info!("Operation cancelled after timeout");
-                     tx.try_send(Event::Disconnect)?;
+            _ = tracker.terminated() => {
+                    info!("Subscription to {} terminated by an administrator", contract_address);
+                    let _ = tx.send(Event::Disconnect);
+                    break;
+                }
+
+            _ = self.shutdown.cancelled() => {
+                    info!("Closing subscription to {} for shutdown", contract_address);
+                    let _ = tx.send(Event::ServerShuttingDown);
                     break;
                 }
 
             msg =  ws_receiver.next() => {
-                info!("Received message {:#?}", msg);
+                trace!("Received message {:?}", msg);
                     match msg {
                         Some(Ok(tungstenite::Message::Text(text))) => {
-                            match serde_json::from_str::<serde_json::Value>(&text) {
-                                Ok(parsed) => {
-                                    info!("parsed is {:#?}", parsed);
-                                    if let Some(contract_action) = parsed
-                                        .get("payload")
-                                        .and_then(|p| p.get("data"))
-                                        .and_then(|d| d.get("contractActions"))
-                                    {
-                                        match serde_json::from_value::<ContractEvent>(
-                                            contract_action.clone(),
-                                        ) {
-                                            Ok(mut event) => {
-                                                let tx_raw = hex::decode(event.state.clone())?;
-                                                match deserialize::<ContractState<D>, _>(std::io::Cursor::new(tx_raw), self.network_id) {
-                                                    Ok(state) => {
-                                                        let state_as_string = format!("{:#?}", state.data);
-                                                        info!("state is {}", state_as_string.clone());
-                                                        event.state = state_as_string;
-                                                    },
-                                                    Err(e) => {
-                                                        error!("boooo did not parse state {}", e);
-                                                    },
-                                                }
-                                                tx.try_send(Event::ContractEvent(event))?
-                                            },
-                                            Err(e) => {
-                                                error!("Issue mapping parsed value to ContractEvent {}", e);
-                                                break;
-                                            }
-                                        }
-                                    } else {
-                                        error!("No contract data");
-                                        break;
-
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("Error parsing message: {}", e);
-                                    break;
-
-                                }
+                            let keep_going = self.handle_frame::<D>(
+                                contract_address,
+                                &text,
+                                tx,
+                                tracker,
+                                &mut previous_state,
+                                &mut positions,
+                            )?;
+                            if !keep_going {
+                                break;
                             }
                         }
-                        Some(Ok(tungstenite::Message::Pong(data))) => {
-                            // Respond to ping with pong
-                            info!("got pong {:#?}", data);
+                        Some(Ok(tungstenite::Message::Pong(_))) => {
+                            trace!("Received pong");
                         }
                         Some(Ok(tungstenite::Message::Close(_))) => {
                             info!("Received close frame from server");
                             break;
                         }
                         Some(Ok(_)) => {
-                            info!("Unexpected message type");
+                            debug!("Unexpected message type");
                         }
                         Some(Err(e)) => {
                             error!("{}", e);
+                            tracker.failed(&e);
                         }
                         None => {
                             info!("WebSocket stream ended");
@@ -212,4 +454,92 @@ impl ContractIndexer {
         }
         Ok(())
     }
+
+    /// Decodes, records and hands one frame of a contract subscription to its client. Returns `false` once the
+    /// subscription should end.
+    #[instrument(
+        name = "indexer_frame",
+        skip_all,
+        fields(
+            bytes = text.len(),
+            transaction = field::Empty,
+            block.height = field::Empty,
+            block.timestamp = field::Empty,
+            lag_ms = field::Empty,
+        )
+    )]
+    fn handle_frame<D>(
+        &self,
+        contract_address: &ContractAddress,
+        text: &str,
+        tx: &EventSink,
+        tracker: &Tracker,
+        previous_state: &mut Option<serde_json::Value>,
+        positions: &mut ActionPositions,
+    ) -> Result<bool, Error>
+    where
+        D: DB + Clone + Send + Sync,
+    {
+        let mut event = match graphql::next_payload::<ContractActionsData>(text) {
+            Ok(Some(data)) => data.contract_actions,
+            Ok(None) => {
+                error!("No contract data");
+                return Ok(false);
+            }
+            Err(e) => {
+                error!("Error parsing message: {}", e);
+                tracker.failed(&e);
+                return Ok(false);
+            }
+        };
+        self.metrics.event_received(self.network());
+        telemetry::record_block(&Span::current(), &event);
+        self.decode_state::<D>(&mut event)
+            .inspect_err(|e| tracker.failed(e))?;
+
+        // debug, so plain info logging does not pay for a diff nobody sees
+        let diffing = debug_span!("diff", changes = field::Empty);
+        if !diffing.is_disabled() {
+            let _diffing = diffing.enter();
+            if let (Some(before), Some(after)) = (previous_state.as_ref(), &event.decoded_state) {
+                diffing.record("changes", diff(before, after).len());
+            }
+            if event.decoded_state.is_some() {
+                previous_state.clone_from(&event.decoded_state);
+            }
+        }
+
+        let position = positions.next(&event);
+        event.sequence =
+            Some(info_span!("record").in_scope(|| self.record(contract_address, &event, position)));
+        if tx.send(Event::ContractEvent(event)).is_err() {
+            info!("Client went away, closing subscription");
+            return Ok(false);
+        }
+        tracker.delivered();
+        Ok(true)
+    }
+}
+
+/// Mirrors a ledger state value so it can be rendered as JSON without the ledger.
+fn state_node<D: DB>(value: &StateValue<D>) -> StateNode {
+    match value {
+        StateValue::Null => StateNode::Null,
+        StateValue::Cell(cell) => StateNode::Cell(atoms(cell)),
+        StateValue::Map(map) => StateNode::Map(
+            map.iter()
+                .map(|entry| (atoms(&entry.0), state_node(&entry.1)))
+                .collect(),
+        ),
+        StateValue::Array(array) => {
+            StateNode::Array(array.iter().map(|item| state_node(&item)).collect())
+        }
+        StateValue::BoundedMerkleTree(tree) => StateNode::MerkleTree {
+            height: tree.height(),
+        },
+    }
+}
+
+fn atoms(value: &AlignedValue) -> Vec<Vec<u8>> {
+    value.value.0.iter().map(|atom| atom.0.clone()).collect()
 }
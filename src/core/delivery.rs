@@ -0,0 +1,386 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll},
+};
+
+use futures::{task::AtomicWaker, Stream};
+
+use crate::{core::telemetry, domain::Event};
+
+/// Number of contract events and alerts buffered per client before the oldest ones are dropped.
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// How an event is treated when the client is not keeping up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Only the most recent value matters, older ones are replaced.
+    Coalesce,
+    /// Buffered up to the channel capacity, then the oldest are dropped and reported as `Lagged`.
+    DropOldest,
+    /// Always delivered. Reserved for rare session-level notifications.
+    Never,
+}
+
+/// Overflow policy for each class of event sent to the browser.
+pub fn overflow_policy(event: &Event) -> OverflowPolicy {
+    match event {
+        Event::TimeLeft(_) => OverflowPolicy::Coalesce,
+        Event::ContractEvent(_) | Event::Alert(_) => OverflowPolicy::DropOldest,
+        Event::Disconnect | Event::Lagged { .. } | Event::Error(_) | Event::ServerShuttingDown => {
+            OverflowPolicy::Never
+        }
+    }
+}
+
+/// The client side of the session has gone away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+impl std::fmt::Display for Closed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "event stream closed by the client")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+struct Queued {
+    event: Event,
+    /// Trace context the event was sent from, kept for contract events so their delivery joins its trace.
+    sent_from: Option<opentelemetry::Context>,
+}
+
+struct Inner {
+    queue: VecDeque<Queued>,
+    status: Option<Event>,
+    buffered: usize,
+    capacity: usize,
+    dropped: u64,
+    senders: usize,
+    sink_dropped: bool,
+    stream_dropped: bool,
+}
+
+struct Shared {
+    inner: Mutex<Inner>,
+    waker: AtomicWaker,
+}
+
+/// Creates a bounded event channel that never blocks or fails the producer because of a slow consumer.
+pub fn channel(capacity: usize) -> (EventSink, EventStream) {
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(Inner {
+            queue: VecDeque::new(),
+            status: None,
+            buffered: 0,
+            capacity: capacity.max(1),
+            dropped: 0,
+            senders: 1,
+            sink_dropped: false,
+            stream_dropped: false,
+        }),
+        waker: AtomicWaker::new(),
+    });
+    (
+        EventSink {
+            shared: shared.clone(),
+            consumers: None,
+        },
+        EventStream { shared },
+    )
+}
+
+/// Producer half of [`channel`], held by the indexer subscription and anything else feeding the same client.
+/// The stream ends once every clone is dropped.
+pub struct EventSink {
+    shared: Arc<Shared>,
+    consumers: Option<Arc<dyn Fn() -> usize + Send + Sync>>,
+}
+
+impl EventSink {
+    /// For a stream that is handed on to several readers, `consumers` counts them.
+    pub fn with_consumers(mut self, consumers: impl Fn() -> usize + Send + Sync + 'static) -> Self {
+        self.consumers = Some(Arc::new(consumers));
+        self
+    }
+
+    /// Readers of what is sent here, the stream alone unless [`EventSink::with_consumers`] said otherwise.
+    pub fn consumers(&self) -> usize {
+        match &self.consumers {
+            Some(consumers) => consumers(),
+            None => usize::from(!self.is_closed()),
+        }
+    }
+
+    /// Queues an event according to its [`OverflowPolicy`].
+    /// Only fails once the consumer has been dropped.
+    pub fn send(&self, event: Event) -> Result<(), Closed> {
+        {
+            let mut inner = self
+                .shared
+                .inner
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if inner.stream_dropped {
+                return Err(Closed);
+            }
+            match overflow_policy(&event) {
+                OverflowPolicy::Coalesce => inner.status = Some(event),
+                OverflowPolicy::Never => inner.queue.push_back(Queued {
+                    event,
+                    sent_from: None,
+                }),
+                OverflowPolicy::DropOldest => {
+                    if inner.buffered >= inner.capacity {
+                        if let Some(position) = inner.queue.iter().position(|queued| {
+                            overflow_policy(&queued.event) == OverflowPolicy::DropOldest
+                        }) {
+                            inner.queue.remove(position);
+                            inner.buffered -= 1;
+                            inner.dropped += 1;
+                        }
+                    }
+                    let sent_from =
+                        matches!(event, Event::ContractEvent(_)).then(telemetry::current_context);
+                    inner.queue.push_back(Queued { event, sent_from });
+                    inner.buffered += 1;
+                }
+            }
+        }
+        self.shared.waker.wake();
+        Ok(())
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shared
+            .inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .stream_dropped
+    }
+}
+
+impl Clone for EventSink {
+    fn clone(&self) -> Self {
+        self.shared
+            .inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .senders += 1;
+        Self {
+            shared: self.shared.clone(),
+            consumers: self.consumers.clone(),
+        }
+    }
+}
+
+impl Drop for EventSink {
+    fn drop(&mut self) {
+        let mut inner = self
+            .shared
+            .inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        inner.senders -= 1;
+        if inner.senders == 0 {
+            inner.sink_dropped = true;
+            drop(inner);
+            self.shared.waker.wake();
+        }
+    }
+}
+
+/// Consumer half of [`channel`], returned to the browser.
+pub struct EventStream {
+    shared: Arc<Shared>,
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut inner = self
+            .shared
+            .inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if inner.dropped > 0 {
+            let dropped = std::mem::take(&mut inner.dropped);
+            return Poll::Ready(Some(Event::Lagged { dropped }));
+        }
+        if let Some(status) = inner.status.take() {
+            return Poll::Ready(Some(status));
+        }
+        if let Some(Queued { event, sent_from }) = inner.queue.pop_front() {
+            if overflow_policy(&event) == OverflowPolicy::DropOldest {
+                inner.buffered -= 1;
+            }
+            if let (Event::ContractEvent(contract_event), Some(sent_from)) = (&event, sent_from) {
+                // the span only marks the hand over, so it ends right away
+                let _delivered = telemetry::deliver_span(sent_from, contract_event);
+            }
+            return Poll::Ready(Some(event));
+        }
+        if inner.sink_dropped {
+            return Poll::Ready(None);
+        }
+        // registered while holding the lock so a concurrent send cannot slip in unnoticed
+        self.shared.waker.register(cx.waker());
+        Poll::Pending
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.shared
+            .inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .stream_dropped = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{FutureExt, StreamExt};
+
+    use super::*;
+    use crate::domain::{
+        alert::{Alert, AlertCondition},
+        ContractEvent,
+    };
+
+    fn contract_event(state: &str) -> Event {
+        Event::ContractEvent(ContractEvent::test_call().with_raw_state(state))
+    }
+
+    fn alert(rule_id: u64) -> Event {
+        Event::Alert(Alert {
+            rule_id,
+            address: String::new(),
+            condition: AlertCondition::Updated,
+            message: String::new(),
+            transaction_hash: None,
+            fired_at: 0,
+        })
+    }
+
+    /// Everything currently buffered, without waiting for more.
+    fn drain(stream: &mut EventStream) -> Vec<Event> {
+        std::iter::from_fn(|| stream.next().now_or_never().flatten()).collect()
+    }
+
+    #[test]
+    fn time_left_is_coalesced_to_the_latest_value() {
+        let (sink, mut stream) = channel(4);
+        for left in [5, 4, 3] {
+            sink.send(Event::TimeLeft(left)).unwrap();
+        }
+        let events = drain(&mut stream);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], Event::TimeLeft(3)));
+    }
+
+    #[test]
+    fn overflowing_contract_events_drop_the_oldest_and_report_lagged() {
+        let (sink, mut stream) = channel(2);
+        for state in ["a", "b", "c", "d"] {
+            sink.send(contract_event(state)).unwrap();
+        }
+        let events = drain(&mut stream);
+        assert!(matches!(events[0], Event::Lagged { dropped: 2 }));
+        let states: Vec<_> = events[1..]
+            .iter()
+            .map(|event| match event {
+                Event::ContractEvent(event) => event.state.as_str(),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(states, ["c", "d"]);
+    }
+
+    #[test]
+    fn session_notifications_are_never_dropped() {
+        let (sink, mut stream) = channel(1);
+        sink.send(contract_event("a")).unwrap();
+        for _ in 0..3 {
+            sink.send(Event::ServerShuttingDown).unwrap();
+        }
+        sink.send(contract_event("b")).unwrap();
+        let events = drain(&mut stream);
+        let shutdowns = events
+            .iter()
+            .filter(|event| matches!(event, Event::ServerShuttingDown))
+            .count();
+        assert_eq!(shutdowns, 3);
+        assert!(matches!(events[0], Event::Lagged { dropped: 1 }));
+    }
+
+    #[test]
+    fn overflowing_alerts_drop_the_oldest_and_report_lagged() {
+        let (sink, mut stream) = channel(2);
+        sink.send(contract_event("a")).unwrap();
+        for rule_id in 1..=3 {
+            sink.send(alert(rule_id)).unwrap();
+        }
+        let events = drain(&mut stream);
+        assert!(matches!(events[0], Event::Lagged { dropped: 2 }));
+        let rules: Vec<_> = events[1..]
+            .iter()
+            .map(|event| match event {
+                Event::Alert(alert) => alert.rule_id,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(rules, [2, 3]);
+    }
+
+    #[test]
+    fn draining_frees_capacity() {
+        let (sink, mut stream) = channel(1);
+        sink.send(contract_event("a")).unwrap();
+        assert_eq!(drain(&mut stream).len(), 1);
+        sink.send(contract_event("b")).unwrap();
+        let events = drain(&mut stream);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], Event::ContractEvent(_)));
+    }
+
+    #[test]
+    fn stream_ends_once_every_sink_is_dropped() {
+        let (sink, mut stream) = channel(1);
+        let other = sink.clone();
+        drop(sink);
+        assert!(stream.next().now_or_never().is_none());
+        other.send(Event::Disconnect).unwrap();
+        drop(other);
+        assert!(matches!(
+            stream.next().now_or_never(),
+            Some(Some(Event::Disconnect))
+        ));
+        assert!(matches!(stream.next().now_or_never(), Some(None)));
+    }
+
+    #[test]
+    fn sending_fails_once_the_stream_is_dropped() {
+        let (sink, stream) = channel(1);
+        assert!(!sink.is_closed());
+        drop(stream);
+        assert!(sink.is_closed());
+        assert_eq!(sink.send(Event::Disconnect), Err(Closed));
+    }
+
+    #[test]
+    fn consumers_count_the_stream_unless_told_otherwise() {
+        let (sink, stream) = channel(1);
+        assert_eq!(sink.consumers(), 1);
+        drop(stream);
+        assert_eq!(sink.consumers(), 0);
+
+        let (sink, _stream) = channel(1);
+        let sink = sink.with_consumers(|| 3);
+        assert_eq!(sink.clone().consumers(), 3);
+    }
+}
@@ -0,0 +1,524 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    sync::{Arc, PoisonError, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::domain::{address::ContractAddress, ActionKind, ContractEvent};
+
+/// Events kept in memory per contract, older ones are only available from the log on disk.
+pub const MAX_EVENTS_PER_CONTRACT: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEvent {
+    /// Position of the event in the contract's history, starting at 0.
+    pub sequence: u64,
+    /// Milliseconds since the unix epoch when the server received the event.
+    pub received_at: u64,
+    pub event: ContractEvent,
+}
+
+/// Outcome of [`EventStore::append`].
+#[derive(Debug, Clone)]
+pub enum Appended {
+    Recorded(Box<StoredEvent>),
+    /// The action was recorded before with this sequence number.
+    Duplicate(u64),
+}
+
+impl Appended {
+    pub fn sequence(&self) -> u64 {
+        match self {
+            Appended::Recorded(stored) => stored.sequence,
+            Appended::Duplicate(sequence) => *sequence,
+        }
+    }
+}
+
+#[derive(Default)]
+struct History {
+    events: VecDeque<StoredEvent>,
+    next_sequence: u64,
+    log: Option<BufWriter<File>>,
+    /// Sequence numbers of each retained transaction's actions on the contract, in the order they ran. An
+    /// action is identified by its transaction hash and its index in this list.
+    actions: HashMap<String, Vec<u64>>,
+}
+
+impl History {
+    /// Sequence number of the action if it was recorded before, even if other actions arrived since.
+    fn find(&self, event: &ContractEvent, position: usize) -> Option<u64> {
+        match &event.transaction {
+            Some(transaction) => self.actions.get(&transaction.hash)?.get(position).copied(),
+            // without a transaction only a repeat of the latest state can be told apart
+            None => self
+                .events
+                .back()
+                .filter(|last| last.event.transaction.is_none() && last.event.state == event.state)
+                .map(|last| last.sequence),
+        }
+    }
+
+    fn push(&mut self, stored: StoredEvent) {
+        if let Some(transaction) = &stored.event.transaction {
+            self.actions
+                .entry(transaction.hash.clone())
+                .or_default()
+                .push(stored.sequence);
+        }
+        self.events.push_back(stored);
+        if self.events.len() > MAX_EVENTS_PER_CONTRACT {
+            if let Some(evicted) = self.events.pop_front() {
+                self.forget(&evicted);
+            }
+        }
+    }
+
+    fn forget(&mut self, evicted: &StoredEvent) {
+        let Some(transaction) = &evicted.event.transaction else {
+            return;
+        };
+        // the earlier actions stay listed so the positions of the later ones keep their meaning
+        if self
+            .actions
+            .get(&transaction.hash)
+            .and_then(|actions| actions.last())
+            == Some(&evicted.sequence)
+        {
+            self.actions.remove(&transaction.hash);
+        }
+    }
+}
+
+/// Numbers the actions a subscription yields by their position within their transaction. The indexer
+/// replays whole blocks, so a transaction's actions on a contract always arrive together and in order.
+#[derive(Debug, Default)]
+pub struct ActionPositions {
+    last: Option<(String, usize)>,
+}
+
+impl ActionPositions {
+    pub fn next(&mut self, event: &ContractEvent) -> usize {
+        let Some(transaction) = &event.transaction else {
+            self.last = None;
+            return 0;
+        };
+        let position = match &self.last {
+            Some((hash, position)) if *hash == transaction.hash => position + 1,
+            _ => 0,
+        };
+        self.last = Some((transaction.hash.clone(), position));
+        position
+    }
+}
+
+/// History of every contract action the server has seen, optionally persisted as one NDJSON file per contract.
+#[derive(Clone, Default)]
+pub struct EventStore {
+    data_dir: Option<PathBuf>,
+    contracts: Arc<RwLock<HashMap<String, History>>>,
+}
+
+impl EventStore {
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Loads the history persisted in `data_dir` and appends new events to it.
+    pub fn open(data_dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&data_dir)?;
+        let mut contracts = HashMap::new();
+        for entry in fs::read_dir(&data_dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "ndjson") {
+                continue;
+            }
+            let Some(address) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let mut history = History::default();
+            for line in BufReader::new(File::open(&path)?).lines() {
+                match serde_json::from_str::<StoredEvent>(&line?) {
+                    Ok(stored) => {
+                        history.next_sequence = stored.sequence + 1;
+                        history.push(stored);
+                    }
+                    Err(e) => error!("Skipping corrupt event in {:?} {}", path, e),
+                }
+            }
+            info!("Loaded {} events for {}", history.next_sequence, address);
+            contracts.insert(address.to_string(), history);
+        }
+        Ok(Self {
+            data_dir: Some(data_dir),
+            contracts: Arc::new(RwLock::new(contracts)),
+        })
+    }
+
+    /// Records an event unless it was recorded before, which happens when a lookup and a subscription both
+    /// return it or a resumed subscription replays the blocks it already saw. `position` is the index of the
+    /// action within its transaction, as counted by [`ActionPositions`].
+    pub fn append(
+        &self,
+        address: &ContractAddress,
+        event: &ContractEvent,
+        position: usize,
+    ) -> Appended {
+        let mut contracts = self
+            .contracts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let history = contracts.entry(address.hex().to_string()).or_default();
+        if let Some(sequence) = history.find(event, position) {
+            return Appended::Duplicate(sequence);
+        }
+
+        let mut stored = StoredEvent {
+            sequence: history.next_sequence,
+            received_at: now_millis(),
+            event: event.clone(),
+        };
+        stored.event.sequence = Some(stored.sequence);
+        history.next_sequence += 1;
+        history.push(stored.clone());
+
+        if let Some(data_dir) = &self.data_dir {
+            if let Err(e) = Self::write(history, data_dir, address, &stored) {
+                error!("Failed to persist event for {} {}", address, e);
+            }
+        }
+        Appended::Recorded(Box::new(stored))
+    }
+
+    /// Sequence number the contract's latest action was recorded with, without recording it. The latest action
+    /// is the last its transaction ran on the contract.
+    pub fn sequence_of(&self, address: &ContractAddress, event: &ContractEvent) -> Option<u64> {
+        let contracts = self
+            .contracts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let history = contracts.get(address.hex())?;
+        match &event.transaction {
+            Some(transaction) => history.actions.get(&transaction.hash)?.last().copied(),
+            None => history.find(event, 0),
+        }
+    }
+
+    fn write(
+        history: &mut History,
+        data_dir: &std::path::Path,
+        address: &ContractAddress,
+        stored: &StoredEvent,
+    ) -> io::Result<()> {
+        if history.log.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(data_dir.join(format!("{}.ndjson", address.hex())))?;
+            history.log = Some(BufWriter::new(file));
+        }
+        let log = history.log.as_mut().expect("log opened above");
+        serde_json::to_writer(&mut *log, stored)?;
+        log.write_all(b"\n")?;
+        log.flush()
+    }
+
+    /// Events with a sequence number in `offset..offset + limit`, oldest first, and the total number recorded.
+    /// Pages older than the events held in memory are read from disk, call it from a blocking task.
+    pub fn page(
+        &self,
+        address: &ContractAddress,
+        offset: u64,
+        limit: usize,
+    ) -> io::Result<(Vec<StoredEvent>, u64)> {
+        let (in_memory, total) = {
+            let contracts = self
+                .contracts
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
+            match contracts.get(address.hex()) {
+                Some(history) => (
+                    history
+                        .events
+                        .front()
+                        .is_some_and(|oldest| oldest.sequence <= offset),
+                    history.next_sequence,
+                ),
+                None => return Ok((Vec::new(), 0)),
+            }
+        };
+        let mut events = Vec::new();
+        if in_memory {
+            let contracts = self
+                .contracts
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
+            if let Some(history) = contracts.get(address.hex()) {
+                events.extend(
+                    history
+                        .events
+                        .iter()
+                        .filter(|stored| stored.sequence >= offset)
+                        .take(limit)
+                        .cloned(),
+                );
+            }
+        } else {
+            self.replay(address, |stored| {
+                if stored.sequence >= offset {
+                    events.push(stored);
+                }
+                events.len() < limit
+            })?;
+        }
+        Ok((events, total))
+    }
+
+    /// Every event still held in memory for a contract.
+    pub fn history(&self, address: &ContractAddress) -> Vec<StoredEvent> {
+        let contracts = self
+            .contracts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        contracts
+            .get(address.hex())
+            .map(|history| history.events.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Visits a contract's whole history oldest first, reading it from disk when it is persisted so
+    /// events no longer held in memory are included. Stops early once `visit` returns `false`.
+    /// Blocks on file IO, call it from a blocking task.
+    pub fn replay(
+        &self,
+        address: &ContractAddress,
+        mut visit: impl FnMut(StoredEvent) -> bool,
+    ) -> io::Result<()> {
+        if let Some(data_dir) = &self.data_dir {
+            let path = data_dir.join(format!("{}.ndjson", address.hex()));
+            if path.exists() {
+                for line in BufReader::new(File::open(&path)?).lines() {
+                    match serde_json::from_str::<StoredEvent>(&line?) {
+                        Ok(stored) => {
+                            if !visit(stored) {
+                                break;
+                            }
+                        }
+                        Err(e) => error!("Skipping corrupt event in {:?} {}", path, e),
+                    }
+                }
+                return Ok(());
+            }
+        }
+        for stored in self.history(address) {
+            if !visit(stored) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn latest(&self, address: &ContractAddress) -> Option<StoredEvent> {
+        let contracts = self
+            .contracts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        contracts
+            .get(address.hex())
+            .and_then(|history| history.events.back().cloned())
+    }
+
+    /// The latest call held in memory.
+    pub fn latest_call(&self, address: &ContractAddress) -> Option<StoredEvent> {
+        let contracts = self
+            .contracts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        contracts.get(address.hex()).and_then(|history| {
+            history
+                .events
+                .iter()
+                .rev()
+                .find(|stored| stored.event.kind() == ActionKind::Call)
+                .cloned()
+        })
+    }
+
+    /// The latest event held in memory that was recorded before `sequence`.
+    pub fn before(&self, address: &ContractAddress, sequence: u64) -> Option<StoredEvent> {
+        let contracts = self
+            .contracts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        contracts.get(address.hex()).and_then(|history| {
+            history
+                .events
+                .iter()
+                .rev()
+                .find(|stored| stored.sequence < sequence)
+                .cloned()
+        })
+    }
+
+    /// Makes sure everything written so far has reached the disk.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut contracts = self
+            .contracts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        for history in contracts.values_mut() {
+            if let Some(log) = history.log.as_mut() {
+                log.flush()?;
+                log.get_ref().sync_all()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address() -> ContractAddress {
+        ContractAddress::parse("00cc2f4e37bb554c344c04aff7ad746d8df129a4985d3908b509712b4cd721f163")
+            .unwrap()
+    }
+
+    fn action(transaction: Option<&str>, state: &str) -> ContractEvent {
+        let action = ContractEvent::test_call().with_raw_state(state);
+        match transaction {
+            Some(hash) => action.in_transaction(hash, 1, 0),
+            None => action,
+        }
+    }
+
+    /// Sequence number of the event if it was new.
+    fn recorded(
+        store: &EventStore,
+        address: &ContractAddress,
+        event: &ContractEvent,
+        position: usize,
+    ) -> Option<u64> {
+        match store.append(address, event, position) {
+            Appended::Recorded(stored) => Some(stored.sequence),
+            Appended::Duplicate(_) => None,
+        }
+    }
+
+    #[test]
+    fn repeated_actions_are_recorded_once() {
+        let store = EventStore::in_memory();
+        let address = address();
+        assert_eq!(
+            recorded(&store, &address, &action(Some("t1"), "01"), 0),
+            Some(0)
+        );
+        assert_eq!(
+            recorded(&store, &address, &action(Some("t2"), "02"), 0),
+            Some(1)
+        );
+        // a replay of an earlier action, not just of the latest one
+        assert!(matches!(
+            store.append(&address, &action(Some("t1"), "01"), 0),
+            Appended::Duplicate(0)
+        ));
+        assert_eq!(
+            store.sequence_of(&address, &action(Some("t2"), "02")),
+            Some(1)
+        );
+        assert_eq!(store.history(&address).len(), 2);
+    }
+
+    #[test]
+    fn actions_of_one_transaction_are_told_apart_by_position() {
+        let store = EventStore::in_memory();
+        let address = address();
+        // a call that leaves the state as it found it still counts as its own action
+        assert_eq!(
+            recorded(&store, &address, &action(Some("t1"), "01"), 0),
+            Some(0)
+        );
+        assert_eq!(
+            recorded(&store, &address, &action(Some("t1"), "01"), 1),
+            Some(1)
+        );
+        assert_eq!(
+            recorded(&store, &address, &action(Some("t1"), "01"), 1),
+            None
+        );
+        assert_eq!(
+            store.sequence_of(&address, &action(Some("t1"), "01")),
+            Some(1)
+        );
+        let sequences: Vec<_> = store.history(&address).iter().map(|s| s.sequence).collect();
+        assert_eq!(sequences, [0, 1]);
+    }
+
+    #[test]
+    fn positions_count_up_within_a_transaction() {
+        let mut positions = ActionPositions::default();
+        let numbered: Vec<_> = [Some("t1"), Some("t1"), Some("t2"), None, Some("t2")]
+            .into_iter()
+            .map(|transaction| positions.next(&action(transaction, "01")))
+            .collect();
+        assert_eq!(numbered, [0, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn actions_without_a_transaction_only_skip_the_latest_state() {
+        let store = EventStore::in_memory();
+        let address = address();
+        assert_eq!(recorded(&store, &address, &action(None, "01"), 0), Some(0));
+        assert_eq!(recorded(&store, &address, &action(None, "01"), 0), None);
+        assert_eq!(recorded(&store, &address, &action(None, "02"), 0), Some(1));
+        assert_eq!(recorded(&store, &address, &action(None, "01"), 0), Some(2));
+    }
+
+    #[test]
+    fn pages_older_than_memory_are_read_from_disk() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let store = EventStore::open(data_dir.path().to_path_buf()).unwrap();
+        let address = address();
+        let recorded = MAX_EVENTS_PER_CONTRACT as u64 + 5;
+        for sequence in 0..recorded {
+            store.append(&address, &action(Some(&sequence.to_string()), "01"), 0);
+        }
+
+        let (events, total) = store.page(&address, 1, 3).unwrap();
+        assert_eq!(total, recorded);
+        let sequences: Vec<_> = events.iter().map(|stored| stored.sequence).collect();
+        assert_eq!(sequences, [1, 2, 3]);
+
+        let (events, _) = store.page(&address, recorded - 2, 10).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].event.sequence, Some(recorded - 1));
+
+        // reopening keeps numbering and recognises what was recorded
+        let reopened = EventStore::open(data_dir.path().to_path_buf()).unwrap();
+        let last = recorded - 1;
+        assert_eq!(
+            reopened
+                .append(&address, &action(Some(&last.to_string()), "01"), 0)
+                .sequence(),
+            last
+        );
+        assert!(matches!(
+            reopened.append(&address, &action(Some("new"), "01"), 0),
+            Appended::Recorded(stored) if stored.sequence == recorded
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use std::io;
+
+use futures::{channel::mpsc, executor::block_on, SinkExt};
+use serde_json::Value;
+
+use crate::{
+    core::event_store::EventStore,
+    domain::{
+        address::ContractAddress,
+        export::{ExportFormat, ExportWriter, ExportedEvent},
+    },
+};
+
+/// Size of the pieces the export is sent in, so large histories never sit in memory as a whole.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Streams a contract's history in `format`, reading it on a blocking thread.
+pub fn export(
+    event_store: EventStore,
+    address: ContractAddress,
+    format: ExportFormat,
+) -> mpsc::Receiver<Result<String, io::Error>> {
+    let (tx, rx) = mpsc::channel(4);
+    tokio::task::spawn_blocking(move || {
+        let mut chunks = tx.clone();
+        let result = write_export(&event_store, &address, format, |chunk| {
+            block_on(chunks.send(Ok(chunk))).is_ok()
+        });
+        if let Err(e) = result {
+            let mut tx = tx;
+            let _ = block_on(tx.send(Err(e)));
+        }
+    });
+    rx
+}
+
+/// Writes the export through `emit`, which returns `false` once nobody is reading anymore.
+pub fn write_export(
+    event_store: &EventStore,
+    address: &ContractAddress,
+    format: ExportFormat,
+    mut emit: impl FnMut(String) -> bool,
+) -> io::Result<()> {
+    let mut writer = ExportWriter::new(format);
+    let mut out = String::new();
+    let mut previous_state: Option<Value> = None;
+    let mut reading = true;
+    let mut encode_error = None;
+    event_store.replay(address, |stored| {
+        let exported = ExportedEvent::new(
+            Some(stored.sequence),
+            stored.received_at,
+            &stored.event,
+            previous_state.as_ref(),
+        );
+        if stored.event.decoded_state.is_some() {
+            previous_state = stored.event.decoded_state;
+        }
+        match writer.write(exported) {
+            Ok(encoded) => out.push_str(&encoded),
+            Err(e) => {
+                encode_error = Some(e);
+                return false;
+            }
+        }
+        if out.len() >= CHUNK_BYTES {
+            reading = emit(std::mem::take(&mut out));
+        }
+        reading
+    })?;
+    if let Some(e) = encode_error {
+        return Err(e.into());
+    }
+
+    out.push_str(&writer.finish());
+    if reading && !out.is_empty() {
+        emit(out);
+    }
+    Ok(())
+}
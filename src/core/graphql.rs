@@ -0,0 +1,546 @@
+use std::{fmt, time::Duration};
+
+use futures_util::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{self, client::IntoClientRequest, http::HeaderValue, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+use tracing::debug;
+
+use crate::domain::{
+    indexer::{Block, Transaction},
+    ContractEvent,
+};
+
+const BLOCK_HEADER_FIELDS: &str = "hash height timestamp";
+/// A one-shot query that takes longer than this fails rather than holding up its caller.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Selection of a contract action, shared by the one-shot queries and the `contractActions` subscription.
+pub fn contract_action_fields() -> String {
+    let fields = format!(
+        "address state chainState transaction {{ hash fees {{ paidFees estimatedFees }} block {{ {} }} }}",
+        BLOCK_HEADER_FIELDS
+    );
+    format!(
+        r#"
+            __typename
+            ... on ContractDeploy {{ {fields} }}
+            ... on ContractCall {{ {fields} entryPoint }}
+            ... on ContractUpdate {{ {fields} }}
+        "#
+    )
+}
+
+fn transaction_fields(with_raw: bool) -> String {
+    format!(
+        "hash protocolVersion identifiers {} block {{ {} }} contractActions {{ {} }}",
+        if with_raw { "raw" } else { "" },
+        BLOCK_HEADER_FIELDS,
+        contract_action_fields()
+    )
+}
+
+fn block_fields(with_transactions: bool) -> String {
+    let transactions = if with_transactions {
+        format!("transactions {{ {} }}", transaction_fields(false))
+    } else {
+        String::new()
+    };
+    format!(
+        "{} protocolVersion author parent {{ hash height }} {}",
+        BLOCK_HEADER_FIELDS, transactions
+    )
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GraphqlError {
+    pub message: String,
+}
+
+/// Envelope of every GraphQL result, whether it came over HTTP or as a websocket `next` payload.
+#[derive(Debug, Deserialize)]
+pub struct GraphqlResponse<T> {
+    pub data: Option<T>,
+    #[serde(default)]
+    pub errors: Vec<GraphqlError>,
+}
+
+impl<T> GraphqlResponse<T> {
+    pub fn into_result(self) -> Result<T, IndexerError> {
+        if !self.errors.is_empty() {
+            return Err(IndexerError::Graphql(self.errors));
+        }
+        self.data.ok_or(IndexerError::MissingData)
+    }
+}
+
+/// Extracts the result from a websocket frame, `None` for protocol frames without a payload.
+pub fn next_payload<T: DeserializeOwned>(frame: &str) -> Result<Option<T>, IndexerError> {
+    let frame: serde_json::Value = serde_json::from_str(frame)?;
+    match frame.get("payload") {
+        Some(payload) => Ok(Some(GraphqlResponse::<T>::deserialize(payload)?.into_result()?)),
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug)]
+pub enum IndexerError {
+    Http(reqwest::Error),
+    Websocket(Box<tungstenite::Error>),
+    Handshake(String),
+    Graphql(Vec<GraphqlError>),
+    Decode(serde_json::Error),
+    MissingData,
+}
+
+impl fmt::Display for IndexerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexerError::Http(e) => write!(f, "request to indexer failed: {}", e),
+            IndexerError::Websocket(e) => write!(f, "indexer websocket failed: {}", e),
+            IndexerError::Handshake(e) => write!(f, "indexer websocket handshake failed: {}", e),
+            IndexerError::Graphql(errors) => {
+                let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+                write!(f, "indexer returned errors: {}", messages.join("; "))
+            }
+            IndexerError::Decode(e) => write!(f, "could not decode indexer response: {}", e),
+            IndexerError::MissingData => write!(f, "indexer response has no data"),
+        }
+    }
+}
+
+impl std::error::Error for IndexerError {}
+
+impl From<reqwest::Error> for IndexerError {
+    fn from(e: reqwest::Error) -> Self {
+        IndexerError::Http(e)
+    }
+}
+
+impl From<tungstenite::Error> for IndexerError {
+    fn from(e: tungstenite::Error) -> Self {
+        IndexerError::Websocket(Box::new(e))
+    }
+}
+
+impl From<serde_json::Error> for IndexerError {
+    fn from(e: serde_json::Error) -> Self {
+        IndexerError::Decode(e)
+    }
+}
+
+/// A typed GraphQL operation against the indexer.
+pub trait Query {
+    type Data: DeserializeOwned;
+
+    fn document(&self) -> String;
+    fn variables(&self) -> serde_json::Value;
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BlockOffset {
+    Hash(String),
+    Height(u64),
+}
+
+impl BlockOffset {
+    /// Heights are plain numbers, anything else is taken as a block hash.
+    pub fn parse(input: &str) -> Self {
+        let input = input.trim();
+        match input.parse::<u64>() {
+            Ok(height) => BlockOffset::Height(height),
+            Err(_) => BlockOffset::Hash(input.trim_start_matches("0x").to_ascii_lowercase()),
+        }
+    }
+}
+
+/// Latest action of a contract, or the one at a given block.
+#[derive(Debug, Clone)]
+pub struct ContractActionQuery {
+    address: String,
+    offset: Option<BlockOffset>,
+}
+
+impl ContractActionQuery {
+    /// `address` must already carry the network prefix.
+    pub fn new(address: String) -> Self {
+        Self {
+            address,
+            offset: None,
+        }
+    }
+
+    pub fn at_block(mut self, offset: BlockOffset) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractActionData {
+    pub contract_action: Option<ContractEvent>,
+}
+
+impl Query for ContractActionQuery {
+    type Data = ContractActionData;
+
+    fn document(&self) -> String {
+        format!(
+            r#"
+                query ContractAction($address: HexEncoded!, $offset: ContractActionOffset) {{
+                    contractAction(address: $address, offset: $offset) {{ {} }}
+                }}
+            "#,
+            contract_action_fields()
+        )
+    }
+
+    fn variables(&self) -> serde_json::Value {
+        json!({
+            "address": self.address,
+            "offset": self.offset.as_ref().map(|offset| json!({ "blockOffset": offset })),
+        })
+    }
+}
+
+/// A block by height or hash, or the latest block when no offset is given.
+#[derive(Debug, Clone)]
+pub struct BlockQuery {
+    offset: Option<BlockOffset>,
+    with_transactions: bool,
+}
+
+impl BlockQuery {
+    pub fn latest() -> Self {
+        Self {
+            offset: None,
+            with_transactions: false,
+        }
+    }
+
+    pub fn by_height(height: u64) -> Self {
+        Self::at(BlockOffset::Height(height))
+    }
+
+    pub fn by_hash(hash: String) -> Self {
+        Self::at(BlockOffset::Hash(hash))
+    }
+
+    pub fn at(offset: BlockOffset) -> Self {
+        Self {
+            offset: Some(offset),
+            with_transactions: false,
+        }
+    }
+
+    pub fn with_transactions(mut self) -> Self {
+        self.with_transactions = true;
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BlockData {
+    pub block: Option<Block>,
+}
+
+impl Query for BlockQuery {
+    type Data = BlockData;
+
+    fn document(&self) -> String {
+        format!(
+            r#"
+                query Block($offset: BlockOffset) {{
+                    block(offset: $offset) {{ {} }}
+                }}
+            "#,
+            block_fields(self.with_transactions)
+        )
+    }
+
+    fn variables(&self) -> serde_json::Value {
+        json!({ "offset": self.offset })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TransactionQuery {
+    hash: String,
+}
+
+impl TransactionQuery {
+    pub fn by_hash(hash: String) -> Self {
+        Self {
+            hash: hash.trim().trim_start_matches("0x").to_ascii_lowercase(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionData {
+    pub transactions: Vec<Transaction>,
+}
+
+impl Query for TransactionQuery {
+    type Data = TransactionData;
+
+    fn document(&self) -> String {
+        format!(
+            r#"
+                query Transaction($offset: TransactionOffset!) {{
+                    transactions(offset: $offset) {{ {} }}
+                }}
+            "#,
+            transaction_fields(true)
+        )
+    }
+
+    fn variables(&self) -> serde_json::Value {
+        json!({ "offset": { "hash": self.hash } })
+    }
+}
+
+/// Payload of the `contractActions` subscription.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractActionsData {
+    pub contract_actions: ContractEvent,
+}
+
+/// One-shot GraphQL queries to the indexer over HTTP.
+#[derive(Clone)]
+pub struct GraphqlClient {
+    endpoint: String,
+    http: reqwest::Client,
+}
+
+impl GraphqlClient {
+    pub fn new(endpoint: String) -> Self {
+        Self {
+            endpoint,
+            http: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("default http client"),
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub async fn query<Q: Query>(&self, query: &Q) -> Result<Q::Data, IndexerError> {
+        let body = json!({
+            "query": query.document(),
+            "variables": query.variables(),
+        });
+        self.http
+            .post(&self.endpoint)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<GraphqlResponse<Q::Data>>()
+            .await?
+            .into_result()
+    }
+
+    pub async fn contract_action(
+        &self,
+        query: &ContractActionQuery,
+    ) -> Result<Option<ContractEvent>, IndexerError> {
+        Ok(self.query(query).await?.contract_action)
+    }
+
+    pub async fn block(&self, query: &BlockQuery) -> Result<Option<Block>, IndexerError> {
+        Ok(self.query(query).await?.block)
+    }
+
+    pub async fn latest_block(&self) -> Result<Option<Block>, IndexerError> {
+        self.block(&BlockQuery::latest()).await
+    }
+
+    pub async fn transaction(
+        &self,
+        query: &TransactionQuery,
+    ) -> Result<Option<Transaction>, IndexerError> {
+        Ok(self.query(query).await?.transactions.into_iter().next())
+    }
+}
+
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+pub type WsSender = SplitSink<WsStream, Message>;
+pub type WsReceiver = SplitStream<WsStream>;
+
+/// Opens a `graphql-ws` connection to the indexer and completes the `connection_init` handshake.
+pub async fn connect_ws(indexer_ws: &str) -> Result<(WsSender, WsReceiver), IndexerError> {
+    let mut request = indexer_ws.into_client_request()?;
+    request.headers_mut().insert(
+        "Sec-WebSocket-Protocol",
+        HeaderValue::from_static("graphql-ws"),
+    );
+    let (ws_stream, _response) = connect_async(request).await?;
+    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+
+    let init_message = json!({
+        "type": "connection_init"
+    });
+    debug!("Sending connection_init message");
+    ws_sender
+        .send(Message::Text(init_message.to_string()))
+        .await?;
+
+    // control frames may arrive before the indexer answers
+    while let Some(message) = ws_receiver.next().await {
+        match message? {
+            Message::Text(text) => {
+                handshake_reply(&text)?;
+                debug!("Received connection_ack message");
+                return Ok((ws_sender, ws_receiver));
+            }
+            Message::Close(frame) => {
+                return Err(IndexerError::Handshake(format!(
+                    "closed before connection_ack {:?}",
+                    frame
+                )))
+            }
+            _ => {}
+        }
+    }
+    Err(IndexerError::Handshake(
+        "No response from web socket connection_init".to_string(),
+    ))
+}
+
+/// Anything but a `connection_ack` means the indexer did not accept the connection.
+fn handshake_reply(text: &str) -> Result<(), IndexerError> {
+    let reply: serde_json::Value = serde_json::from_str(text)?;
+    match reply.get("type").and_then(|kind| kind.as_str()) {
+        Some("connection_ack") => Ok(()),
+        Some("connection_error") => Err(IndexerError::Handshake(format!(
+            "connection refused {}",
+            reply.get("payload").unwrap_or(&serde_json::Value::Null)
+        ))),
+        _ => Err(IndexerError::Handshake(format!(
+            "expected connection_ack, got {}",
+            text
+        ))),
+    }
+}
+
+/// Starts a subscription on an open connection.
+pub async fn subscribe(
+    ws_sender: &mut WsSender,
+    id: &str,
+    query: &str,
+    variables: serde_json::Value,
+) -> Result<(), IndexerError> {
+    debug!("Sending subscribe message");
+    let start_message = json!({
+        "id": id,
+        "type": "subscribe",
+        "payload": {
+            "query": query,
+            "variables": variables
+        }
+    });
+    ws_sender
+        .send(Message::Text(start_message.to_string()))
+        .await?;
+    Ok(())
+}
+
+/// Completes a subscription and closes the connection, so the indexer can release it straight away.
+pub async fn close(ws_sender: &mut WsSender, id: &str) -> Result<(), IndexerError> {
+    let complete_message = json!({
+        "id": id,
+        "type": "complete"
+    });
+    ws_sender
+        .send(Message::Text(complete_message.to_string()))
+        .await?;
+    ws_sender.close().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_connection_ack_completes_the_handshake() {
+        assert!(handshake_reply(r#"{"type":"connection_ack"}"#).is_ok());
+        assert!(handshake_reply(r#"{"type":"connection_ack","payload":{}}"#).is_ok());
+        for reply in [
+            r#"{"type":"connection_error","payload":{"message":"bad"}}"#,
+            r#"{"type":"ka"}"#,
+            r#"{"payload":{}}"#,
+            "not json",
+        ] {
+            assert!(
+                matches!(
+                    handshake_reply(reply),
+                    Err(IndexerError::Handshake(_) | IndexerError::Decode(_))
+                ),
+                "{}",
+                reply
+            );
+        }
+        let refused = handshake_reply(r#"{"type":"connection_error","payload":{"message":"bad"}}"#);
+        assert!(refused.unwrap_err().to_string().contains("bad"));
+    }
+
+    #[test]
+    fn contract_action_queries_take_an_optional_block_offset() {
+        let query = ContractActionQuery::new("02ab".to_string());
+        assert!(query
+            .document()
+            .contains("contractAction(address: $address, offset: $offset)"));
+        assert_eq!(
+            query.variables(),
+            json!({ "address": "02ab", "offset": null })
+        );
+        assert_eq!(
+            query.at_block(BlockOffset::Height(5)).variables(),
+            json!({ "address": "02ab", "offset": { "blockOffset": { "height": 5 } } })
+        );
+        assert_eq!(
+            ContractActionQuery::new("02ab".to_string())
+                .at_block(BlockOffset::parse("0xCD"))
+                .variables(),
+            json!({ "address": "02ab", "offset": { "blockOffset": { "hash": "cd" } } })
+        );
+    }
+
+    #[test]
+    fn block_queries_only_select_transactions_when_asked() {
+        let latest = BlockQuery::latest();
+        assert_eq!(latest.variables(), json!({ "offset": null }));
+        assert!(!latest.document().contains("transactions"));
+        assert_eq!(
+            BlockQuery::by_height(7).variables(),
+            json!({ "offset": { "height": 7 } })
+        );
+        let by_hash = BlockQuery::by_hash("ab".to_string()).with_transactions();
+        assert_eq!(by_hash.variables(), json!({ "offset": { "hash": "ab" } }));
+        assert!(by_hash.document().contains("transactions {"));
+    }
+
+    #[test]
+    fn transaction_queries_normalise_the_hash_and_ask_for_the_raw_transaction() {
+        let query = TransactionQuery::by_hash(" 0xABcd ".to_string());
+        assert_eq!(query.variables(), json!({ "offset": { "hash": "abcd" } }));
+        assert!(query.document().contains("transactions(offset: $offset)"));
+        assert!(query.document().contains(" raw "));
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use futures::{future::join_all, SinkExt};
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::{
+    core::{
+        app_state::AppState,
+        chain_head::LAG_THRESHOLD_SECS,
+        config::IndexerEndpoint,
+        event_store::now_millis,
+        graphql::{self, GraphqlClient},
+    },
+    domain::network::Network,
+};
+
+/// Readiness is checked at most this often, requests in between get the previous result.
+const CACHE_FOR: Duration = Duration::from_secs(10);
+/// A check that takes longer than this counts as failed.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether one network's indexer is usable.
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkReadiness {
+    pub network: Network,
+    pub ready: bool,
+    /// The websocket `connection_init` handshake completed.
+    pub websocket: bool,
+    pub latest_block: Option<u64>,
+    pub seconds_since_block: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    /// Milliseconds since the unix epoch at which the indexers were checked.
+    pub checked_at: u64,
+    pub networks: Vec<NetworkReadiness>,
+}
+
+/// Checks the configured indexers on demand, caching the result so probes do not hammer them.
+#[derive(Clone)]
+pub struct HealthChecker {
+    endpoints: Vec<IndexerEndpoint>,
+    timeout: Duration,
+    last: Arc<Mutex<Option<(Instant, Readiness)>>>,
+}
+
+impl HealthChecker {
+    pub fn new(endpoints: Vec<IndexerEndpoint>) -> Self {
+        Self {
+            endpoints,
+            timeout: CHECK_TIMEOUT,
+            last: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub async fn readiness(&self) -> Readiness {
+        self.readiness_at(Instant::now()).await
+    }
+
+    async fn readiness_at(&self, now: Instant) -> Readiness {
+        // held across the check, so concurrent probes wait for one check instead of starting their own
+        let mut last = self.last.lock().await;
+        if let Some((checked, readiness)) = last.as_ref() {
+            if now.saturating_duration_since(*checked) < CACHE_FOR {
+                return readiness.clone();
+            }
+        }
+        let networks = join_all(
+            self.endpoints
+                .iter()
+                .map(|endpoint| check(endpoint, self.timeout)),
+        )
+        .await;
+        let readiness = Readiness {
+            ready: networks.iter().all(|network| network.ready),
+            checked_at: now_millis(),
+            networks,
+        };
+        *last = Some((now, readiness.clone()));
+        readiness
+    }
+}
+
+async fn check(endpoint: &IndexerEndpoint, timeout: Duration) -> NetworkReadiness {
+    let mut readiness = NetworkReadiness {
+        network: endpoint.network,
+        ready: false,
+        websocket: false,
+        latest_block: None,
+        seconds_since_block: None,
+        error: None,
+    };
+
+    match tokio::time::timeout(timeout, graphql::connect_ws(&endpoint.ws_url)).await {
+        Ok(Ok((mut ws_sender, _ws_receiver))) => {
+            readiness.websocket = true;
+            // only the handshake was needed
+            let _ = ws_sender.close().await;
+        }
+        Ok(Err(e)) => readiness.error = Some(e.to_string()),
+        Err(_) => readiness.error = Some("indexer websocket handshake timed out".to_string()),
+    }
+
+    let client = GraphqlClient::new(endpoint.http_url.clone());
+    match tokio::time::timeout(timeout, client.latest_block()).await {
+        Ok(Ok(Some(block))) => {
+            let seconds_since_block = now_millis().saturating_sub(block.timestamp) / 1000;
+            readiness.latest_block = Some(block.height);
+            readiness.seconds_since_block = Some(seconds_since_block);
+            if seconds_since_block > LAG_THRESHOLD_SECS {
+                readiness.error.get_or_insert_with(|| {
+                    format!("latest block is {} seconds old", seconds_since_block)
+                });
+            }
+        }
+        Ok(Ok(None)) => {
+            readiness
+                .error
+                .get_or_insert_with(|| "indexer has no blocks".to_string());
+        }
+        Ok(Err(e)) => {
+            readiness.error.get_or_insert_with(|| e.to_string());
+        }
+        Err(_) => {
+            readiness
+                .error
+                .get_or_insert_with(|| "latest block query timed out".to_string());
+        }
+    }
+
+    readiness.ready = readiness.error.is_none();
+    if let Some(e) = &readiness.error {
+        warn!("Indexer for {} is not ready {}", endpoint.network, e);
+    }
+    readiness
+}
+
+#[derive(Serialize)]
+struct Liveness {
+    status: &'static str,
+}
+
+/// Handler for `/healthz`, answering at all means the process is alive.
+pub async fn healthz() -> impl IntoResponse {
+    Json(Liveness { status: "ok" })
+}
+
+/// Handler for `/readyz`, `503` unless every configured indexer is usable.
+pub async fn readyz(State(app_state): State<AppState>) -> impl IntoResponse {
+    let readiness = app_state.health.readiness().await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn readiness(ready: bool, checked_at: u64) -> Readiness {
+        Readiness {
+            ready,
+            checked_at,
+            networks: Vec::new(),
+        }
+    }
+
+    /// An endpoint that accepts connections but never answers, so every check times out.
+    fn silent_endpoint(listener: &tokio::net::TcpListener) -> IndexerEndpoint {
+        let addr = listener.local_addr().unwrap();
+        IndexerEndpoint {
+            network: Network::Undeployed,
+            ws_url: format!("ws://{}/api/v3/graphql/ws", addr),
+            http_url: format!("http://{}/api/v3/graphql", addr),
+        }
+    }
+
+    #[test]
+    fn probes_get_the_cached_result_until_it_expires() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let mut checker = HealthChecker::new(vec![silent_endpoint(&listener)]);
+            checker.timeout = Duration::from_millis(100);
+            let checked = Instant::now();
+            *checker.last.lock().await = Some((checked, readiness(true, 1)));
+
+            // the indexer is down, but the cached result is still fresh
+            let cached = checker.readiness_at(checked + Duration::from_secs(9)).await;
+            assert!(cached.ready);
+            assert_eq!(cached.checked_at, 1);
+
+            let expired = checker.readiness_at(checked + CACHE_FOR).await;
+            assert!(!expired.ready);
+            assert_ne!(expired.checked_at, 1);
+        });
+    }
+
+    #[test]
+    fn a_handshake_that_times_out_is_not_ready() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let mut checker = HealthChecker::new(vec![silent_endpoint(&listener)]);
+            checker.timeout = Duration::from_millis(100);
+
+            let readiness = checker.readiness().await;
+            assert!(!readiness.ready);
+            let network = &readiness.networks[0];
+            assert!(!network.ready);
+            assert!(!network.websocket);
+            assert_eq!(
+                network.error.as_deref(),
+                Some("indexer websocket handshake timed out")
+            );
+        });
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use axum::http::{header, HeaderMap};
+
+use crate::{core::config::LimitConfig, domain::limit::LimitExceeded};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct Usage {
+    sessions: HashMap<IpAddr, usize>,
+    subscriptions: usize,
+    /// Subscribe commands within the last [`RATE_WINDOW`], oldest first.
+    subscribes: HashMap<IpAddr, VecDeque<Instant>>,
+}
+
+/// Enforces [`LimitConfig`] on browser sessions and the SSE API.
+#[derive(Clone)]
+pub struct ConnectionLimits {
+    config: Arc<LimitConfig>,
+    usage: Arc<Mutex<Usage>>,
+}
+
+impl ConnectionLimits {
+    pub fn new(config: LimitConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            usage: Arc::new(Mutex::new(Usage::default())),
+        }
+    }
+
+    /// The address limits are counted against. When trusted, `Fly-Client-IP` or else the last `X-Forwarded-For`
+    /// hop, the one our proxy appended, as the client can put anything before it.
+    pub fn client_ip(&self, headers: &HeaderMap, peer: SocketAddr) -> IpAddr {
+        if self.config.trust_forwarded_for {
+            let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+            let forwarded = header("fly-client-ip")
+                .and_then(|ip| ip.trim().parse().ok())
+                .or_else(|| {
+                    header("x-forwarded-for")
+                        .and_then(|value| value.rsplit(',').next())
+                        .and_then(|last| last.trim().parse().ok())
+                });
+            if let Some(ip) = forwarded {
+                return ip;
+            }
+        }
+        peer.ip()
+    }
+
+    /// Requests without an `Origin`, which browsers always send, come from scripts and are let through.
+    pub fn check_origin(&self, headers: &HeaderMap) -> Result<(), LimitExceeded> {
+        if self.config.allowed_origins.is_empty() {
+            return Ok(());
+        }
+        match headers.get(header::ORIGIN).and_then(|value| value.to_str().ok()) {
+            Some(origin)
+                if !self
+                    .config
+                    .allowed_origins
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(origin)) =>
+            {
+                Err(LimitExceeded::OriginNotAllowed {
+                    origin: origin.to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn open_session(&self, client: IpAddr) -> Result<Permit, LimitExceeded> {
+        let max = self.config.max_sessions_per_client;
+        let mut usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+        let sessions = usage.sessions.entry(client).or_default();
+        if *sessions >= max {
+            return Err(LimitExceeded::SessionsPerClient { max });
+        }
+        *sessions += 1;
+        Ok(Permit {
+            usage: self.usage.clone(),
+            kind: PermitKind::Session(client),
+        })
+    }
+
+    /// Counts a subscribe command against the client's rate and takes one of the server's subscriptions. A refused
+    /// command takes neither.
+    pub fn subscribe(&self, client: IpAddr) -> Result<Permit, LimitExceeded> {
+        let per_minute = self.config.max_subscribes_per_minute;
+        let max = self.config.max_subscriptions;
+        let now = Instant::now();
+        let mut usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+        // forget clients that have been quiet for a minute, so the map does not grow forever
+        usage.subscribes.retain(|_, recent| {
+            while recent
+                .front()
+                .is_some_and(|at| now.duration_since(*at) >= RATE_WINDOW)
+            {
+                recent.pop_front();
+            }
+            !recent.is_empty()
+        });
+        if usage
+            .subscribes
+            .get(&client)
+            .is_some_and(|recent| recent.len() >= per_minute)
+        {
+            return Err(LimitExceeded::SubscribeRate { per_minute });
+        }
+        if usage.subscriptions >= max {
+            return Err(LimitExceeded::Subscriptions { max });
+        }
+        usage.subscribes.entry(client).or_default().push_back(now);
+        usage.subscriptions += 1;
+        Ok(Permit {
+            usage: self.usage.clone(),
+            kind: PermitKind::Subscription,
+        })
+    }
+}
+
+enum PermitKind {
+    Session(IpAddr),
+    Subscription,
+}
+
+/// Gives its session or subscription back when dropped.
+pub struct Permit {
+    usage: Arc<Mutex<Usage>>,
+    kind: PermitKind,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+        match self.kind {
+            PermitKind::Session(client) => {
+                if let Some(sessions) = usage.sessions.get_mut(&client) {
+                    *sessions -= 1;
+                    if *sessions == 0 {
+                        usage.sessions.remove(&client);
+                    }
+                }
+            }
+            PermitKind::Subscription => usage.subscriptions -= 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    const PEER: &str = "10.0.0.1:4000";
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn client_ip(trust_forwarded_for: bool, pairs: &[(&'static str, &str)]) -> String {
+        ConnectionLimits::new(LimitConfig {
+            trust_forwarded_for,
+            ..LimitConfig::default()
+        })
+        .client_ip(&headers(pairs), PEER.parse().unwrap())
+        .to_string()
+    }
+
+    #[test]
+    fn forwarded_addresses_are_only_read_when_trusted() {
+        let forwarded = [("x-forwarded-for", "1.1.1.1")];
+        assert_eq!(client_ip(false, &forwarded), "10.0.0.1");
+        assert_eq!(client_ip(true, &forwarded), "1.1.1.1");
+        assert_eq!(client_ip(true, &[]), "10.0.0.1");
+    }
+
+    #[test]
+    fn a_spoofed_first_hop_is_ignored() {
+        assert_eq!(
+            client_ip(true, &[("x-forwarded-for", "6.6.6.6, 1.1.1.1")]),
+            "1.1.1.1"
+        );
+        assert_eq!(
+            client_ip(
+                true,
+                &[("x-forwarded-for", "6.6.6.6"), ("fly-client-ip", "2.2.2.2")]
+            ),
+            "2.2.2.2"
+        );
+        assert_eq!(
+            client_ip(true, &[("x-forwarded-for", "1.1.1.1, garbage")]),
+            "10.0.0.1"
+        );
+    }
+
+    #[test]
+    fn refused_subscribes_are_not_recorded() {
+        let limits = ConnectionLimits::new(LimitConfig {
+            max_subscriptions: 1,
+            max_subscribes_per_minute: 2,
+            ..LimitConfig::default()
+        });
+        let client: IpAddr = "1.1.1.1".parse().unwrap();
+        let first = limits.subscribe(client).unwrap();
+        // refused for capacity, so it does not use up the rate
+        assert!(matches!(
+            limits.subscribe(client),
+            Err(LimitExceeded::Subscriptions { max: 1 })
+        ));
+        drop(first);
+        let _second = limits.subscribe(client).unwrap();
+        assert!(matches!(
+            limits.subscribe(client),
+            Err(LimitExceeded::SubscribeRate { per_minute: 2 })
+        ));
+    }
+
+    #[test]
+    fn sessions_are_counted_per_client_until_released() {
+        let limits = ConnectionLimits::new(LimitConfig {
+            max_sessions_per_client: 1,
+            ..LimitConfig::default()
+        });
+        let client: IpAddr = "1.1.1.1".parse().unwrap();
+        let session = limits.open_session(client).unwrap();
+        assert!(limits.open_session(client).is_err());
+        assert!(limits.open_session("2.2.2.2".parse().unwrap()).is_ok());
+        drop(session);
+        assert!(limits.open_session(client).is_ok());
+    }
+}
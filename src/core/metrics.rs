@@ -0,0 +1,312 @@
+use std::time::Duration;
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use prometheus::{
+    exponential_buckets, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use tracing::error;
+
+use crate::{
+    core::app_state::AppState,
+    domain::{connection::SessionOrigin, network::Network},
+};
+
+/// What reopened an indexer subscription, the `subscriber` label of `upstream_reconnects_total`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subscriber {
+    Watchlist,
+    ChainHead,
+    /// An SSE client that reconnected with the id of the last event it received.
+    Api,
+}
+
+impl Subscriber {
+    fn label(self) -> &'static str {
+        match self {
+            Subscriber::Watchlist => "watchlist",
+            Subscriber::ChainHead => "chain_head",
+            Subscriber::Api => "api",
+        }
+    }
+}
+
+/// Server metrics exported in the Prometheus text format on `/metrics`.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    sessions: IntGaugeVec,
+    subscriptions: IntGaugeVec,
+    reconnects: IntCounterVec,
+    events_received: IntCounterVec,
+    events_decoded: IntCounterVec,
+    decode_failures: IntCounterVec,
+    decode_seconds: HistogramVec,
+    dropped_events: IntCounter,
+    handshake_failures: IntCounterVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("compactsee".to_string()), None)
+            .expect("valid metric prefix");
+        let metrics = Self {
+            sessions: IntGaugeVec::new(
+                Opts::new(
+                    "active_sessions",
+                    "Browser and SSE sessions currently connected",
+                ),
+                &["origin"],
+            )
+            .expect("valid metric"),
+            subscriptions: IntGaugeVec::new(
+                Opts::new(
+                    "upstream_subscriptions",
+                    "Indexer websocket subscriptions currently open",
+                ),
+                &["network"],
+            )
+            .expect("valid metric"),
+            reconnects: IntCounterVec::new(
+                Opts::new(
+                    "upstream_reconnects_total",
+                    "Indexer subscriptions reopened after they ended or failed",
+                ),
+                &["network", "subscriber"],
+            )
+            .expect("valid metric"),
+            events_received: IntCounterVec::new(
+                Opts::new(
+                    "events_received_total",
+                    "Contract actions received from the indexer",
+                ),
+                &["network"],
+            )
+            .expect("valid metric"),
+            events_decoded: IntCounterVec::new(
+                Opts::new(
+                    "events_decoded_total",
+                    "Contract actions whose state was decoded",
+                ),
+                &["network"],
+            )
+            .expect("valid metric"),
+            decode_failures: IntCounterVec::new(
+                Opts::new(
+                    "decode_failures_total",
+                    "Contract actions whose state could not be decoded",
+                ),
+                &["network"],
+            )
+            .expect("valid metric"),
+            decode_seconds: HistogramVec::new(
+                HistogramOpts::new(
+                    "decode_duration_seconds",
+                    "Time taken to deserialize and decode a contract state",
+                )
+                .buckets(exponential_buckets(0.0005, 2.0, 14).expect("valid buckets")),
+                &["network"],
+            )
+            .expect("valid metric"),
+            dropped_events: IntCounter::new(
+                "dropped_events_total",
+                "Contract events dropped because a client did not keep up",
+            )
+            .expect("valid metric"),
+            handshake_failures: IntCounterVec::new(
+                Opts::new(
+                    "ws_handshake_failures_total",
+                    "Indexer websocket connections that could not be established",
+                ),
+                &["network"],
+            )
+            .expect("valid metric"),
+            registry,
+        };
+        let collectors: [Box<dyn prometheus::core::Collector>; 9] = [
+            Box::new(metrics.sessions.clone()),
+            Box::new(metrics.subscriptions.clone()),
+            Box::new(metrics.reconnects.clone()),
+            Box::new(metrics.events_received.clone()),
+            Box::new(metrics.events_decoded.clone()),
+            Box::new(metrics.decode_failures.clone()),
+            Box::new(metrics.decode_seconds.clone()),
+            Box::new(metrics.dropped_events.clone()),
+            Box::new(metrics.handshake_failures.clone()),
+        ];
+        for collector in collectors {
+            metrics
+                .registry
+                .register(collector)
+                .expect("metric registered once");
+        }
+        metrics
+    }
+
+    /// Counts a browser or SSE session until the returned guard is dropped.
+    pub fn session(&self, origin: SessionOrigin) -> ActiveGuard {
+        ActiveGuard::new(self.sessions.with_label_values(&[&origin.to_string()]))
+    }
+
+    /// Counts an open indexer subscription until the returned guard is dropped.
+    pub fn subscription(&self, network: Network) -> ActiveGuard {
+        ActiveGuard::new(
+            self.subscriptions
+                .with_label_values(&[&network.to_string()]),
+        )
+    }
+
+    pub fn reconnected(&self, network: Network, subscriber: Subscriber) {
+        self.reconnects
+            .with_label_values(&[&network.to_string(), subscriber.label()])
+            .inc();
+    }
+
+    pub fn event_received(&self, network: Network) {
+        self.events_received
+            .with_label_values(&[&network.to_string()])
+            .inc();
+    }
+
+    pub fn event_decoded(&self, network: Network, took: Duration) {
+        self.events_decoded
+            .with_label_values(&[&network.to_string()])
+            .inc();
+        self.decode_seconds
+            .with_label_values(&[&network.to_string()])
+            .observe(took.as_secs_f64());
+    }
+
+    pub fn decode_failed(&self, network: Network) {
+        self.decode_failures
+            .with_label_values(&[&network.to_string()])
+            .inc();
+    }
+
+    pub fn events_dropped(&self, dropped: u64) {
+        self.dropped_events.inc_by(dropped);
+    }
+
+    pub fn handshake_failed(&self, network: Network) {
+        self.handshake_failures
+            .with_label_values(&[&network.to_string()])
+            .inc();
+    }
+
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        TextEncoder::new().encode_to_string(&self.registry.gather())
+    }
+}
+
+/// Decrements a gauge when dropped, so early returns and cancelled tasks are counted correctly.
+pub struct ActiveGuard(IntGauge);
+
+impl ActiveGuard {
+    fn new(gauge: IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Handler for `/metrics`.
+pub async fn serve(State(app_state): State<AppState>) -> impl IntoResponse {
+    match app_state.contract_indexer.metrics().render() {
+        Ok(body) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+            body,
+        ),
+        Err(e) => {
+            error!("Could not render metrics {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [(header::CONTENT_TYPE, "text/plain")],
+                e.to_string(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The rendered sample of a metric with exactly these labels, in the order they are rendered.
+    fn sample(rendered: &str, metric: &str, labels: &str) -> Option<f64> {
+        let prefix = format!("compactsee_{}{{{}}} ", metric, labels);
+        rendered
+            .lines()
+            .find_map(|line| line.strip_prefix(&prefix))
+            .map(|value| value.parse().unwrap())
+    }
+
+    #[test]
+    fn renders_each_metric_with_its_labels() {
+        let metrics = Metrics::new();
+        let browser = metrics.session(SessionOrigin::Browser);
+        let _api = metrics.session(SessionOrigin::Api);
+        let _subscribed = metrics.subscription(Network::TestNet);
+        metrics.reconnected(Network::TestNet, Subscriber::Watchlist);
+        metrics.reconnected(Network::TestNet, Subscriber::Api);
+        metrics.event_received(Network::TestNet);
+        metrics.event_received(Network::TestNet);
+        metrics.event_decoded(Network::TestNet, Duration::from_millis(1));
+        metrics.decode_failed(Network::TestNet);
+        metrics.events_dropped(3);
+        metrics.handshake_failed(Network::TestNet);
+        drop(browser);
+
+        let rendered = metrics.render().unwrap();
+        let network = format!("network=\"{}\"", Network::TestNet);
+        let expected = [
+            ("active_sessions", "origin=\"api\"".to_string(), 1.0),
+            ("active_sessions", "origin=\"browser\"".to_string(), 0.0),
+            ("upstream_subscriptions", network.clone(), 1.0),
+            (
+                "upstream_reconnects_total",
+                format!("{},subscriber=\"watchlist\"", network),
+                1.0,
+            ),
+            (
+                "upstream_reconnects_total",
+                format!("{},subscriber=\"api\"", network),
+                1.0,
+            ),
+            ("events_received_total", network.clone(), 2.0),
+            ("events_decoded_total", network.clone(), 1.0),
+            ("decode_failures_total", network.clone(), 1.0),
+            ("decode_duration_seconds_count", network.clone(), 1.0),
+            ("ws_handshake_failures_total", network.clone(), 1.0),
+        ];
+        for (metric, labels, value) in expected {
+            assert_eq!(
+                sample(&rendered, metric, &labels),
+                Some(value),
+                "{}{{{}}} in\n{}",
+                metric,
+                labels,
+                rendered
+            );
+        }
+        assert!(rendered
+            .lines()
+            .any(|line| line == "compactsee_dropped_events_total 3"));
+        assert!(!rendered.contains("compactsee_events_total"));
+    }
+}
@@ -1,2 +1,22 @@
+pub mod alerts;
+pub mod analytics;
+pub mod api;
 pub mod app_state;
+pub mod auth;
+pub mod chain_head;
+pub mod config;
+pub mod connections;
 pub mod contract_indexer;
+pub mod delivery;
+pub mod event_store;
+pub mod export;
+pub mod graphql;
+pub mod health;
+pub mod limits;
+pub mod metrics;
+pub mod persist;
+pub mod secret_box;
+pub mod shutdown;
+pub mod telemetry;
+pub mod watchlist;
+pub mod webhooks;
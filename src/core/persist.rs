@@ -0,0 +1,29 @@
+use std::{fs, io, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::error;
+
+/// Reads a settings file written by [`save_json`], starting from the default if it is missing or unreadable.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            error!("Ignoring corrupt {:?} {}", path, e);
+            T::default()
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => T::default(),
+        Err(e) => {
+            error!("Could not read {:?} {}", path, e);
+            T::default()
+        }
+    }
+}
+
+/// Writes the whole file next to the old one and renames it over, so a crash never leaves half a file behind.
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(value)?)?;
+    fs::rename(tmp, path)
+}
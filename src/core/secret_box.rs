@@ -0,0 +1,138 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use tracing::info;
+
+/// Hex encoded 32 byte key, takes precedence over the key file.
+pub const KEY_ENV: &str = "COMPACTSEE_SECRET_KEY";
+const KEY_FILE: &str = "secret.key";
+const NONCE_LEN: usize = 12;
+
+/// Encrypts secrets the server has to keep, such as webhook signing secrets, so a copy of the data directory
+/// alone does not reveal them.
+#[derive(Clone)]
+pub struct SecretBox {
+    cipher: ChaCha20Poly1305,
+}
+
+impl SecretBox {
+    /// Uses the key in `COMPACTSEE_SECRET_KEY`, or the `secret.key` file in `data_dir`, which is created readable
+    /// by the owner only on first start. Without either, secrets are never written out, so a fresh key will do.
+    pub fn open(data_dir: Option<&Path>) -> io::Result<Self> {
+        if let Ok(key) = std::env::var(KEY_ENV) {
+            return Self::from_hex(&key).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("{} {}", KEY_ENV, e))
+            });
+        }
+        let Some(data_dir) = data_dir else {
+            return Ok(Self::generate());
+        };
+        let path = data_dir.join(KEY_FILE);
+        match fs::read_to_string(&path) {
+            Ok(key) => Self::from_hex(key.trim()).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{:?} {}", path, e))
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+                fs::create_dir_all(data_dir)?;
+                let mut options = fs::OpenOptions::new();
+                options.write(true).create_new(true);
+                #[cfg(unix)]
+                std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+                options
+                    .open(&path)?
+                    .write_all(hex::encode(key).as_bytes())?;
+                info!("Created the secret key {:?}", path);
+                Ok(Self::new(&key))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn generate() -> Self {
+        Self::new(&ChaCha20Poly1305::generate_key(&mut OsRng))
+    }
+
+    fn new(key: &Key) -> Self {
+        Self {
+            cipher: ChaCha20Poly1305::new(key),
+        }
+    }
+
+    fn from_hex(key: &str) -> Result<Self, String> {
+        match hex::decode(key) {
+            Ok(bytes) if bytes.len() == 32 => Ok(Self::new(Key::from_slice(&bytes))),
+            _ => Err("must be 64 hex characters".to_string()),
+        }
+    }
+
+    /// Hex encoded nonce followed by the ciphertext, a fresh nonce every time.
+    pub fn seal(&self, secret: &str) -> String {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, secret.as_bytes())
+            .expect("encrypting into memory does not fail");
+        hex::encode([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// `None` when the value was sealed with another key or has been tampered with.
+    pub fn unseal(&self, sealed: &str) -> Option<String> {
+        let bytes = hex::decode(sealed).ok()?;
+        if bytes.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let secret = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .ok()?;
+        String::from_utf8(secret).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_secrets_only_open_with_the_same_key() {
+        let secret_box = SecretBox::generate();
+        let sealed = secret_box.seal("hunter2");
+        assert!(!sealed.contains(&hex::encode("hunter2")));
+        assert_ne!(sealed, secret_box.seal("hunter2"));
+        assert_eq!(secret_box.unseal(&sealed).as_deref(), Some("hunter2"));
+        assert_eq!(SecretBox::generate().unseal(&sealed), None);
+        assert_eq!(secret_box.unseal(&sealed[..20]), None);
+        assert_eq!(secret_box.unseal("not hex"), None);
+    }
+
+    #[test]
+    fn the_key_file_is_created_once_and_kept_private() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+
+        let sealed = SecretBox::open(Some(dir)).unwrap().seal("hunter2");
+        let reopened = SecretBox::open(Some(dir)).unwrap();
+        assert_eq!(reopened.unseal(&sealed).as_deref(), Some("hunter2"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join(KEY_FILE))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        fs::write(dir.join(KEY_FILE), "short").unwrap();
+        assert!(SecretBox::open(Some(dir)).is_err());
+    }
+}
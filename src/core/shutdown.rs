@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::core::{chain_head::ChainHeadTracker, contract_indexer::ContractIndexer};
+
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(10);
+const DRAIN_POLL: Duration = Duration::from_millis(100);
+
+/// How long open sessions and subscriptions get to close, read from `COMPACTSEE_SHUTDOWN_GRACE_SECS`.
+pub fn grace_period() -> Duration {
+    std::env::var("COMPACTSEE_SHUTDOWN_GRACE_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_GRACE_PERIOD)
+}
+
+/// Resolves on Ctrl+C, or SIGTERM where there are signals.
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Could not listen for Ctrl+C {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Could not listen for SIGTERM {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl+C"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
+
+/// Waits until every indexer subscription has sent its `complete` and closed, the watchlist's and the chain
+/// head's included.
+pub async fn drain(indexer: &ContractIndexer, chain_head: &ChainHeadTracker) {
+    chain_head.stopped().await;
+    while !indexer.connections().subscriptions().is_empty() {
+        tokio::time::sleep(DRAIN_POLL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{config::ledger_network_id, event_store::EventStore, metrics::Metrics},
+        domain::{address::ContractAddress, network::Network},
+    };
+
+    const ADDRESS: &str = "00cc2f4e37bb554c344c04aff7ad746d8df129a4985d3908b509712b4cd721f163";
+
+    #[test]
+    fn drain_waits_until_the_subscriptions_are_closed() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            // nothing listens there, neither is ever connected
+            let indexer = ContractIndexer::new(
+                ledger_network_id(Network::TestNet),
+                "ws://127.0.0.1:9".to_string(),
+                "http://127.0.0.1:9".to_string(),
+                EventStore::in_memory(),
+                300,
+            );
+            let chain_head = ChainHeadTracker::spawn(
+                Network::TestNet,
+                "ws://127.0.0.1:9".to_string(),
+                Metrics::new(),
+                indexer.shutdown_token(),
+            );
+            let address = ContractAddress::parse(ADDRESS).unwrap();
+            let subscription = indexer
+                .connections()
+                .subscription(&address, Network::TestNet, || 1);
+            indexer.shutdown_token().cancel();
+
+            let drained = drain(&indexer, &chain_head);
+            tokio::pin!(drained);
+            let still_open = tokio::time::timeout(Duration::from_millis(300), &mut drained);
+            assert!(still_open.await.is_err());
+
+            drop(subscription);
+            let closed = tokio::time::timeout(Duration::from_secs(5), drained);
+            assert!(closed.await.is_ok());
+        });
+    }
+}
@@ -0,0 +1,201 @@
+use opentelemetry::{trace::TracerProvider as _, Context};
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+use tracing::{error, field, info, info_span, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    fmt::{self, writer::BoxMakeWriter},
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+use crate::{
+    core::{
+        config::{LogConfig, LogFormat, DEFAULT_LOG_FILTER, DEFAULT_OTLP_FILTER},
+        event_store::now_millis,
+    },
+    domain::ContractEvent,
+};
+
+/// Keeps the OTLP exporter alive, [`Telemetry::shutdown`] sends the spans it still buffers.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                error!("Could not flush spans {}", e);
+            }
+        }
+    }
+}
+
+/// Installs the global subscriber, exporting spans over OTLP if configured. Invalid filter directives are reported and
+/// the defaults used instead.
+pub fn init(config: &LogConfig) -> Telemetry {
+    install(config, BoxMakeWriter::new(std::io::stdout))
+}
+
+/// Like [`init`], but writes to stderr, for the command line client whose stdout carries its output.
+pub fn init_stderr(config: &LogConfig) -> Telemetry {
+    install(config, BoxMakeWriter::new(std::io::stderr))
+}
+
+fn install(config: &LogConfig, writer: BoxMakeWriter) -> Telemetry {
+    let mut invalid = Vec::new();
+    let mut filter = |directives: &str, default: &str| match EnvFilter::try_new(directives) {
+        Ok(filter) => filter,
+        Err(e) => {
+            invalid.push(format!("'{}' {}", directives, e));
+            EnvFilter::new(default)
+        }
+    };
+
+    let output = match config.format {
+        LogFormat::Text => fmt::layer()
+            .with_writer(writer)
+            .with_target(false)
+            .with_thread_ids(false)
+            .with_thread_names(false)
+            .with_file(false)
+            .with_line_number(true)
+            .with_timer(fmt::time::time())
+            .with_filter(filter(&config.filter, DEFAULT_LOG_FILTER))
+            .boxed(),
+        // the session and subscription fields end up on every line, so they can be grouped by
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_writer(writer)
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_filter(filter(&config.filter, DEFAULT_LOG_FILTER))
+            .boxed(),
+    };
+
+    let (export, provider) = match &config.otlp_filter {
+        Some(directives) => match otlp_layer() {
+            Ok((layer, provider)) => (
+                Some(layer.with_filter(filter(directives, DEFAULT_OTLP_FILTER))),
+                Some(provider),
+            ),
+            Err(e) => {
+                invalid.push(format!("OTLP export {}", e));
+                (None, None)
+            }
+        },
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(output)
+        .with(export)
+        .init();
+    for e in invalid {
+        error!("Ignoring invalid configuration {}", e);
+    }
+    if provider.is_some() {
+        info!("Exporting spans over OTLP");
+    }
+    Telemetry { provider }
+}
+
+/// The exporter reads its endpoint, headers and timeout from the standard `OTEL_EXPORTER_OTLP_*` variables.
+fn otlp_layer<S>() -> Result<(impl Layer<S>, SdkTracerProvider), String>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("compactsee").build())
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("compactsee"));
+    Ok((layer, provider))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct BlockFields<'a> {
+    transaction: &'a str,
+    height: u64,
+    timestamp: u64,
+    lag_ms: u64,
+}
+
+/// What [`record_block`] records at `now`, nothing for an event without a transaction.
+fn block_fields(event: &ContractEvent, now: u64) -> Option<BlockFields<'_>> {
+    let transaction = event.transaction.as_ref()?;
+    Some(BlockFields {
+        transaction: &transaction.hash,
+        height: transaction.block.height,
+        timestamp: transaction.block.timestamp,
+        lag_ms: now.saturating_sub(transaction.block.timestamp),
+    })
+}
+
+/// Records the event's transaction and block on `span`, which declares those fields, and `lag_ms` since the block was
+/// produced. Comparing the lag of a frame with that of its delivery shows where the time goes.
+pub fn record_block(span: &Span, event: &ContractEvent) {
+    if let Some(fields) = block_fields(event, now_millis()) {
+        span.record("transaction", fields.transaction);
+        span.record("block.height", fields.height);
+        span.record("block.timestamp", fields.timestamp);
+        span.record("lag_ms", fields.lag_ms);
+    }
+}
+
+/// Where the current span sits in its trace, so a span started later, elsewhere, can join it.
+pub fn current_context() -> Context {
+    Span::current().context()
+}
+
+/// Marks the moment `event` is handed to its reader. `parent` is the context of the `indexer_frame` span that sent it,
+/// so one trace follows an action from the indexer to the client.
+pub fn deliver_span(parent: Context, event: &ContractEvent) -> Span {
+    let span = info_span!(
+        "deliver",
+        transaction = field::Empty,
+        block.height = field::Empty,
+        block.timestamp = field::Empty,
+        lag_ms = field::Empty,
+    );
+    // fails when spans are not exported, then there is no trace to join
+    let _ = span.set_parent(parent);
+    record_block(&span, event);
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_fields_measure_the_lag_from_the_block() {
+        let event = ContractEvent::test_call().in_transaction("tx1", 7, 1_000);
+        assert_eq!(
+            block_fields(&event, 1_250),
+            Some(BlockFields {
+                transaction: "tx1",
+                height: 7,
+                timestamp: 1_000,
+                lag_ms: 250,
+            })
+        );
+        // a clock behind the block's does not wrap around
+        assert_eq!(
+            block_fields(&event, 900).map(|fields| fields.lag_ms),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn events_without_a_transaction_record_nothing() {
+        assert_eq!(block_fields(&ContractEvent::test_call(), 1_000), None);
+    }
+}
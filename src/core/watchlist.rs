@@ -0,0 +1,247 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use midnight_node_ledger_helpers::DefaultDB;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::{
+    core::{
+        contract_indexer::ContractIndexer,
+        delivery,
+        event_store::now_millis,
+        metrics::Subscriber,
+        persist::{load_json, save_json},
+    },
+    domain::{address::ContractAddress, webhook::WatchedContract, ContractEvent, Event},
+};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Events buffered for consumers of the watchlist, a consumer that falls further behind loses the oldest.
+const EVENT_BUFFER: usize = 1024;
+
+/// A contract action seen on a watched contract.
+#[derive(Debug, Clone)]
+pub struct WatchedEvent {
+    pub address: ContractAddress,
+    pub event: ContractEvent,
+}
+
+struct Entry {
+    contract: WatchedContract,
+    cancel: CancellationToken,
+}
+
+/// Contracts the server follows for as long as it runs, independently of any browser session.
+#[derive(Clone)]
+pub struct Watchlist {
+    indexer: ContractIndexer,
+    contracts: Arc<RwLock<HashMap<String, Entry>>>,
+    events: broadcast::Sender<WatchedEvent>,
+    path: Option<PathBuf>,
+}
+
+impl Watchlist {
+    /// Starts following the contracts saved in `data_dir`, if any.
+    pub fn spawn(indexer: &ContractIndexer, data_dir: Option<&Path>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let watchlist = Self {
+            // server-side subscriptions are not tied to a browser, so they never time out
+            indexer: indexer.with_timeout(u64::MAX),
+            contracts: Arc::new(RwLock::new(HashMap::new())),
+            events,
+            path: data_dir.map(|dir| dir.join("watchlist.json")),
+        };
+        let saved: Vec<WatchedContract> = watchlist
+            .path
+            .as_deref()
+            .map(load_json)
+            .unwrap_or_default();
+        for contract in saved {
+            match indexer.parse_address(&contract.address) {
+                Ok(address) => watchlist.follow(address, contract.added_at),
+                Err(e) => error!("Dropping {} from the watchlist {}", contract.address, e),
+            }
+        }
+        watchlist
+    }
+
+    /// Every event seen on a watched contract from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<WatchedEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn contains(&self, address: &ContractAddress) -> bool {
+        self.contracts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(&address.to_string())
+    }
+
+    /// Oldest first.
+    pub fn list(&self) -> Vec<WatchedContract> {
+        let mut contracts: Vec<WatchedContract> = self
+            .contracts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .map(|entry| entry.contract.clone())
+            .collect();
+        contracts.sort_by_key(|contract| contract.added_at);
+        contracts
+    }
+
+    /// Returns `false` if the contract was already watched.
+    pub fn add(&self, address: ContractAddress) -> bool {
+        if self.contains(&address) {
+            return false;
+        }
+        self.follow(address, now_millis());
+        self.save();
+        true
+    }
+
+    /// Returns `false` if the contract was not watched.
+    pub fn remove(&self, address: &ContractAddress) -> bool {
+        let removed = self
+            .contracts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&address.to_string());
+        match removed {
+            Some(entry) => {
+                entry.cancel.cancel();
+                info!("Stopped watching {}", address);
+                self.save();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            if let Err(e) = save_json(path, &self.list()) {
+                error!("Could not save the watchlist {}", e);
+            }
+        }
+    }
+
+    fn follow(&self, address: ContractAddress, added_at: u64) {
+        // shutting the server down stops every watched contract, removing one only stops that one
+        let cancel = self.indexer.shutdown_token().child_token();
+        self.contracts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(
+                address.to_string(),
+                Entry {
+                    contract: WatchedContract {
+                        address: address.to_string(),
+                        added_at,
+                    },
+                    cancel: cancel.clone(),
+                },
+            );
+        info!("Watching {}", address);
+
+        let watchlist = self.clone();
+        tokio::spawn(async move {
+            // resume after the last block we have seen, so reconnecting neither misses nor repeats actions
+            let mut next_block = None;
+            let mut reconnecting = false;
+            while !cancel.is_cancelled() {
+                if reconnecting {
+                    watchlist
+                        .indexer
+                        .metrics()
+                        .reconnected(watchlist.indexer.network(), Subscriber::Watchlist);
+                }
+                reconnecting = true;
+                if next_block.is_none() {
+                    match watchlist
+                        .indexer
+                        .find_contract::<DefaultDB>(&address)
+                        .await
+                    {
+                        Ok(latest) => next_block = Some(resume_height(&latest)),
+                        Err(e) => warn!("Could not look up watched contract {} {}", address, e),
+                    }
+                }
+                if let Some(from_block) = next_block {
+                    watchlist
+                        .run_subscription(&address, from_block, &cancel, &mut next_block)
+                        .await;
+                }
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+                }
+            }
+        });
+    }
+
+    async fn run_subscription(
+        &self,
+        address: &ContractAddress,
+        from_block: Option<u64>,
+        cancel: &CancellationToken,
+        next_block: &mut Option<Option<u64>>,
+    ) {
+        let (tx, mut rx) = delivery::channel(delivery::DEFAULT_CAPACITY);
+        // the stream is read here and handed on to whoever subscribed to the watchlist
+        let events = self.events.clone();
+        let tx = tx.with_consumers(move || events.receiver_count());
+        let indexer = self.indexer.clone();
+        let subscribed = address.clone();
+        let subscription = tokio::spawn(async move {
+            indexer
+                .subscribe_to_contract_from::<DefaultDB>(subscribed, from_block, tx)
+                .await
+                .map_err(|e| e.to_string())
+        });
+
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                event = rx.next() => match event {
+                    Some(Event::ContractEvent(event)) => {
+                        *next_block = Some(resume_height(&event));
+                        // no receivers just means nothing is interested yet
+                        let _ = self.events.send(WatchedEvent {
+                            address: address.clone(),
+                            event,
+                        });
+                    }
+                    Some(Event::Lagged { dropped }) => {
+                        self.indexer.metrics().events_dropped(dropped);
+                        warn!("Watchlist dropped {} events for {}", dropped, address)
+                    }
+                    Some(_) => {}
+                    None => break,
+                },
+            }
+        }
+        // dropping the receiver makes the subscription notice and close its socket
+        drop(rx);
+        match subscription.await {
+            Ok(Err(e)) => error!("Watchlist subscription for {} failed {}", address, e),
+            Err(e) => error!("Watchlist subscription for {} panicked {}", address, e),
+            Ok(Ok(())) => info!("Watchlist subscription for {} ended", address),
+        }
+    }
+}
+
+/// Block to resubscribe from after seeing `event`, `None` to follow only new actions.
+fn resume_height(event: &ContractEvent) -> Option<u64> {
+    event
+        .transaction
+        .as_ref()
+        .map(|transaction| transaction.block.height + 1)
+}
@@ -0,0 +1,703 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    core::{
+        api::v1::{AlertV1, EventV1, WebhookPayloadV1},
+        event_store::now_millis,
+        persist::{load_json, save_json},
+        secret_box::SecretBox,
+        watchlist::WatchedEvent,
+    },
+    domain::{
+        address::ContractAddress,
+        alert::Alert,
+        webhook::{DeadLetter, DeliveryAttempt, NewWebhook, Webhook},
+        SubscriptionError,
+    },
+};
+
+/// Attempts per payload, including the first one, before it goes to the dead-letter list.
+pub const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const DELIVERY_LOG_SIZE: usize = 500;
+const DEAD_LETTER_SIZE: usize = 500;
+/// Payloads waiting for one webhook while an earlier one is retried, further ones go straight to the dead letters.
+const QUEUE_SIZE: usize = 100;
+
+/// `sha256=<hex>` HMAC of the request body, only sent when the webhook has a secret.
+pub const SIGNATURE_HEADER: &str = "X-Compactsee-Signature";
+/// Action kind of the event in the payload, or `alert`, so receivers can route without parsing the body.
+pub const EVENT_HEADER: &str = "X-Compactsee-Event";
+const ALERT_KIND: &str = "alert";
+
+#[derive(Debug)]
+pub enum WebhookError {
+    InvalidUrl(String),
+    /// The contract could not be put on the watchlist.
+    Subscription(SubscriptionError),
+}
+
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookError::InvalidUrl(url) => write!(f, "'{}' is not an http(s) URL", url),
+            WebhookError::Subscription(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {}
+
+impl From<SubscriptionError> for WebhookError {
+    fn from(e: SubscriptionError) -> Self {
+        WebhookError::Subscription(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Registered {
+    webhook: Webhook,
+    secret: Option<String>,
+    /// The secret as it is saved, see [`SecretBox::seal`].
+    sealed_secret: Option<String>,
+}
+
+/// A payload on its way to one receiver.
+#[derive(Debug)]
+struct Outgoing {
+    /// `None` for alerts posted to a rule's webhook sink.
+    webhook_id: Option<u64>,
+    url: String,
+    secret: Option<String>,
+    /// Sent as [`EVENT_HEADER`].
+    kind: String,
+    transaction_hash: Option<String>,
+    payload: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedWebhook {
+    webhook: Webhook,
+    #[serde(default)]
+    sealed_secret: Option<String>,
+    /// Earlier versions saved the secret in plain text, it is sealed when the file is loaded.
+    #[serde(default, skip_serializing)]
+    secret: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Saved {
+    next_id: u64,
+    webhooks: Vec<SavedWebhook>,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    webhooks: BTreeMap<u64, Registered>,
+    deliveries: VecDeque<DeliveryAttempt>,
+    dead_letters: VecDeque<DeadLetter>,
+    /// One queue per URL, so each receiver gets its payloads in the order the actions happened.
+    queues: HashMap<String, mpsc::Sender<Outgoing>>,
+}
+
+/// Webhooks fired for events on watched contracts, with their delivery log and dead letters.
+#[derive(Clone)]
+pub struct WebhookRegistry {
+    registry: Arc<RwLock<Registry>>,
+    http: reqwest::Client,
+    secrets: SecretBox,
+    path: Option<PathBuf>,
+}
+
+impl WebhookRegistry {
+    /// Loads the webhooks saved in `data_dir`, if any, their secrets are only saved sealed with `secrets`.
+    /// Delivery history is only kept in memory.
+    pub fn open(data_dir: Option<&Path>, secrets: SecretBox) -> Self {
+        let path = data_dir.map(|dir| dir.join("webhooks.json"));
+        let saved: Saved = path.as_deref().map(load_json).unwrap_or_default();
+        let mut unsealed = false;
+        let webhooks = saved
+            .webhooks
+            .into_iter()
+            .map(|saved| {
+                let (secret, sealed_secret) = match (saved.secret, saved.sealed_secret) {
+                    (Some(secret), _) => {
+                        unsealed = true;
+                        let sealed = secrets.seal(&secret);
+                        (Some(secret), Some(sealed))
+                    }
+                    (None, Some(sealed)) => {
+                        let secret = secrets.unseal(&sealed);
+                        if secret.is_none() {
+                            error!(
+                                "Could not unseal the secret of webhook {}, its payloads go out unsigned",
+                                saved.webhook.id
+                            );
+                        }
+                        (secret, Some(sealed))
+                    }
+                    (None, None) => (None, None),
+                };
+                let registered = Registered {
+                    webhook: saved.webhook,
+                    secret,
+                    sealed_secret,
+                };
+                (registered.webhook.id, registered)
+            })
+            .collect();
+        let registry = Registry {
+            next_id: saved.next_id,
+            webhooks,
+            ..Registry::default()
+        };
+        let registry = Self {
+            registry: Arc::new(RwLock::new(registry)),
+            http: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("default http client"),
+            secrets,
+            path,
+        };
+        if unsealed {
+            info!("Sealing webhook secrets saved in plain text");
+            registry.save();
+        }
+        registry
+    }
+
+    pub fn register(
+        &self,
+        address: &ContractAddress,
+        request: NewWebhook,
+    ) -> Result<Webhook, WebhookError> {
+        match reqwest::Url::parse(&request.url) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+            _ => return Err(WebhookError::InvalidUrl(request.url)),
+        }
+        let secret = request.secret.filter(|secret| !secret.is_empty());
+        let sealed_secret = secret.as_deref().map(|secret| self.secrets.seal(secret));
+        let webhook = {
+            let mut registry = self
+                .registry
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            let id = registry.next_id;
+            registry.next_id += 1;
+            let webhook = Webhook {
+                id,
+                address: address.to_string(),
+                url: request.url,
+                event_types: request.event_types,
+                predicate: request.predicate,
+                signed: secret.is_some(),
+                created_at: now_millis(),
+            };
+            registry.webhooks.insert(
+                id,
+                Registered {
+                    webhook: webhook.clone(),
+                    secret,
+                    sealed_secret,
+                },
+            );
+            webhook
+        };
+        info!("Registered webhook {} for {}", webhook.id, webhook.address);
+        self.save();
+        Ok(webhook)
+    }
+
+    /// Returns `false` if there is no webhook with this id. Payloads already queued for it are still delivered.
+    pub fn remove(&self, id: u64) -> bool {
+        let removed = {
+            let mut registry = self
+                .registry
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            let removed = registry.webhooks.remove(&id);
+            // alerts may still post to the URL, they start a new queue
+            if let Some(removed) = &removed {
+                let url = &removed.webhook.url;
+                if !registry
+                    .webhooks
+                    .values()
+                    .any(|other| &other.webhook.url == url)
+                {
+                    registry.queues.remove(url);
+                }
+            }
+            removed.is_some()
+        };
+        if removed {
+            self.save();
+        }
+        removed
+    }
+
+    pub fn list(&self) -> Vec<Webhook> {
+        let registry = self.registry.read().unwrap_or_else(PoisonError::into_inner);
+        registry
+            .webhooks
+            .values()
+            .map(|registered| registered.webhook.clone())
+            .collect()
+    }
+
+    /// Most recent first.
+    pub fn deliveries(&self) -> Vec<DeliveryAttempt> {
+        let registry = self.registry.read().unwrap_or_else(PoisonError::into_inner);
+        registry.deliveries.iter().rev().cloned().collect()
+    }
+
+    /// Most recent first.
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        let registry = self.registry.read().unwrap_or_else(PoisonError::into_inner);
+        registry.dead_letters.iter().rev().cloned().collect()
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let saved = {
+            let registry = self.registry.read().unwrap_or_else(PoisonError::into_inner);
+            Saved {
+                next_id: registry.next_id,
+                webhooks: registry
+                    .webhooks
+                    .values()
+                    .map(|registered| SavedWebhook {
+                        webhook: registered.webhook.clone(),
+                        sealed_secret: registered.sealed_secret.clone(),
+                        secret: None,
+                    })
+                    .collect(),
+            }
+        };
+        if let Err(e) = save_json(path, &saved) {
+            error!("Could not save webhooks {}", e);
+        }
+    }
+
+    /// Delivers every event from the watchlist to the webhooks it matches, for as long as the server runs.
+    pub fn spawn_dispatcher(&self, mut events: broadcast::Receiver<WatchedEvent>) {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(watched) => registry.dispatch(&watched),
+                    Err(RecvError::Lagged(skipped)) => {
+                        error!(
+                            "Webhook dispatcher fell behind, {} events not delivered",
+                            skipped
+                        )
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    fn dispatch(&self, watched: &WatchedEvent) {
+        let address = watched.address.to_string();
+        let targets: Vec<Registered> = {
+            let registry = self.registry.read().unwrap_or_else(PoisonError::into_inner);
+            registry
+                .webhooks
+                .values()
+                .filter(|registered| {
+                    registered.webhook.address == address
+                        && registered.webhook.matches(&watched.event)
+                })
+                .cloned()
+                .collect()
+        };
+        for target in targets {
+            let event = EventV1::new(watched.event.sequence, now_millis(), &watched.event);
+            let payload = WebhookPayloadV1 {
+                webhook_id: target.webhook.id,
+                event,
+            };
+            self.enqueue(Outgoing {
+                webhook_id: Some(target.webhook.id),
+                url: target.webhook.url,
+                secret: target.secret,
+                kind: payload.event.kind.clone(),
+                transaction_hash: payload.event.transaction_hash.clone(),
+                payload: serde_json::to_value(&payload).unwrap_or_default(),
+            });
+        }
+    }
+
+    /// Posts an alert to a rule's webhook sink, with the same retries as webhook payloads. It is signed with the
+    /// secret of the contract's webhook at the same URL, if there is one.
+    pub fn send_alert(&self, url: &str, alert: &Alert) {
+        let secret = {
+            let registry = self.registry.read().unwrap_or_else(PoisonError::into_inner);
+            registry
+                .webhooks
+                .values()
+                .find(|registered| {
+                    registered.webhook.address == alert.address && registered.webhook.url == url
+                })
+                .and_then(|registered| registered.secret.clone())
+        };
+        self.enqueue(Outgoing {
+            webhook_id: None,
+            url: url.to_string(),
+            secret,
+            kind: ALERT_KIND.to_string(),
+            transaction_hash: alert.transaction_hash.clone(),
+            payload: serde_json::to_value(AlertV1::from(alert)).unwrap_or_default(),
+        });
+    }
+
+    /// Hands the payload to the queue of its URL, starting it on the first payload.
+    fn enqueue(&self, outgoing: Outgoing) {
+        let queue = {
+            let mut registry = self
+                .registry
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            registry
+                .queues
+                .entry(outgoing.url.clone())
+                .or_insert_with(|| self.spawn_queue())
+                .clone()
+        };
+        if let Err(e) = queue.try_send(outgoing) {
+            let outgoing = e.into_inner();
+            warn!("{} is not keeping up", outgoing.url);
+            self.dead_letter(&outgoing, 0, "delivery queue full".to_string());
+        }
+    }
+
+    /// Delivers queued payloads one after the other, until no webhook posts to the URL any more.
+    fn spawn_queue(&self) -> mpsc::Sender<Outgoing> {
+        let (queue, mut queued) = mpsc::channel(QUEUE_SIZE);
+        let registry = self.clone();
+        tokio::spawn(async move {
+            while let Some(outgoing) = queued.recv().await {
+                registry.deliver(outgoing).await;
+            }
+        });
+        queue
+    }
+
+    async fn deliver(&self, outgoing: Outgoing) {
+        let body = match serde_json::to_vec(&outgoing.payload) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Could not encode webhook payload {}", e);
+                return;
+            }
+        };
+        let signature = outgoing.secret.as_deref().map(|secret| sign(secret, &body));
+
+        let mut backoff = INITIAL_BACKOFF;
+        let mut last_error = String::new();
+        for attempt in 1..=MAX_ATTEMPTS {
+            let mut request = self
+                .http
+                .post(&outgoing.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, &outgoing.kind)
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header(SIGNATURE_HEADER, format!("sha256={}", signature));
+            }
+            let (status, error) = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    (Some(response.status().as_u16()), None)
+                }
+                Ok(response) => (
+                    Some(response.status().as_u16()),
+                    Some(format!("receiver answered {}", response.status())),
+                ),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            self.log(DeliveryAttempt {
+                webhook_id: outgoing.webhook_id,
+                url: outgoing.url.clone(),
+                transaction_hash: outgoing.transaction_hash.clone(),
+                attempt,
+                status,
+                error: error.clone(),
+                at: now_millis(),
+            });
+            match error {
+                None => return,
+                Some(e) => {
+                    warn!(
+                        "Delivery to {} attempt {} failed {}",
+                        outgoing.url, attempt, e
+                    );
+                    last_error = e;
+                }
+            }
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+
+        error!(
+            "Giving up on {} after {} attempts",
+            outgoing.url, MAX_ATTEMPTS
+        );
+        self.dead_letter(&outgoing, MAX_ATTEMPTS, last_error);
+    }
+
+    fn dead_letter(&self, outgoing: &Outgoing, attempts: u32, last_error: String) {
+        let mut registry = self
+            .registry
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        registry.dead_letters.push_back(DeadLetter {
+            webhook_id: outgoing.webhook_id,
+            url: outgoing.url.clone(),
+            attempts,
+            last_error,
+            payload: outgoing.payload.clone(),
+            at: now_millis(),
+        });
+        if registry.dead_letters.len() > DEAD_LETTER_SIZE {
+            registry.dead_letters.pop_front();
+        }
+    }
+
+    fn log(&self, attempt: DeliveryAttempt) {
+        let mut registry = self
+            .registry
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        registry.deliveries.push_back(attempt);
+        if registry.deliveries.len() > DELIVERY_LOG_SIZE {
+            registry.deliveries.pop_front();
+        }
+    }
+}
+
+/// Hex encoded HMAC-SHA256 of `body`, receivers recompute it with their copy of the secret.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use axum::{body::Bytes, http::HeaderMap, routing::post, Router};
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::domain::{alert::AlertCondition, ContractEvent};
+
+    const ADDRESS: &str = "00cc2f4e37bb554c344c04aff7ad746d8df129a4985d3908b509712b4cd721f163";
+
+    fn new_webhook(url: &str, secret: Option<&str>) -> NewWebhook {
+        NewWebhook {
+            address: ADDRESS.to_string(),
+            url: url.to_string(),
+            secret: secret.map(str::to_string),
+            event_types: Vec::new(),
+            predicate: None,
+        }
+    }
+
+    fn watched_event(sequence: u64) -> WatchedEvent {
+        WatchedEvent {
+            address: ContractAddress::parse(ADDRESS).unwrap(),
+            event: ContractEvent::test_call()
+                .with_raw_state("00")
+                .with_address(ADDRESS)
+                .with_entry_point("increment")
+                .with_sequence(sequence),
+        }
+    }
+
+    /// Serves a receiver on a local port and returns its URL with what it receives.
+    async fn local_receiver() -> (String, mpsc::UnboundedReceiver<(HeaderMap, Bytes)>) {
+        let (received, receiver) = mpsc::unbounded_channel();
+        let receiver_app = Router::new().route(
+            "/hook",
+            post(move |headers: HeaderMap, body: Bytes| async move {
+                received.send((headers, body)).unwrap();
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, receiver_app).await });
+        (url, receiver)
+    }
+
+    async fn next_delivery(
+        receiver: &mut mpsc::UnboundedReceiver<(HeaderMap, Bytes)>,
+    ) -> (HeaderMap, Bytes) {
+        tokio::time::timeout(Duration::from_secs(10), receiver.recv())
+            .await
+            .expect("delivered in time")
+            .unwrap()
+    }
+
+    #[test]
+    fn delivers_signed_payloads_to_a_local_receiver() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (url, mut receiver) = local_receiver().await;
+            let registry = WebhookRegistry::open(None, SecretBox::generate());
+            let address = ContractAddress::parse(ADDRESS).unwrap();
+            let webhook = registry
+                .register(&address, new_webhook(&url, Some("hunter2")))
+                .unwrap();
+            assert!(webhook.signed);
+            registry.dispatch(&watched_event(7));
+
+            let (headers, body) = next_delivery(&mut receiver).await;
+            assert_eq!(
+                headers[SIGNATURE_HEADER],
+                format!("sha256={}", sign("hunter2", &body))
+            );
+            assert_eq!(headers[EVENT_HEADER], "call");
+            let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(payload["webhook_id"], webhook.id);
+            assert_eq!(payload["event"]["entry_point"], "increment");
+            assert_eq!(payload["event"]["sequence"], 7);
+        });
+    }
+
+    #[test]
+    fn delivers_to_each_webhook_in_order() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (url, mut receiver) = local_receiver().await;
+            let registry = WebhookRegistry::open(None, SecretBox::generate());
+            let address = ContractAddress::parse(ADDRESS).unwrap();
+            registry
+                .register(&address, new_webhook(&url, None))
+                .unwrap();
+            for sequence in 0..10 {
+                registry.dispatch(&watched_event(sequence));
+            }
+
+            for sequence in 0..10 {
+                let (_, body) = next_delivery(&mut receiver).await;
+                let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                assert_eq!(payload["event"]["sequence"], sequence);
+            }
+        });
+    }
+
+    #[test]
+    fn alerts_are_signed_with_the_secret_of_the_webhook_at_their_url() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (url, mut receiver) = local_receiver().await;
+            let registry = WebhookRegistry::open(None, SecretBox::generate());
+            let address = ContractAddress::parse(ADDRESS).unwrap();
+            registry
+                .register(&address, new_webhook(&url, Some("hunter2")))
+                .unwrap();
+            let alert = Alert {
+                rule_id: 3,
+                address: address.to_string(),
+                condition: AlertCondition::Updated,
+                message: "Contract was updated".to_string(),
+                transaction_hash: None,
+                fired_at: 0,
+            };
+            registry.send_alert(&url, &alert);
+
+            let (headers, body) = next_delivery(&mut receiver).await;
+            assert_eq!(
+                headers[SIGNATURE_HEADER],
+                format!("sha256={}", sign("hunter2", &body))
+            );
+            assert_eq!(headers[EVENT_HEADER], "alert");
+            let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(payload["rule_id"], 3);
+        });
+    }
+
+    #[test]
+    fn rejects_urls_that_are_not_http() {
+        let registry = WebhookRegistry::open(None, SecretBox::generate());
+        let address = ContractAddress::parse(ADDRESS).unwrap();
+        for url in ["file:///etc/passwd", "localhost:9000", "ftp://example.com"] {
+            assert!(matches!(
+                registry.register(&address, new_webhook(url, None)),
+                Err(WebhookError::InvalidUrl(_))
+            ));
+        }
+        assert!(registry.list().is_empty());
+    }
+
+    #[test]
+    fn secrets_are_saved_sealed() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("webhooks.json");
+        // as saved before secrets were sealed
+        fs::write(
+            &path,
+            serde_json::json!({
+                "next_id": 2,
+                "webhooks": [{
+                    "webhook": {
+                        "id": 1,
+                        "address": ADDRESS,
+                        "url": "http://localhost:9000",
+                        "signed": true,
+                        "created_at": 0,
+                    },
+                    "secret": "hunter2",
+                }],
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let secrets = SecretBox::generate();
+
+        let registry = WebhookRegistry::open(Some(dir), secrets.clone());
+        let address = ContractAddress::parse(ADDRESS).unwrap();
+        registry
+            .register(
+                &address,
+                new_webhook("http://localhost:9001", Some("swordfish")),
+            )
+            .unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("hunter2") && !saved.contains("swordfish"));
+
+        let reopened = WebhookRegistry::open(Some(dir), secrets);
+        let registry = reopened
+            .registry
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        assert_eq!(registry.webhooks[&1].secret.as_deref(), Some("hunter2"));
+        assert_eq!(registry.webhooks[&2].secret.as_deref(), Some("swordfish"));
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::network::Network;
+
+/// Hex length of a contract address without the network prefix.
+pub const ADDRESS_HEX_LEN: usize = 66;
+/// Hex length of a contract address with the two digit network prefix.
+pub const PREFIXED_ADDRESS_HEX_LEN: usize = ADDRESS_HEX_LEN + 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AddressError {
+    Empty,
+    InvalidCharacter { character: char, position: usize },
+    InvalidLength(usize),
+    UnknownNetworkPrefix(String),
+    NetworkMismatch { expected: Network, found: Network },
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressError::Empty => write!(f, "Enter a contract address."),
+            AddressError::InvalidCharacter {
+                character,
+                position,
+            } => write!(
+                f,
+                "Contract addresses are hex encoded, found '{}' at position {}.",
+                character, position
+            ),
+            AddressError::InvalidLength(len) => write!(
+                f,
+                "Contract addresses are {} hex characters ({} with the network prefix), got {}.",
+                ADDRESS_HEX_LEN, PREFIXED_ADDRESS_HEX_LEN, len
+            ),
+            AddressError::UnknownNetworkPrefix(prefix) => {
+                write!(f, "Unknown network prefix '{}'.", prefix)
+            }
+            AddressError::NetworkMismatch { expected, found } => write!(
+                f,
+                "This is a {} address but Compactsee is connected to {}.",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// A syntactically valid contract address, normalized to lowercase hex.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ContractAddress {
+    network: Option<Network>,
+    hex: String,
+}
+
+impl ContractAddress {
+    /// Accepts addresses with or without the network prefix, a `0x` prefix, any case and surrounding whitespace.
+    pub fn parse(input: &str) -> Result<Self, AddressError> {
+        let unpadded = input.trim();
+        let trimmed = unpadded
+            .strip_prefix("0x")
+            .or_else(|| unpadded.strip_prefix("0X"))
+            .unwrap_or(unpadded);
+        if trimmed.is_empty() {
+            return Err(AddressError::Empty);
+        }
+        if let Some((position, character)) = trimmed
+            .chars()
+            .enumerate()
+            .find(|(_, c)| !c.is_ascii_hexdigit())
+        {
+            // positions refer to what the user typed, including whitespace and the `0x`
+            let leading = input.len() - input.trim_start().len();
+            let skipped = input[..leading].chars().count() + unpadded.len() - trimmed.len();
+            return Err(AddressError::InvalidCharacter {
+                character,
+                position: skipped + position,
+            });
+        }
+        let hex = trimmed.to_ascii_lowercase();
+        match hex.len() {
+            ADDRESS_HEX_LEN => Ok(Self { network: None, hex }),
+            PREFIXED_ADDRESS_HEX_LEN => {
+                let (prefix, address) = hex.split_at(2);
+                let network = u8::from_str_radix(prefix, 16)
+                    .ok()
+                    .and_then(Network::from_id)
+                    .ok_or_else(|| AddressError::UnknownNetworkPrefix(prefix.to_string()))?;
+                Ok(Self {
+                    network: Some(network),
+                    hex: address.to_string(),
+                })
+            }
+            len => Err(AddressError::InvalidLength(len)),
+        }
+    }
+
+    /// Checks that a prefixed address belongs to `network` and pins unprefixed ones to it.
+    pub fn for_network(self, network: Network) -> Result<Self, AddressError> {
+        match self.network {
+            Some(found) if found != network => Err(AddressError::NetworkMismatch {
+                expected: network,
+                found,
+            }),
+            _ => Ok(Self {
+                network: Some(network),
+                hex: self.hex,
+            }),
+        }
+    }
+
+    pub fn network(&self) -> Option<Network> {
+        self.network
+    }
+
+    /// Address without the network prefix.
+    pub fn hex(&self) -> &str {
+        &self.hex
+    }
+
+    /// Address as the indexer expects it, with the network prefix.
+    pub fn prefixed(&self, network: Network) -> String {
+        format!("{}{}", network.prefix(), self.hex)
+    }
+}
+
+impl fmt::Display for ContractAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.network {
+            Some(network) => write!(f, "{}{}", network.prefix(), self.hex),
+            None => write!(f, "{}", self.hex),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "00cc2f4e37bb554c344c04aff7ad746d8df129a4985d3908b509712b4cd721f163";
+
+    #[test]
+    fn accepts_prefixes_case_and_whitespace() {
+        let address =
+            ContractAddress::parse(&format!(" 0x02{} \n", ADDRESS.to_uppercase())).unwrap();
+        assert_eq!(address.network(), Some(Network::TestNet));
+        assert_eq!(address.hex(), ADDRESS);
+        assert_eq!(address.to_string(), format!("02{}", ADDRESS));
+
+        let unprefixed = ContractAddress::parse(ADDRESS).unwrap();
+        assert_eq!(unprefixed.network(), None);
+        assert_eq!(
+            unprefixed.prefixed(Network::TestNet),
+            format!("02{}", ADDRESS)
+        );
+    }
+
+    #[test]
+    fn reports_invalid_characters_against_the_input() {
+        assert_eq!(
+            ContractAddress::parse("  0x00zz").unwrap_err(),
+            AddressError::InvalidCharacter {
+                character: 'z',
+                position: 6
+            }
+        );
+        assert_eq!(
+            ContractAddress::parse("g0").unwrap_err(),
+            AddressError::InvalidCharacter {
+                character: 'g',
+                position: 0
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_empty_short_and_unknown_prefixes() {
+        assert_eq!(
+            ContractAddress::parse(" 0x ").unwrap_err(),
+            AddressError::Empty
+        );
+        assert_eq!(
+            ContractAddress::parse("abcd").unwrap_err(),
+            AddressError::InvalidLength(4)
+        );
+        assert_eq!(
+            ContractAddress::parse(&format!("09{}", ADDRESS)).unwrap_err(),
+            AddressError::UnknownNetworkPrefix("09".to_string())
+        );
+    }
+
+    #[test]
+    fn for_network_pins_unprefixed_and_refuses_other_networks() {
+        let pinned = ContractAddress::parse(ADDRESS)
+            .unwrap()
+            .for_network(Network::TestNet)
+            .unwrap();
+        assert_eq!(
+            pinned,
+            ContractAddress::parse(&format!("02{}", ADDRESS)).unwrap()
+        );
+        assert_eq!(
+            pinned.for_network(Network::MainNet).unwrap_err(),
+            AddressError::NetworkMismatch {
+                expected: Network::MainNet,
+                found: Network::TestNet
+            }
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::domain::{
+    path::JsonPath,
+    predicate::{as_number, Predicate},
+    ActionKind, ContractEvent,
+};
+
+/// What an alert rule watches for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// The number at the path went down.
+    Decreased { path: JsonPath },
+    /// The map, set or array at the path gained more than this many entries in one action.
+    GrewBy { path: JsonPath, entries: usize },
+    /// Nobody called the contract for this many seconds.
+    NoCallsFor { seconds: u64 },
+    /// The contract was updated, which is how its verifier keys change.
+    Updated,
+    /// The decoded state starts satisfying the predicate. It fires again only once the predicate stopped holding.
+    Matches(Predicate),
+}
+
+impl AlertCondition {
+    /// Checks a new event against the state before it, returning what happened if the rule fires. Without a
+    /// state before it, only [`AlertCondition::Updated`] and [`AlertCondition::Matches`] can fire.
+    ///
+    /// [`AlertCondition::NoCallsFor`] never fires here, it depends on time passing rather than on events.
+    pub fn fires(&self, previous: Option<&Value>, event: &ContractEvent) -> Option<String> {
+        let current = event.decoded_state.as_ref();
+        match self {
+            AlertCondition::Decreased { path } => {
+                let before = previous.and_then(|state| path.select_first(state)).and_then(as_number)?;
+                let after = current.and_then(|state| path.select_first(state)).and_then(as_number)?;
+                (after < before).then(|| format!("{} decreased from {} to {}", path, before, after))
+            }
+            AlertCondition::GrewBy { path, entries } => {
+                let before = previous.and_then(|state| path.select_first(state)).and_then(size)?;
+                let after = current.and_then(|state| path.select_first(state)).and_then(size)?;
+                let grown = after.saturating_sub(before);
+                (grown > *entries).then(|| {
+                    format!("{} grew by {} entries, from {} to {}", path, grown, before, after)
+                })
+            }
+            AlertCondition::NoCallsFor { .. } => None,
+            AlertCondition::Updated => (event.kind() == ActionKind::Update)
+                .then(|| "Contract was updated, its verifier keys may have changed".to_string()),
+            AlertCondition::Matches(predicate) => {
+                let held = previous.is_some_and(|state| predicate.holds(state));
+                current
+                    .filter(|state| !held && predicate.holds(state))
+                    .map(|_| format!("{} holds", predicate))
+            }
+        }
+    }
+}
+
+/// Number of entries of a collection, `None` for scalars.
+pub fn size(value: &Value) -> Option<usize> {
+    match value {
+        Value::Array(items) => Some(items.len()),
+        Value::Object(entries) => Some(entries.len()),
+        _ => None,
+    }
+}
+
+impl fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertCondition::Decreased { path } => write!(f, "{} decreased", path),
+            AlertCondition::GrewBy { path, entries } => {
+                write!(f, "{} grew by more than {} entries", path, entries)
+            }
+            AlertCondition::NoCallsFor { seconds } => write!(f, "no calls for {}s", seconds),
+            AlertCondition::Updated => write!(f, "contract updated"),
+            AlertCondition::Matches(predicate) => write!(f, "{}", predicate),
+        }
+    }
+}
+
+/// Where a firing alert is sent, besides the UI.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSink {
+    Log,
+    Webhook { url: String },
+    Email { to: String },
+}
+
+impl fmt::Display for AlertSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertSink::Log => write!(f, "log"),
+            AlertSink::Webhook { url } => write!(f, "webhook {}", url),
+            AlertSink::Email { to } => write!(f, "email {}", to),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub id: u64,
+    pub address: String,
+    pub condition: AlertCondition,
+    #[serde(default)]
+    pub sinks: Vec<AlertSink>,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewAlertRule {
+    pub address: String,
+    pub condition: AlertCondition,
+    #[serde(default)]
+    pub sinks: Vec<AlertSink>,
+}
+
+/// A rule that fired.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub rule_id: u64,
+    pub address: String,
+    pub condition: AlertCondition,
+    pub message: String,
+    /// Transaction that triggered the alert, absent for alerts about inactivity.
+    pub transaction_hash: Option<String>,
+    pub fired_at: u64,
+}
+
+/// Rules and recently fired alerts, shown on the admin page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertOverview {
+    pub rules: Vec<AlertRule>,
+    /// Most recent first.
+    pub alerts: Vec<Alert>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::domain::predicate::Condition;
+
+    fn call(state: Value) -> ContractEvent {
+        ContractEvent::test_call().with_state(state)
+    }
+
+    fn path(path: &str) -> JsonPath {
+        JsonPath::parse(path).unwrap()
+    }
+
+    #[test]
+    fn decreased_needs_the_state_before() {
+        let condition = AlertCondition::Decreased { path: path("$[0]") };
+        assert_eq!(
+            condition
+                .fires(Some(&json!([5])), &call(json!([3])))
+                .as_deref(),
+            Some("$[0] decreased from 5 to 3")
+        );
+        assert_eq!(condition.fires(Some(&json!([3])), &call(json!([5]))), None);
+        assert_eq!(condition.fires(None, &call(json!([3]))), None);
+    }
+
+    #[test]
+    fn grew_by_counts_entries_added_in_one_action() {
+        let condition = AlertCondition::GrewBy {
+            path: path("$[1]"),
+            entries: 1,
+        };
+        let before = json!([0, { "1": 1 }]);
+        assert_eq!(
+            condition.fires(Some(&before), &call(json!([0, { "1": 1, "2": 2 }]))),
+            None
+        );
+        assert_eq!(
+            condition
+                .fires(Some(&before), &call(json!([0, { "1": 1, "2": 2, "3": 3 }])))
+                .as_deref(),
+            Some("$[1] grew by 2 entries, from 1 to 3")
+        );
+        assert_eq!(condition.fires(Some(&before), &call(json!([0, {}]))), None);
+    }
+
+    #[test]
+    fn updated_fires_on_updates_only() {
+        let state = json!([0]);
+        let update = call(state.clone()).with_type("ContractUpdate");
+        assert!(AlertCondition::Updated
+            .fires(Some(&state), &update)
+            .is_some());
+        assert!(AlertCondition::Updated
+            .fires(Some(&state), &call(state.clone()))
+            .is_none());
+        assert!(AlertCondition::NoCallsFor { seconds: 1 }
+            .fires(None, &call(state))
+            .is_none());
+    }
+
+    #[test]
+    fn matches_fires_when_the_predicate_starts_to_hold() {
+        let condition =
+            AlertCondition::Matches(Predicate::new(path("$[0]"), Condition::GreaterThan(10.0)));
+        assert!(condition.fires(None, &call(json!([11]))).is_some());
+        assert!(condition
+            .fires(Some(&json!([5])), &call(json!([11])))
+            .is_some());
+        assert!(condition
+            .fires(Some(&json!([11])), &call(json!([12])))
+            .is_none());
+        assert!(condition
+            .fires(Some(&json!([12])), &call(json!([5])))
+            .is_none());
+    }
+}
@@ -0,0 +1,341 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{ActionKind, ContractEvent};
+
+/// Periods listed as the busiest.
+const BUSIEST_PERIODS: usize = 5;
+
+/// Length of the periods activity is counted in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interval {
+    #[default]
+    Hour,
+    Day,
+}
+
+impl Interval {
+    pub const ALL: [Interval; 2] = [Interval::Hour, Interval::Day];
+
+    pub fn millis(&self) -> u64 {
+        match self {
+            Interval::Hour => 3_600_000,
+            Interval::Day => 86_400_000,
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interval::Hour => write!(f, "hour"),
+            Interval::Day => write!(f, "day"),
+        }
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "hour" | "hourly" => Ok(Interval::Hour),
+            "day" | "daily" => Ok(Interval::Day),
+            other => Err(format!("unknown interval '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionCounts {
+    pub deploys: u64,
+    pub calls: u64,
+    pub updates: u64,
+}
+
+impl ActionCounts {
+    fn add(&mut self, kind: ActionKind) {
+        match kind {
+            ActionKind::Deploy => self.deploys += 1,
+            ActionKind::Call => self.calls += 1,
+            ActionKind::Update => self.updates += 1,
+            ActionKind::Unknown => {}
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.deploys + self.calls + self.updates
+    }
+}
+
+/// Contract actions within one period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Period {
+    /// Milliseconds since the unix epoch at which the period starts.
+    pub start: u64,
+    #[serde(flatten)]
+    pub actions: ActionCounts,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryPointCalls {
+    pub entry_point: String,
+    pub calls: u64,
+}
+
+/// A block that contained actions on the contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockActivity {
+    pub height: u64,
+    pub timestamp: u64,
+    /// Distinct transactions in the block that acted on the contract.
+    pub transactions: u64,
+}
+
+/// Fees of the distinct transactions that acted on the contract, in the smallest unit of DUST. Written as
+/// decimal strings like the indexer reports them, totals can exceed what a JSON number holds exactly.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeTotals {
+    /// Transactions the indexer reported fees for.
+    pub transactions: u64,
+    pub paid: String,
+    pub estimated: String,
+}
+
+/// How a contract has been used, aggregated over every event the server recorded for it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractAnalytics {
+    pub address: String,
+    pub interval: Interval,
+    pub events: u64,
+    pub actions: ActionCounts,
+    /// Block time in milliseconds since the unix epoch. Events without a block are counted but left out of
+    /// the time range and the periods.
+    pub first_event_at: Option<u64>,
+    pub last_event_at: Option<u64>,
+    pub fees: FeeTotals,
+    /// Most called first.
+    pub entry_points: Vec<EntryPointCalls>,
+    /// Oldest first, periods without any action are left out.
+    pub periods: Vec<Period>,
+    /// The periods with the most actions, busiest first.
+    pub busiest: Vec<Period>,
+    /// Oldest first.
+    pub blocks: Vec<BlockActivity>,
+}
+
+/// Collects [`ContractAnalytics`] one event at a time, so a history never has to be in memory as a whole.
+pub struct AnalyticsBuilder {
+    interval: Interval,
+    events: u64,
+    actions: ActionCounts,
+    first_event_at: Option<u64>,
+    last_event_at: Option<u64>,
+    entry_points: HashMap<String, u64>,
+    periods: BTreeMap<u64, ActionCounts>,
+    blocks: BTreeMap<u64, (u64, HashSet<String>)>,
+    fee_transactions: u64,
+    paid_fees: u128,
+    estimated_fees: u128,
+}
+
+impl AnalyticsBuilder {
+    pub fn new(interval: Interval) -> Self {
+        Self {
+            interval,
+            events: 0,
+            actions: ActionCounts::default(),
+            first_event_at: None,
+            last_event_at: None,
+            entry_points: HashMap::new(),
+            periods: BTreeMap::new(),
+            blocks: BTreeMap::new(),
+            fee_transactions: 0,
+            paid_fees: 0,
+            estimated_fees: 0,
+        }
+    }
+
+    pub fn add(&mut self, event: &ContractEvent) {
+        let kind = event.kind();
+        self.events += 1;
+        self.actions.add(kind);
+        if let Some(entry_point) = event.entry_point_name() {
+            *self.entry_points.entry(entry_point).or_default() += 1;
+        }
+        let Some(transaction) = &event.transaction else {
+            return;
+        };
+        let at = transaction.block.timestamp;
+        self.first_event_at = Some(self.first_event_at.map_or(at, |first| first.min(at)));
+        self.last_event_at = Some(self.last_event_at.map_or(at, |last| last.max(at)));
+        let start = at - at % self.interval.millis();
+        self.periods.entry(start).or_default().add(kind);
+
+        let (_, transactions) = self
+            .blocks
+            .entry(transaction.block.height)
+            .or_insert_with(|| (at, HashSet::new()));
+        // a transaction acting on the contract several times paid its fees once
+        if transactions.insert(transaction.hash.clone()) {
+            if let Some(fees) = &transaction.fees {
+                self.fee_transactions += 1;
+                self.paid_fees += fees.paid_fees.parse::<u128>().unwrap_or_default();
+                self.estimated_fees += fees.estimated_fees.parse::<u128>().unwrap_or_default();
+            }
+        }
+    }
+
+    /// The analytics so far, more events can be added afterwards.
+    pub fn finish(&self, address: String) -> ContractAnalytics {
+        let mut entry_points: Vec<EntryPointCalls> = self
+            .entry_points
+            .iter()
+            .map(|(entry_point, calls)| EntryPointCalls {
+                entry_point: entry_point.clone(),
+                calls: *calls,
+            })
+            .collect();
+        entry_points.sort_by(|a, b| b.calls.cmp(&a.calls).then(a.entry_point.cmp(&b.entry_point)));
+
+        let periods: Vec<Period> = self
+            .periods
+            .iter()
+            .map(|(start, actions)| Period {
+                start: *start,
+                actions: *actions,
+            })
+            .collect();
+        let mut busiest = periods.clone();
+        // stable, so ties keep the earlier period first
+        busiest.sort_by_key(|period| std::cmp::Reverse(period.actions.total()));
+        busiest.truncate(BUSIEST_PERIODS);
+
+        ContractAnalytics {
+            address,
+            interval: self.interval,
+            events: self.events,
+            actions: self.actions,
+            first_event_at: self.first_event_at,
+            last_event_at: self.last_event_at,
+            fees: FeeTotals {
+                transactions: self.fee_transactions,
+                paid: self.paid_fees.to_string(),
+                estimated: self.estimated_fees.to_string(),
+            },
+            entry_points,
+            periods,
+            busiest,
+            blocks: self
+                .blocks
+                .iter()
+                .map(|(height, (timestamp, transactions))| BlockActivity {
+                    height: *height,
+                    timestamp: *timestamp,
+                    transactions: transactions.len() as u64,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 3_600_000;
+
+    fn event(type_name: &str, hash: &str, height: u64, timestamp: u64) -> ContractEvent {
+        ContractEvent::test_call()
+            .with_type(type_name)
+            .in_transaction(hash, height, timestamp)
+            .with_fees("10000000000000000000", "12")
+    }
+
+    fn call(entry_point: &str, hash: &str, height: u64, timestamp: u64) -> ContractEvent {
+        event("ContractCall", hash, height, timestamp).with_entry_point(entry_point)
+    }
+
+    #[test]
+    fn counts_actions_per_period_entry_point_and_block() {
+        let mut builder = AnalyticsBuilder::new(Interval::Hour);
+        builder.add(&event("ContractDeploy", "a", 1, HOUR / 2));
+        builder.add(&call("mint", "b", 2, HOUR + 1));
+        builder.add(&call("mint", "b", 2, HOUR + 1));
+        builder.add(&call("burn", "c", 2, HOUR + 1));
+        let analytics = builder.finish("addr".to_string());
+
+        assert_eq!(analytics.events, 4);
+        assert_eq!(
+            analytics.actions,
+            ActionCounts {
+                deploys: 1,
+                calls: 3,
+                updates: 0
+            }
+        );
+        assert_eq!(
+            (analytics.first_event_at, analytics.last_event_at),
+            (Some(HOUR / 2), Some(HOUR + 1))
+        );
+        assert_eq!(
+            analytics.entry_points,
+            [
+                EntryPointCalls {
+                    entry_point: "mint".to_string(),
+                    calls: 2
+                },
+                EntryPointCalls {
+                    entry_point: "burn".to_string(),
+                    calls: 1
+                },
+            ]
+        );
+        let starts: Vec<u64> = analytics.periods.iter().map(|period| period.start).collect();
+        assert_eq!(starts, [0, HOUR]);
+        assert_eq!(analytics.busiest[0].start, HOUR);
+        assert_eq!(analytics.blocks[1].transactions, 2);
+    }
+
+    #[test]
+    fn sums_fees_once_per_transaction() {
+        let mut builder = AnalyticsBuilder::new(Interval::Day);
+        builder.add(&call("mint", "b", 2, 0));
+        builder.add(&call("burn", "b", 2, 0));
+        builder.add(&call("mint", "c", 3, 0));
+        let mut unreported = call("mint", "d", 3, 0);
+        unreported.transaction.as_mut().unwrap().fees = None;
+        builder.add(&unreported);
+        assert_eq!(
+            builder.finish("addr".to_string()).fees,
+            FeeTotals {
+                transactions: 2,
+                paid: "20000000000000000000".to_string(),
+                estimated: "24".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn events_without_a_block_are_only_counted() {
+        let mut builder = AnalyticsBuilder::new(Interval::Hour);
+        let mut pending = call("mint", "a", 1, 0);
+        pending.transaction = None;
+        builder.add(&pending);
+        let analytics = builder.finish("addr".to_string());
+        assert_eq!((analytics.events, analytics.actions.calls), (1, 1));
+        assert_eq!(analytics.first_event_at, None);
+        assert!(analytics.periods.is_empty() && analytics.blocks.is_empty());
+
+        // finishing leaves the builder usable
+        builder.add(&call("mint", "b", 2, HOUR));
+        let analytics = builder.finish("addr".to_string());
+        assert_eq!((analytics.events, analytics.periods.len()), (2, 1));
+    }
+}
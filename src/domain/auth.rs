@@ -0,0 +1,114 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// What an API key may do. Each scope includes the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Contract events, state, exports and streams.
+    Read,
+    /// Also the watchlist, webhooks and alert rules.
+    Watch,
+    /// Also API keys, accounts and the admin page.
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 3] = [Scope::Read, Scope::Watch, Scope::Admin];
+
+    pub fn allows(&self, required: Scope) -> bool {
+        *self >= required
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Read => write!(f, "read"),
+            Scope::Watch => write!(f, "watch"),
+            Scope::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "read" => Ok(Scope::Read),
+            "watch" => Ok(Scope::Watch),
+            "admin" => Ok(Scope::Admin),
+            other => Err(format!("unknown scope '{}'", other)),
+        }
+    }
+}
+
+/// An API key as listed, the secret itself is only shown once when the key is created.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: u64,
+    pub name: String,
+    pub scope: Scope,
+    /// Start of the secret, enough to tell keys apart.
+    pub prefix: String,
+    /// Milliseconds since the unix epoch.
+    pub created_at: u64,
+    #[serde(default)]
+    pub last_used_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scope: Scope,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    /// Sent as `Authorization: Bearer <secret>` or `X-Api-Key: <secret>`.
+    pub secret: String,
+}
+
+/// A local account that can sign in to the admin page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    pub username: String,
+    /// Milliseconds since the unix epoch.
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthOverview {
+    pub keys: Vec<ApiKey>,
+    pub accounts: Vec<Account>,
+    /// The account signed in to the admin page.
+    pub signed_in_as: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_scope_includes_the_ones_before_it() {
+        for (granted, allowed) in [
+            (Scope::Read, vec![Scope::Read]),
+            (Scope::Watch, vec![Scope::Read, Scope::Watch]),
+            (Scope::Admin, Scope::ALL.to_vec()),
+        ] {
+            for required in Scope::ALL {
+                assert_eq!(
+                    granted.allows(required),
+                    allowed.contains(&required),
+                    "{} allows {}",
+                    granted,
+                    required
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::network::Network;
+
+/// How a client follows a contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionOrigin {
+    /// The contract page, over the server function websocket.
+    Browser,
+    /// The server-sent events stream of the HTTP API.
+    Api,
+}
+
+impl fmt::Display for SessionOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionOrigin::Browser => write!(f, "browser"),
+            SessionOrigin::Api => write!(f, "api"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionStats {
+    /// Contract events handed on, to the client for sessions and to the session for subscriptions.
+    pub events_delivered: u64,
+    /// Milliseconds since the unix epoch.
+    pub last_event_at: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+/// A client following a contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: u64,
+    pub origin: SessionOrigin,
+    /// Absent until the client picked a contract.
+    pub address: Option<String>,
+    pub network: Network,
+    /// Milliseconds since the unix epoch.
+    pub started_at: u64,
+    #[serde(flatten)]
+    pub stats: ConnectionStats,
+}
+
+/// An open `contractActions` subscription on the indexer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionInfo {
+    pub id: u64,
+    pub address: String,
+    pub network: Network,
+    /// Readers of the subscription's events, its session or the watchlist's webhooks and alerts.
+    pub clients: usize,
+    /// Milliseconds since the unix epoch.
+    pub started_at: u64,
+    #[serde(flatten)]
+    pub stats: ConnectionStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionOverview {
+    /// Oldest first.
+    pub sessions: Vec<SessionInfo>,
+    /// Oldest first.
+    pub subscriptions: Vec<SubscriptionInfo>,
+    /// Seconds after which browser subscriptions are closed.
+    pub idle_timeout: u64,
+    /// Milliseconds since the unix epoch at which the overview was taken, uptimes are relative to it.
+    pub at: u64,
+}
+
+/// Compact duration such as `2h 05m` or `41s`.
+pub fn format_duration(millis: u64) -> String {
+    let secs = millis / 1000;
+    match (secs / 86_400, secs / 3600 % 24, secs / 60 % 60, secs % 60) {
+        (0, 0, 0, s) => format!("{}s", s),
+        (0, 0, m, s) => format!("{}m {:02}s", m, s),
+        (0, h, m, _) => format!("{}h {:02}m", h, m),
+        (d, h, _, _) => format!("{}d {:02}h", d, h),
+    }
+}
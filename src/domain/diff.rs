@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::domain::path::JsonPath;
+
+/// A single difference between two decoded states.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateChange {
+    pub path: JsonPath,
+    /// `None` when the value was added.
+    pub before: Option<Value>,
+    /// `None` when the value was removed.
+    pub after: Option<Value>,
+}
+
+/// Leaf level differences between `before` and `after`, objects and arrays are compared member by member.
+pub fn diff(before: &Value, after: &Value) -> Vec<StateChange> {
+    let mut changes = Vec::new();
+    diff_at(JsonPath::root(), Some(before), Some(after), &mut changes);
+    changes
+}
+
+fn diff_at(
+    path: JsonPath,
+    before: Option<&Value>,
+    after: Option<&Value>,
+    changes: &mut Vec<StateChange>,
+) {
+    match (before, after) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            for (key, old_value) in old {
+                diff_at(path.child(key), Some(old_value), new.get(key), changes);
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    diff_at(path.child(key), None, Some(new_value), changes);
+                }
+            }
+        }
+        (Some(Value::Array(old)), Some(Value::Array(new))) => {
+            for index in 0..old.len().max(new.len()) {
+                diff_at(path.index(index), old.get(index), new.get(index), changes);
+            }
+        }
+        (old, new) if old != new => changes.push(StateChange {
+            path,
+            before: old.cloned(),
+            after: new.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+impl std::fmt::Display for StateChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.before, &self.after) {
+            (None, Some(after)) => write!(f, "+ {} = {}", self.path, after),
+            (Some(before), None) => write!(f, "- {} = {}", self.path, before),
+            (Some(before), Some(after)) => {
+                write!(f, "~ {}: {} -> {}", self.path, before, after)
+            }
+            (None, None) => write!(f, "  {}", self.path),
+        }
+    }
+}
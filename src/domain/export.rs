@@ -0,0 +1,421 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::domain::{
+    diff::{diff, StateChange},
+    indexer::{BlockHeader, TransactionRef},
+    path::JsonPath,
+    ActionKind, ContractEvent,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// A pretty printed JSON array.
+    Json,
+    /// One JSON record per line.
+    Ndjson,
+    /// One row per event and one column per decoded ledger field.
+    Csv,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Json, ExportFormat::Ndjson, ExportFormat::Csv];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "ndjson" | "jsonl" => Ok(ExportFormat::Ndjson),
+            "csv" => Ok(ExportFormat::Csv),
+            other => Err(format!("unknown export format '{}'", other)),
+        }
+    }
+}
+
+/// One event of an exported session, as written to JSON and NDJSON files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedEvent {
+    pub sequence: Option<u64>,
+    pub received_at: u64,
+    pub kind: ActionKind,
+    pub address: String,
+    pub transaction_hash: Option<String>,
+    pub block_height: Option<u64>,
+    pub block_hash: Option<String>,
+    pub block_timestamp: Option<u64>,
+    /// Hex encoded entry point of a call.
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    pub state_hex: String,
+    #[serde(default)]
+    pub chain_state_hex: String,
+    pub decoded_state: Option<Value>,
+    /// Differences to the decoded state of the event before, empty for the first one.
+    #[serde(default)]
+    pub changes: Vec<StateChange>,
+}
+
+impl ExportedEvent {
+    pub fn new(
+        sequence: Option<u64>,
+        received_at: u64,
+        event: &ContractEvent,
+        previous_state: Option<&Value>,
+    ) -> Self {
+        let transaction = event.transaction.as_ref();
+        let changes = match (previous_state, &event.decoded_state) {
+            (Some(before), Some(after)) => diff(before, after),
+            _ => Vec::new(),
+        };
+        Self {
+            sequence,
+            received_at,
+            kind: event.kind(),
+            address: event.address.clone(),
+            transaction_hash: transaction.map(|t| t.hash.clone()),
+            block_height: transaction.map(|t| t.block.height),
+            block_hash: transaction.map(|t| t.block.hash.clone()),
+            block_timestamp: transaction.map(|t| t.block.timestamp),
+            entry_point: event.entry_point.clone(),
+            state_hex: event.state.clone(),
+            chain_state_hex: event.chain_state.clone(),
+            decoded_state: event.decoded_state.clone(),
+            changes,
+        }
+    }
+
+    /// The contract action the record was exported from.
+    pub fn into_event(self) -> ContractEvent {
+        let type_name = match self.kind {
+            ActionKind::Deploy => "ContractDeploy",
+            ActionKind::Call => "ContractCall",
+            ActionKind::Update => "ContractUpdate",
+            ActionKind::Unknown => "Unknown",
+        };
+        let transaction = match (self.transaction_hash, self.block_hash, self.block_height) {
+            (Some(hash), Some(block_hash), Some(height)) => Some(TransactionRef {
+                hash,
+                block: BlockHeader {
+                    hash: block_hash,
+                    height,
+                    timestamp: self.block_timestamp.unwrap_or_default(),
+                },
+                fees: None,
+            }),
+            _ => None,
+        };
+        ContractEvent {
+            type_name: type_name.to_string(),
+            state: self.state_hex,
+            address: self.address,
+            chain_state: self.chain_state_hex,
+            transaction,
+            entry_point: self.entry_point,
+            decoded_state: self.decoded_state,
+            sequence: self.sequence,
+        }
+    }
+}
+
+/// Columns every CSV export starts with, followed by one column per decoded field.
+pub const CSV_COLUMNS: [&str; 9] = [
+    "sequence",
+    "received_at",
+    "kind",
+    "address",
+    "transaction_hash",
+    "block_height",
+    "block_timestamp",
+    "state_hex",
+    "changes",
+];
+
+/// Encodes a history one event at a time, so it is written in a single pass without holding it in memory.
+///
+/// CSV files get one column per top-level field of the first decoded state. Compact lays the ledger out
+/// positionally, so every state of a contract has the same fields.
+pub struct ExportWriter {
+    format: ExportFormat,
+    /// Decoded field columns of a CSV export, known once the first decoded state was written.
+    fields: Option<Vec<JsonPath>>,
+    /// CSV records that came before the first decoded state, written once the columns are known.
+    pending: Vec<ExportedEvent>,
+    written: usize,
+}
+
+impl ExportWriter {
+    pub fn new(format: ExportFormat) -> Self {
+        Self {
+            format,
+            fields: None,
+            pending: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Text for the next event, empty for CSV records held back until the columns are known.
+    pub fn write(&mut self, event: ExportedEvent) -> serde_json::Result<String> {
+        self.written += 1;
+        match self.format {
+            ExportFormat::Json => serde_json::to_string_pretty(&event)
+                .map(|json| format!("{}\n{}", if self.written == 1 { "[" } else { "," }, json)),
+            ExportFormat::Ndjson => serde_json::to_string(&event).map(|json| json + "\n"),
+            ExportFormat::Csv => Ok(match (&self.fields, &event.decoded_state) {
+                (Some(fields), _) => csv_row(&event, fields),
+                (None, Some(state)) => {
+                    let fields = csv_fields(state);
+                    let mut out = csv_header(&fields);
+                    for pending in self.pending.drain(..) {
+                        out.push_str(&csv_row(&pending, &fields));
+                    }
+                    out.push_str(&csv_row(&event, &fields));
+                    self.fields = Some(fields);
+                    out
+                }
+                (None, None) => {
+                    self.pending.push(event);
+                    String::new()
+                }
+            }),
+        }
+    }
+
+    /// Text that ends the export.
+    pub fn finish(self) -> String {
+        match self.format {
+            ExportFormat::Json if self.written == 0 => "[]\n".to_string(),
+            ExportFormat::Json => "\n]\n".to_string(),
+            ExportFormat::Ndjson => String::new(),
+            ExportFormat::Csv if self.fields.is_some() => String::new(),
+            ExportFormat::Csv => {
+                let mut out = csv_header(&[]);
+                for pending in &self.pending {
+                    out.push_str(&csv_row(pending, &[]));
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Exports events the browser collected, in order. They carry no receive time, so their block time is used.
+pub fn export_events(events: &[ContractEvent], format: ExportFormat) -> serde_json::Result<String> {
+    let mut writer = ExportWriter::new(format);
+    let mut out = String::new();
+    let mut previous_state = None;
+    for event in events {
+        let received_at = event.transaction.as_ref().map(|t| t.block.timestamp);
+        out.push_str(&writer.write(ExportedEvent::new(
+            event.sequence,
+            received_at.unwrap_or_default(),
+            event,
+            previous_state,
+        ))?);
+        if event.decoded_state.is_some() {
+            previous_state = event.decoded_state.as_ref();
+        }
+    }
+    out.push_str(&writer.finish());
+    Ok(out)
+}
+
+/// One column per ledger field, `$[0]`, `$[1]` and so on.
+fn csv_fields(state: &Value) -> Vec<JsonPath> {
+    match state {
+        Value::Array(items) => (0..items.len())
+            .map(|index| JsonPath::root().index(index))
+            .collect(),
+        Value::Object(entries) => entries
+            .keys()
+            .map(|key| JsonPath::root().child(key))
+            .collect(),
+        _ => vec![JsonPath::root()],
+    }
+}
+
+fn csv_header(fields: &[JsonPath]) -> String {
+    let mut columns: Vec<String> = CSV_COLUMNS
+        .iter()
+        .map(|column| column.to_string())
+        .collect();
+    columns.extend(fields.iter().map(ToString::to_string));
+    csv_line(columns)
+}
+
+/// Scalars are written as they are, maps, sets and arrays as compact JSON.
+fn csv_row(event: &ExportedEvent, fields: &[JsonPath]) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    let mut cells = vec![
+        optional(event.sequence.map(|s| s.to_string())),
+        event.received_at.to_string(),
+        event.kind.to_string(),
+        event.address.clone(),
+        optional(event.transaction_hash.clone()),
+        optional(event.block_height.map(|h| h.to_string())),
+        optional(event.block_timestamp.map(|t| t.to_string())),
+        event.state_hex.clone(),
+        event
+            .changes
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; "),
+    ];
+    cells.extend(fields.iter().map(|field| {
+        event
+            .decoded_state
+            .as_ref()
+            .and_then(|state| field.select_first(state))
+            .map(|value| match value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            })
+            .unwrap_or_default()
+    }));
+    csv_line(cells)
+}
+
+fn csv_line(cells: Vec<String>) -> String {
+    let mut line = cells
+        .iter()
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+/// Reads a session exported as JSON or NDJSON back into its events.
+pub fn parse_session(text: &str) -> Result<Vec<ExportedEvent>, String> {
+    let text = text.trim_start_matches('\u{feff}').trim();
+    if text.starts_with('[') {
+        return serde_json::from_str(text).map_err(|e| format!("invalid JSON export: {}", e));
+    }
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line).map_err(|e| format!("line {}: {}", number + 1, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(state: Option<Value>, height: u64) -> ContractEvent {
+        ContractEvent {
+            decoded_state: state,
+            ..ContractEvent::test_call()
+                .at_height(height)
+                .with_raw_state(&format!("{:02x}", height))
+                .with_address("00aa")
+                .with_sequence(height)
+        }
+    }
+
+    #[test]
+    fn json_and_ndjson_exports_read_back() {
+        let events = vec![event(Some(json!([1])), 1), event(Some(json!([2])), 2)];
+        for format in [ExportFormat::Json, ExportFormat::Ndjson] {
+            let exported = parse_session(&export_events(&events, format).unwrap()).unwrap();
+            assert_eq!(exported.len(), 2);
+            assert_eq!(exported[1].received_at, 2000);
+            assert_eq!(exported[1].changes.len(), 1);
+            assert_eq!(
+                exported[1].clone().into_event().decoded_state,
+                Some(json!([2]))
+            );
+        }
+        assert_eq!(export_events(&[], ExportFormat::Json).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn csv_has_a_column_per_ledger_field() {
+        let events = vec![
+            event(None, 1),
+            event(Some(json!([3, { "1": "a,b" }])), 2),
+            event(Some(json!([4, {}])), 3),
+        ];
+        let csv = export_events(&events, ExportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with(",changes,$[0],$[1]"));
+        // the record before the first decoded state is held back until the columns are known
+        assert!(lines[1].starts_with("1,1000,call,00aa,tx1,1,1000,01,,"));
+        assert!(lines[1].ends_with(",,"));
+        assert!(lines[2].ends_with(",3,\"{\"\"1\"\":\"\"a,b\"\"}\""));
+        assert!(lines[3].ends_with(",4,{}"));
+    }
+
+    #[test]
+    fn csv_without_decoded_states_has_the_fixed_columns_only() {
+        let csv = export_events(&[event(None, 1)], ExportFormat::Csv).unwrap();
+        assert_eq!(csv.lines().next(), Some(CSV_COLUMNS.join(",").as_str()));
+        assert_eq!(csv.lines().count(), 2);
+    }
+
+    #[test]
+    fn parses_sessions_with_a_byte_order_mark_and_blank_lines() {
+        let record = serde_json::to_string(&ExportedEvent::new(
+            Some(1),
+            5,
+            &event(Some(json!([1])), 1),
+            None,
+        ))
+        .unwrap();
+        let ndjson = format!("\u{feff}{}\n\n{}\r\n", record, record);
+        assert_eq!(parse_session(&ndjson).unwrap().len(), 2);
+        let json = format!("\u{feff} [{}]", record);
+        assert_eq!(parse_session(&json).unwrap()[0].received_at, 5);
+        assert!(parse_session("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_the_line_of_a_broken_record() {
+        let record =
+            serde_json::to_string(&ExportedEvent::new(None, 5, &event(None, 1), None)).unwrap();
+        let error = parse_session(&format!("{}\n{{\"sequence\": 1}}\n", record)).unwrap_err();
+        assert!(error.starts_with("line 2:"), "{}", error);
+        assert!(parse_session("[{}]")
+            .unwrap_err()
+            .starts_with("invalid JSON export"));
+    }
+}
@@ -0,0 +1,467 @@
+use serde_json::Value;
+
+use crate::domain::{
+    diff::{diff, StateChange},
+    path::JsonPath,
+    ActionKind, ContractEvent,
+};
+
+/// What the search box of the contract panel looks for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateSearch {
+    /// `$[1] changed`, events where something at or below the path differs from the event before.
+    Changed(JsonPath),
+    /// Any key or value of the decoded state, or the raw state hex, containing the text. Case is ignored and so is a leading `0x`.
+    Text(String),
+}
+
+impl StateSearch {
+    /// `None` for an empty query. Anything that is not `<path> changed`, with whitespace before `changed`, is
+    /// searched for as text.
+    pub fn parse(query: &str) -> Option<Self> {
+        let query = query.trim();
+        if query.is_empty() {
+            return None;
+        }
+        let path = query
+            .strip_suffix("changed")
+            .filter(|path| path.ends_with(char::is_whitespace))
+            .map(str::trim_end);
+        if let Some(path) = path {
+            if path.starts_with('$') {
+                if let Ok(path) = JsonPath::parse(path) {
+                    return Some(StateSearch::Changed(path));
+                }
+            }
+        }
+        let text = query.strip_prefix("0x").unwrap_or(query);
+        Some(StateSearch::Text(text.to_ascii_lowercase()))
+    }
+
+    /// `changes` are the differences between the event and the one before, see [`changes`].
+    pub fn matches(&self, event: &ContractEvent, changes: &[StateChange]) -> bool {
+        match self {
+            StateSearch::Changed(path) => changes.iter().any(|change| path.covers(&change.path)),
+            StateSearch::Text(needle) => {
+                event.state.to_ascii_lowercase().contains(needle.as_str())
+                    || event
+                        .decoded_state
+                        .as_ref()
+                        .is_some_and(|state| contains_text(state, needle))
+            }
+        }
+    }
+
+    /// Whether a whole line of the state is highlighted, text searches only highlight the matching part.
+    pub fn highlights(&self, line: &StateLine, changes: &[StateChange]) -> bool {
+        match self {
+            StateSearch::Changed(path) => {
+                path.covers(&line.path) && changes.iter().any(|change| change.path.covers(&line.path))
+            }
+            StateSearch::Text(_) => false,
+        }
+    }
+
+    /// Splits `text` into pieces, `true` marking the ones a text search matched.
+    pub fn split<'a>(&self, text: &'a str) -> Vec<(&'a str, bool)> {
+        let StateSearch::Text(needle) = self else {
+            return vec![(text, false)];
+        };
+        if needle.is_empty() {
+            return vec![(text, false)];
+        }
+        // ascii lowercasing keeps byte offsets, so they can be used on the original text
+        let lowered = text.to_ascii_lowercase();
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        for (offset, _) in lowered.match_indices(needle.as_str()) {
+            if offset > start {
+                pieces.push((&text[start..offset], false));
+            }
+            pieces.push((&text[offset..offset + needle.len()], true));
+            start = offset + needle.len();
+        }
+        if start < text.len() {
+            pieces.push((&text[start..], false));
+        }
+        pieces
+    }
+}
+
+fn contains_text(value: &Value, needle: &str) -> bool {
+    match value {
+        Value::Object(entries) => entries.iter().any(|(key, value)| {
+            key.to_ascii_lowercase().contains(needle) || contains_text(value, needle)
+        }),
+        Value::Array(items) => items.iter().any(|item| contains_text(item, needle)),
+        Value::String(text) => text.to_ascii_lowercase().contains(needle),
+        other => other.to_string().to_ascii_lowercase().contains(needle),
+    }
+}
+
+/// Filters applied to the events of the contract panel, every condition that is set has to hold.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventFilter {
+    /// Every kind passes when empty.
+    pub kinds: Vec<ActionKind>,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    /// Milliseconds since the unix epoch, compared to the block timestamp.
+    pub from_time: Option<u64>,
+    pub to_time: Option<u64>,
+    pub search: Option<StateSearch>,
+}
+
+impl EventFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Events without a block never pass a block or time range.
+    pub fn matches(&self, event: &ContractEvent, previous_state: Option<&Value>) -> bool {
+        if !self.kinds.is_empty() && !self.kinds.contains(&event.kind()) {
+            return false;
+        }
+        let block = event.transaction.as_ref().map(|t| &t.block);
+        if !in_range(block.map(|b| b.height), self.from_block, self.to_block)
+            || !in_range(block.map(|b| b.timestamp), self.from_time, self.to_time)
+        {
+            return false;
+        }
+        match &self.search {
+            Some(search) => search.matches(event, &changes(previous_state, event)),
+            None => true,
+        }
+    }
+
+    /// Positions of the events that pass, in order.
+    pub fn apply(&self, events: &[ContractEvent]) -> Vec<usize> {
+        let mut filtered = FilteredEvents::default();
+        filtered.update(self, events, 0);
+        filtered.positions
+    }
+}
+
+/// The result of [`EventFilter::apply`] kept up to date while events are appended, only new
+/// events are looked at unless the filter changes or the list is replaced.
+#[derive(Debug, Clone, Default)]
+pub struct FilteredEvents {
+    filter: EventFilter,
+    /// Generation of the list the positions belong to, see [`FilteredEvents::update`].
+    generation: u64,
+    /// Number of events already filtered.
+    seen: usize,
+    /// Position of the latest event with a decoded state.
+    last_decoded: Option<usize>,
+    /// Positions of the events that passed, in order.
+    pub positions: Vec<usize>,
+}
+
+/// How [`FilteredEvents::positions`] changed in an update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterUpdate {
+    Unchanged,
+    /// Positions were added at the end, the ones before stayed where they were.
+    Appended,
+    /// The positions were worked out again from the first event.
+    Rebuilt,
+}
+
+impl FilteredEvents {
+    /// `generation` changes whenever `events` is replaced by another list rather than appended to.
+    pub fn update(
+        &mut self,
+        filter: &EventFilter,
+        events: &[ContractEvent],
+        generation: u64,
+    ) -> FilterUpdate {
+        let rebuilt =
+            *filter != self.filter || generation != self.generation || events.len() < self.seen;
+        if rebuilt {
+            *self = Self {
+                filter: filter.clone(),
+                generation,
+                ..Self::default()
+            };
+        }
+        let before = self.positions.len();
+        for (index, event) in events.iter().enumerate().skip(self.seen) {
+            let previous_state = self
+                .last_decoded
+                .and_then(|position| events[position].decoded_state.as_ref());
+            if self.filter.matches(event, previous_state) {
+                self.positions.push(index);
+            }
+            if event.decoded_state.is_some() {
+                self.last_decoded = Some(index);
+            }
+        }
+        self.seen = events.len();
+        match (rebuilt, self.positions.len() > before) {
+            (true, _) => FilterUpdate::Rebuilt,
+            (false, true) => FilterUpdate::Appended,
+            (false, false) => FilterUpdate::Unchanged,
+        }
+    }
+}
+
+fn in_range(value: Option<u64>, from: Option<u64>, to: Option<u64>) -> bool {
+    if from.is_none() && to.is_none() {
+        return true;
+    }
+    value.is_some_and(|value| from.is_none_or(|from| value >= from) && to.is_none_or(|to| value <= to))
+}
+
+/// Decoded state of the closest event before `index` that has one.
+pub fn previous_state(events: &[ContractEvent], index: usize) -> Option<&Value> {
+    events[..index.min(events.len())]
+        .iter()
+        .rev()
+        .find_map(|event| event.decoded_state.as_ref())
+}
+
+/// What an event changed compared to the state before it, nothing for the first decoded state.
+pub fn changes(previous_state: Option<&Value>, event: &ContractEvent) -> Vec<StateChange> {
+    match (previous_state, &event.decoded_state) {
+        (Some(before), Some(after)) => diff(before, after),
+        _ => Vec::new(),
+    }
+}
+
+/// A line of pretty printed state together with the path of the value on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateLine {
+    pub text: String,
+    pub path: JsonPath,
+}
+
+/// The state printed like `serde_json::to_string_pretty`, one entry per line.
+pub fn state_lines(state: &Value) -> Vec<StateLine> {
+    let mut lines = Vec::new();
+    lines_at(JsonPath::root(), None, state, 0, false, &mut lines);
+    lines
+}
+
+fn lines_at(
+    path: JsonPath,
+    key: Option<&str>,
+    value: &Value,
+    depth: usize,
+    comma: bool,
+    lines: &mut Vec<StateLine>,
+) {
+    let indent = "  ".repeat(depth);
+    let label = key
+        .map(|key| format!("{}: ", Value::String(key.to_string())))
+        .unwrap_or_default();
+    let comma = if comma { "," } else { "" };
+    match value {
+        Value::Object(entries) if !entries.is_empty() => {
+            lines.push(StateLine {
+                text: format!("{}{}{{", indent, label),
+                path: path.clone(),
+            });
+            for (position, (key, value)) in entries.iter().enumerate() {
+                let more = position + 1 < entries.len();
+                lines_at(path.child(key), Some(key), value, depth + 1, more, lines);
+            }
+            lines.push(StateLine {
+                text: format!("{}}}{}", indent, comma),
+                path,
+            });
+        }
+        Value::Array(items) if !items.is_empty() => {
+            lines.push(StateLine {
+                text: format!("{}{}[", indent, label),
+                path: path.clone(),
+            });
+            for (index, item) in items.iter().enumerate() {
+                let more = index + 1 < items.len();
+                lines_at(path.index(index), None, item, depth + 1, more, lines);
+            }
+            lines.push(StateLine {
+                text: format!("{}]{}", indent, comma),
+                path,
+            });
+        }
+        leaf => lines.push(StateLine {
+            text: format!("{}{}{}{}", indent, label, leaf, comma),
+            path,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(type_name: &str, height: u64, state: Value) -> ContractEvent {
+        ContractEvent::test_call()
+            .with_type(type_name)
+            .at_height(height)
+            .with_raw_state("00ABCD")
+            .with_state(state)
+    }
+
+    #[test]
+    fn parses_changed_queries_only_with_a_separator() {
+        assert_eq!(StateSearch::parse("   "), None);
+        assert_eq!(
+            StateSearch::parse(" $[1] changed "),
+            Some(StateSearch::Changed(JsonPath::parse("$[1]").unwrap()))
+        );
+        assert_eq!(
+            StateSearch::parse("$.xunchanged"),
+            Some(StateSearch::Text("$.xunchanged".to_string()))
+        );
+        assert_eq!(
+            StateSearch::parse("counter changed"),
+            Some(StateSearch::Text("counter changed".to_string()))
+        );
+        assert_eq!(
+            StateSearch::parse("0xABcd"),
+            Some(StateSearch::Text("abcd".to_string()))
+        );
+    }
+
+    #[test]
+    fn text_search_looks_at_state_hex_keys_and_values() {
+        let event = event("ContractCall", 1, json!([{ "Owner": "Alice" }, 42]));
+        let matches = |query: &str| StateSearch::parse(query).unwrap().matches(&event, &[]);
+        assert!(matches("0xabcd"));
+        assert!(matches("owner"));
+        assert!(matches("ALICE"));
+        assert!(matches("42"));
+        assert!(!matches("bob"));
+    }
+
+    #[test]
+    fn changed_search_matches_changes_at_or_below_the_path() {
+        let before = json!([1, { "1": 10 }]);
+        let after = event("ContractCall", 2, json!([1, { "1": 11 }]));
+        let changes = changes(Some(&before), &after);
+        assert!(StateSearch::parse("$[1] changed")
+            .unwrap()
+            .matches(&after, &changes));
+        assert!(!StateSearch::parse("$[0] changed")
+            .unwrap()
+            .matches(&after, &changes));
+    }
+
+    #[test]
+    fn splits_text_around_case_insensitive_matches() {
+        let search = StateSearch::parse("ab").unwrap();
+        assert_eq!(
+            search.split("xAByab"),
+            [("x", false), ("AB", true), ("y", false), ("ab", true)]
+        );
+        assert_eq!(
+            StateSearch::parse("$[0] changed").unwrap().split("ab"),
+            [("ab", false)]
+        );
+    }
+
+    #[test]
+    fn every_set_condition_has_to_hold() {
+        let events = vec![
+            event("ContractDeploy", 1, json!([0])),
+            event("ContractCall", 5, json!([1])),
+            event("ContractCall", 9, json!([1])),
+            event("ContractCall", 12, json!([2])),
+        ];
+        let calls = EventFilter {
+            kinds: vec![ActionKind::Call],
+            ..EventFilter::default()
+        };
+        assert_eq!(calls.apply(&events), [1, 2, 3]);
+        let range = EventFilter {
+            from_block: Some(5),
+            to_time: Some(9_000),
+            ..calls.clone()
+        };
+        assert_eq!(range.apply(&events), [1, 2]);
+        let changed = EventFilter {
+            search: StateSearch::parse("$[0] changed"),
+            ..calls
+        };
+        assert_eq!(changed.apply(&events), [1, 3]);
+        assert!(EventFilter::default().is_empty());
+        assert_eq!(EventFilter::default().apply(&events), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn events_without_a_block_fail_ranges_only() {
+        let mut pending = event("ContractCall", 1, json!([0]));
+        pending.transaction = None;
+        assert!(EventFilter::default().matches(&pending, None));
+        let range = EventFilter {
+            from_block: Some(0),
+            ..EventFilter::default()
+        };
+        assert!(!range.matches(&pending, None));
+    }
+
+    #[test]
+    fn filtered_events_only_look_at_appended_events() {
+        let calls = EventFilter {
+            search: StateSearch::parse("$[0] changed"),
+            ..EventFilter::default()
+        };
+        let mut events = vec![
+            event("ContractDeploy", 1, json!([0])),
+            event("ContractCall", 2, json!([1])),
+        ];
+        let mut filtered = FilteredEvents::default();
+        assert_eq!(filtered.update(&calls, &events, 0), FilterUpdate::Rebuilt);
+        assert_eq!(filtered.positions, [1]);
+        assert_eq!(filtered.update(&calls, &events, 0), FilterUpdate::Unchanged);
+
+        // the state a new event is compared with is the last one already filtered
+        events.push(event("ContractCall", 3, json!([1])));
+        assert_eq!(filtered.update(&calls, &events, 0), FilterUpdate::Unchanged);
+        events.push(event("ContractCall", 4, json!([2])));
+        assert_eq!(filtered.update(&calls, &events, 0), FilterUpdate::Appended);
+        assert_eq!(filtered.positions, [1, 3]);
+        assert_eq!(filtered.positions, calls.apply(&events));
+    }
+
+    #[test]
+    fn filtered_events_rebuild_for_another_filter_or_list() {
+        let events = vec![
+            event("ContractDeploy", 1, json!([0])),
+            event("ContractCall", 2, json!([1])),
+        ];
+        let mut filtered = FilteredEvents::default();
+        filtered.update(&EventFilter::default(), &events, 0);
+        assert_eq!(filtered.positions, [0, 1]);
+
+        let deploys = EventFilter {
+            kinds: vec![ActionKind::Deploy],
+            ..EventFilter::default()
+        };
+        assert_eq!(filtered.update(&deploys, &events, 0), FilterUpdate::Rebuilt);
+        assert_eq!(filtered.positions, [0]);
+
+        // a list of the same length with other events is only told apart by its generation
+        let imported = vec![
+            event("ContractCall", 7, json!([0])),
+            event("ContractDeploy", 8, json!([1])),
+        ];
+        assert_eq!(
+            filtered.update(&deploys, &imported, 0),
+            FilterUpdate::Unchanged
+        );
+        assert_eq!(
+            filtered.update(&deploys, &imported, 1),
+            FilterUpdate::Rebuilt
+        );
+        assert_eq!(filtered.positions, [1]);
+        assert_eq!(
+            filtered.update(&deploys, &imported[..1], 1),
+            FilterUpdate::Rebuilt
+        );
+        assert!(filtered.positions.is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{network::Network, ContractEvent};
+
+/// The part of a block that is attached to everything the indexer returns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub hash: String,
+    pub height: u64,
+    /// Milliseconds since the unix epoch.
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockRef {
+    pub hash: String,
+    pub height: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Block {
+    pub hash: String,
+    pub height: u64,
+    pub timestamp: u64,
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub parent: Option<BlockRef>,
+    #[serde(default)]
+    pub transactions: Vec<Transaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    pub hash: String,
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub identifiers: Vec<String>,
+    /// Hex encoded ledger transaction, only requested when looking up a single transaction.
+    #[serde(default)]
+    pub raw: Option<String>,
+    /// The raw transaction as decoded by the ledger, `None` if it was not requested or could not be parsed.
+    #[serde(default)]
+    pub decoded: Option<String>,
+    pub block: BlockHeader,
+    #[serde(default)]
+    pub contract_actions: Vec<ContractEvent>,
+}
+
+/// Transaction a contract action was part of.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionRef {
+    pub hash: String,
+    pub block: BlockHeader,
+    /// Missing from events recorded before fees were requested.
+    #[serde(default)]
+    pub fees: Option<TransactionFees>,
+}
+
+/// Fees in the smallest unit of DUST, as the decimal strings the indexer reports since they can exceed 64 bits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionFees {
+    pub paid_fees: String,
+    pub estimated_fees: String,
+}
+
+/// Latest block seen by the server's `blocks` subscription for a network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainHead {
+    pub network: Network,
+    pub block: Option<BlockHeader>,
+    pub seconds_since_block: Option<u64>,
+    /// Either the indexer has not produced a block for too long or the server lost its subscription.
+    pub lagging: bool,
+}
+
+/// Formats a block timestamp as `YYYY-MM-DD HH:MM:SS UTC` without pulling a date library into the wasm bundle.
+pub fn format_timestamp(timestamp_ms: u64) -> String {
+    let time = DateTime::from_timestamp(timestamp_ms);
+    format!("{} {}:{:02} UTC", time.date(), time.clock(), time.second)
+}
+
+/// The calendar parts of a block timestamp in UTC, for labels that only need some of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    pub fn from_timestamp(timestamp_ms: u64) -> Self {
+        let secs = timestamp_ms / 1000;
+        let (days, rem) = (secs / 86_400, secs % 86_400);
+        // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        Self {
+            year: yoe + era * 400 + if month <= 2 { 1 } else { 0 },
+            month: month as u8,
+            day: day as u8,
+            hour: (rem / 3600) as u8,
+            minute: (rem % 3600 / 60) as u8,
+            second: (rem % 60) as u8,
+        }
+    }
+
+    /// `YYYY-MM-DD`
+    pub fn date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+    /// `MM-DD`
+    pub fn month_day(&self) -> String {
+        format!("{:02}-{:02}", self.month, self.day)
+    }
+
+    /// `HH:MM`
+    pub fn clock(&self) -> String {
+        format!("{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+/// Reads `YYYY-MM-DD`, `YYYY-MM-DD HH:MM` or `YYYY-MM-DDTHH:MM:SS` as UTC, the inverse of [`format_timestamp`].
+/// Dates and times that do not exist, such as February 30 or 24:00, are refused.
+pub fn parse_timestamp(input: &str) -> Option<u64> {
+    let input = input.trim().trim_end_matches(" UTC").trim_end_matches('Z');
+    let (date, time) = input.split_once(['T', ' ']).unwrap_or((input, ""));
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    let mut clock = [0i64; 3];
+    if !time.is_empty() {
+        let parts: Vec<&str> = time.split(':').collect();
+        if !(2..=3).contains(&parts.len()) {
+            return None;
+        }
+        for (slot, part) in clock.iter_mut().zip(parts) {
+            *slot = part.parse().ok()?;
+        }
+    }
+    if !(0..24).contains(&clock[0]) || !(0..60).contains(&clock[1]) || !(0..60).contains(&clock[2]) {
+        return None;
+    }
+    // days from civil, see http://howardhinnant.github.io/date_algorithms.html
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let secs = days * 86_400 + clock[0] * 3600 + clock[1] * 60 + clock[2];
+    u64::try_from(secs).ok().map(|secs| secs * 1000)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-02-29 13:45:30 UTC
+    const LEAP_DAY: u64 = 1_709_214_330_000;
+
+    #[test]
+    fn formats_and_parses_back() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(LEAP_DAY), "2024-02-29 13:45:30 UTC");
+        assert_eq!(parse_timestamp(&format_timestamp(LEAP_DAY)), Some(LEAP_DAY));
+        let leap_day = DateTime::from_timestamp(LEAP_DAY);
+        assert_eq!(
+            (leap_day.date(), leap_day.month_day(), leap_day.clock()),
+            (
+                "2024-02-29".to_string(),
+                "02-29".to_string(),
+                "13:45".to_string()
+            )
+        );
+        assert_eq!(leap_day.second, 30);
+    }
+
+    #[test]
+    fn parses_dates_with_or_without_a_time() {
+        assert_eq!(parse_timestamp("1970-01-02"), Some(86_400_000));
+        assert_eq!(parse_timestamp(" 2024-02-29T13:45:30Z "), Some(LEAP_DAY));
+        assert_eq!(parse_timestamp("2024-02-29T13:45"), Some(LEAP_DAY - 30_000));
+        assert_eq!(parse_timestamp("2024-02-29 13:45"), Some(LEAP_DAY - 30_000));
+    }
+
+    #[test]
+    fn refuses_dates_and_times_that_do_not_exist() {
+        for input in [
+            "",
+            "2024-13-01",
+            "2023-02-29",
+            "2024-04-31",
+            "2024-01-01T24:00",
+            "2024-01-01T12:60",
+            "2024-01-01T12:00:60",
+            "2024-01-01T12",
+            "2024-01-01T12:00:00:00",
+            "1969-12-31",
+            "yesterday",
+        ] {
+            assert_eq!(parse_timestamp(input), None, "{}", input);
+        }
+        assert!(parse_timestamp("2000-02-29").is_some());
+        assert!(parse_timestamp("2100-02-29").is_none());
+    }
+}
@@ -0,0 +1,43 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A limit the server enforces to protect itself and the indexer from a single client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LimitExceeded {
+    /// Too many sessions open from the client's IP address.
+    SessionsPerClient { max: usize },
+    /// The server has as many indexer subscriptions open as it allows.
+    Subscriptions { max: usize },
+    /// The client asked to follow too many contracts within the last minute.
+    SubscribeRate { per_minute: usize },
+    /// The page connecting is not served from an allowed origin.
+    OriginNotAllowed { origin: String },
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::SessionsPerClient { max } => write!(
+                f,
+                "You already have {} sessions open, close one to open another.",
+                max
+            ),
+            LimitExceeded::Subscriptions { max } => write!(
+                f,
+                "The server is following {} contracts, the most it allows. Try again later.",
+                max
+            ),
+            LimitExceeded::SubscribeRate { per_minute } => write!(
+                f,
+                "At most {} contracts can be opened per minute, wait a moment and try again.",
+                per_minute
+            ),
+            LimitExceeded::OriginNotAllowed { origin } => {
+                write!(f, "Connections from {} are not allowed.", origin)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
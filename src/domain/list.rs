@@ -0,0 +1,195 @@
+use std::{collections::HashMap, ops::Range};
+
+/// Where the rows of a virtualized list sit. Rows are laid out with an estimated height until they
+/// have been rendered and measured, measured heights are kept per event so they survive filtering.
+#[derive(Debug, Clone)]
+pub struct RowLayout {
+    estimate: i32,
+    gap: i32,
+    /// Measured height of each event's row, by event index.
+    heights: HashMap<usize, i32>,
+    /// Top of every laid out row followed by the bottom of the last one.
+    tops: Vec<i32>,
+}
+
+impl RowLayout {
+    pub fn new(estimate: i32, gap: i32) -> Self {
+        Self {
+            estimate,
+            gap,
+            heights: HashMap::new(),
+            tops: vec![0],
+        }
+    }
+
+    /// Forgets where rows sit but keeps what was measured, for when the rows are filtered differently.
+    pub fn reset(&mut self) {
+        self.tops.truncate(1);
+    }
+
+    /// Forgets the measured heights too, for when the list holds other events.
+    pub fn clear(&mut self) {
+        self.reset();
+        self.heights.clear();
+    }
+
+    /// Records the height of the row at `position`, showing event `index`. Returns whether it
+    /// changed, the rows below it have to be laid out again by [`RowLayout::sync`] then.
+    pub fn measure(&mut self, position: usize, index: usize, height: i32) -> bool {
+        if self.heights.insert(index, height) == Some(height) {
+            return false;
+        }
+        self.tops.truncate(position + 1);
+        true
+    }
+
+    /// Lays out the rows for `positions`, the event index shown in each row. Only rows past the
+    /// ones already laid out are looked at, so call [`RowLayout::reset`] when earlier rows changed.
+    pub fn sync(&mut self, positions: &[usize]) {
+        self.tops.truncate(positions.len() + 1);
+        for index in &positions[self.tops.len() - 1..] {
+            let top = self.tops[self.tops.len() - 1];
+            let height = self.heights.get(index).copied().unwrap_or(self.estimate);
+            self.tops.push(top + height + self.gap);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tops.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn top(&self, position: usize) -> i32 {
+        self.tops[position.min(self.len())]
+    }
+
+    /// Height of the row at `position` without the gap below it.
+    pub fn height(&self, position: usize) -> i32 {
+        match self.tops.get(position..position + 2) {
+            Some([top, next]) => next - top - self.gap,
+            _ => 0,
+        }
+    }
+
+    /// Height of all rows with the gaps between them.
+    pub fn total(&self) -> i32 {
+        self.tops[self.len()]
+    }
+
+    /// Rows that show between `scroll_top` and `scroll_top + viewport`, with `overscan` more on both sides.
+    pub fn window(&self, scroll_top: i32, viewport: i32, overscan: usize) -> Range<usize> {
+        let first = self.tops[1..].partition_point(|bottom| *bottom <= scroll_top);
+        let end = self.tops[..self.len()].partition_point(|top| *top < scroll_top + viewport);
+        first.saturating_sub(overscan)..(end + overscan).min(self.len())
+    }
+}
+
+/// Position in `visible` to select after a navigation key, `None` for keys that don't navigate.
+/// An event hidden by the filters counts as sitting between its visible neighbours.
+pub fn navigate(
+    visible: &[usize],
+    selected: Option<usize>,
+    key: &str,
+    page: usize,
+) -> Option<usize> {
+    let last = visible.len().checked_sub(1)?;
+    let (before, after) = match selected.map(|selected| visible.binary_search(&selected)) {
+        Some(Ok(position)) => (position.checked_sub(1), position + 1),
+        Some(Err(position)) => (position.checked_sub(1), position),
+        None => (None, 0),
+    };
+    let target = match key {
+        "ArrowDown" | "j" => after,
+        "ArrowUp" | "k" => before.unwrap_or(0),
+        "PageDown" => after + page - 1,
+        "PageUp" => before.map_or(0, |before| (before + 1).saturating_sub(page)),
+        "Home" => 0,
+        "End" => last,
+        _ => return None,
+    };
+    Some(target.min(last))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn navigates_between_visible_events() {
+        let visible = [2, 5, 9, 12];
+        assert_eq!(navigate(&visible, None, "ArrowDown", 2), Some(0));
+        assert_eq!(navigate(&visible, None, "ArrowUp", 2), Some(0));
+        assert_eq!(navigate(&visible, Some(5), "j", 2), Some(2));
+        assert_eq!(navigate(&visible, Some(5), "k", 2), Some(0));
+        assert_eq!(navigate(&visible, Some(12), "ArrowDown", 2), Some(3));
+        assert_eq!(navigate(&visible, Some(2), "PageDown", 2), Some(2));
+        assert_eq!(navigate(&visible, Some(12), "PageUp", 2), Some(1));
+        assert_eq!(navigate(&visible, Some(5), "PageUp", 5), Some(0));
+        assert_eq!(navigate(&visible, Some(5), "End", 2), Some(3));
+        assert_eq!(navigate(&visible, Some(9), "Home", 2), Some(0));
+        assert_eq!(navigate(&visible, Some(5), "Enter", 2), None);
+        assert_eq!(navigate(&[], Some(5), "ArrowDown", 2), None);
+    }
+
+    #[test]
+    fn hidden_selection_sits_between_its_neighbours() {
+        let visible = [2, 5, 9];
+        assert_eq!(navigate(&visible, Some(7), "ArrowDown", 2), Some(2));
+        assert_eq!(navigate(&visible, Some(7), "ArrowUp", 2), Some(1));
+        assert_eq!(navigate(&visible, Some(0), "ArrowUp", 2), Some(0));
+        assert_eq!(navigate(&visible, Some(20), "ArrowDown", 2), Some(2));
+    }
+
+    #[test]
+    fn lays_out_estimated_rows_until_measured() {
+        let mut layout = RowLayout::new(50, 10);
+        assert!(layout.is_empty());
+        assert_eq!(layout.total(), 0);
+        layout.sync(&[0, 3, 4]);
+        assert_eq!(layout.len(), 3);
+        assert_eq!(
+            (layout.top(2), layout.height(2), layout.total()),
+            (120, 50, 180)
+        );
+
+        assert!(layout.measure(1, 3, 100));
+        assert!(!layout.measure(1, 3, 100));
+        layout.sync(&[0, 3, 4, 7]);
+        assert_eq!(layout.top(2), 170);
+        assert_eq!(layout.height(1), 100);
+        assert_eq!(layout.total(), 290);
+        assert_eq!(layout.height(4), 0);
+    }
+
+    #[test]
+    fn measured_heights_follow_their_event() {
+        let mut layout = RowLayout::new(50, 10);
+        layout.sync(&[0, 3, 4]);
+        layout.measure(1, 3, 100);
+        layout.reset();
+        layout.sync(&[3, 4]);
+        assert_eq!((layout.height(0), layout.top(1)), (100, 110));
+
+        layout.clear();
+        layout.sync(&[3, 4]);
+        assert_eq!(layout.height(0), 50);
+        layout.sync(&[3]);
+        assert_eq!(layout.total(), 60);
+    }
+
+    #[test]
+    fn windows_the_rows_on_screen() {
+        let mut layout = RowLayout::new(50, 10);
+        layout.sync(&(0..100).collect::<Vec<_>>());
+        assert_eq!(layout.window(0, 120, 0), 0..2);
+        assert_eq!(layout.window(0, 121, 0), 0..3);
+        assert_eq!(layout.window(60, 60, 0), 1..2);
+        assert_eq!(layout.window(59, 60, 0), 0..2);
+        assert_eq!(layout.window(600, 120, 5), 5..17);
+        assert_eq!(layout.window(5990, 600, 5), 94..100);
+        assert_eq!(RowLayout::new(50, 10).window(0, 100, 5), 0..0);
+    }
+}
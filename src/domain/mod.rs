@@ -1,13 +1,193 @@
 use serde::{Deserialize, Serialize};
 
+pub mod address;
+pub mod alert;
+pub mod analytics;
+pub mod auth;
+pub mod connection;
+pub mod diff;
+pub mod export;
+pub mod filter;
+pub mod indexer;
+pub mod limit;
+pub mod list;
+pub mod network;
+pub mod path;
+pub mod predicate;
+pub mod series;
+pub mod state;
+pub mod webhook;
+
+use address::AddressError;
+use alert::Alert;
+use indexer::TransactionRef;
+use limit::LimitExceeded;
+use network::Network;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContractEvent {
     #[serde(rename = "__typename")]
     pub type_name: String,
+    /// Hex encoded contract state as returned by the indexer.
     pub state: String,
     pub address: String,
     #[serde(rename = "chainState")]
     pub chain_state: String,
+    #[serde(default)]
+    pub transaction: Option<TransactionRef>,
+    /// Hex encoded name of the circuit a call ran, only set for calls.
+    #[serde(rename = "entryPoint", default)]
+    pub entry_point: Option<String>,
+    /// Contract state decoded by the ledger, `None` if it could not be parsed.
+    #[serde(default)]
+    pub decoded_state: Option<serde_json::Value>,
+    /// Position in the contract's history, set once the server has recorded the action.
+    #[serde(default)]
+    pub sequence: Option<u64>,
+}
+
+/// What a contract action did, derived from its GraphQL type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionKind {
+    Deploy,
+    Call,
+    Update,
+    Unknown,
+}
+
+impl std::fmt::Display for ActionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ActionKind::Deploy => "deploy",
+            ActionKind::Call => "call",
+            ActionKind::Update => "update",
+            ActionKind::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for ActionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "deploy" => Ok(ActionKind::Deploy),
+            "call" => Ok(ActionKind::Call),
+            "update" => Ok(ActionKind::Update),
+            other => Err(format!("unknown action kind '{}'", other)),
+        }
+    }
+}
+
+impl ContractEvent {
+    pub fn kind(&self) -> ActionKind {
+        match self.type_name.as_str() {
+            "ContractDeploy" => ActionKind::Deploy,
+            "ContractCall" => ActionKind::Call,
+            "ContractUpdate" => ActionKind::Update,
+            _ => ActionKind::Unknown,
+        }
+    }
+
+    /// The entry point of a call as text, left in hex if it is not valid UTF-8.
+    pub fn entry_point_name(&self) -> Option<String> {
+        let hex_name = self.entry_point.as_ref()?;
+        Some(
+            hex::decode(hex_name)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .unwrap_or_else(|| hex_name.clone()),
+        )
+    }
+
+    /// Pretty printed decoded state, falling back to the raw hex.
+    pub fn display_state(&self) -> String {
+        self.decoded_state
+            .as_ref()
+            .and_then(|state| serde_json::to_string_pretty(state).ok())
+            .unwrap_or_else(|| self.state.clone())
+    }
+}
+
+/// Builds events for tests, starting from a call with nothing else set.
+#[cfg(test)]
+impl ContractEvent {
+    pub fn test_call() -> Self {
+        ContractEvent {
+            type_name: "ContractCall".to_string(),
+            state: String::new(),
+            address: String::new(),
+            chain_state: String::new(),
+            transaction: None,
+            entry_point: None,
+            decoded_state: None,
+            sequence: None,
+        }
+    }
+
+    /// In transaction `tx<height>` of block `block<height>`, timestamped `height` seconds after the epoch.
+    pub fn at_height(self, height: u64) -> Self {
+        self.in_transaction(&format!("tx{}", height), height, height * 1000)
+    }
+
+    pub fn in_transaction(mut self, hash: &str, height: u64, timestamp: u64) -> Self {
+        self.transaction = Some(TransactionRef {
+            hash: hash.to_string(),
+            block: indexer::BlockHeader {
+                hash: format!("block{}", height),
+                height,
+                timestamp,
+            },
+            fees: None,
+        });
+        self
+    }
+
+    /// Only once the event is in a transaction.
+    pub fn with_fees(mut self, paid_fees: &str, estimated_fees: &str) -> Self {
+        if let Some(transaction) = &mut self.transaction {
+            transaction.fees = Some(indexer::TransactionFees {
+                paid_fees: paid_fees.to_string(),
+                estimated_fees: estimated_fees.to_string(),
+            });
+        }
+        self
+    }
+
+    pub fn with_type(mut self, type_name: &str) -> Self {
+        self.type_name = type_name.to_string();
+        self
+    }
+
+    /// The decoded state.
+    pub fn with_state(mut self, state: serde_json::Value) -> Self {
+        self.decoded_state = Some(state);
+        self
+    }
+
+    /// The hex state as the indexer returns it.
+    pub fn with_raw_state(mut self, state: &str) -> Self {
+        self.state = state.to_string();
+        self
+    }
+
+    pub fn with_address(mut self, address: &str) -> Self {
+        self.address = address.to_string();
+        self
+    }
+
+    /// Hex encoded, like the indexer returns it.
+    pub fn with_entry_point(mut self, name: &str) -> Self {
+        self.entry_point = Some(hex::encode(name));
+        self
+    }
+
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,4 +195,39 @@ pub enum Event {
     ContractEvent(ContractEvent),
     Disconnect,
     TimeLeft(u64),
+    /// The browser fell behind and this many contract events or alerts were dropped before delivery.
+    Lagged { dropped: u64 },
+    /// The subscription could not be started, the browser may retry with another address.
+    Error(SubscriptionError),
+    /// An alert rule on the contract fired.
+    Alert(Alert),
+    /// The server is stopping and closes the subscription, the browser may reconnect once it is back.
+    ServerShuttingDown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SubscriptionError {
+    InvalidAddress(AddressError),
+    ContractNotFound { network: Network },
+    IndexerUnavailable(String),
+    ShuttingDown,
+    LimitExceeded(LimitExceeded),
+}
+
+impl std::fmt::Display for SubscriptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubscriptionError::InvalidAddress(e) => write!(f, "{}", e),
+            SubscriptionError::ContractNotFound { network } => {
+                write!(f, "Contract not found on {}.", network)
+            }
+            SubscriptionError::IndexerUnavailable(e) => {
+                write!(f, "Could not reach the indexer: {}", e)
+            }
+            SubscriptionError::ShuttingDown => {
+                write!(f, "The server is shutting down, try again in a moment.")
+            }
+            SubscriptionError::LimitExceeded(e) => write!(f, "{}", e),
+        }
+    }
 }
@@ -0,0 +1,68 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Midnight network, mirroring the ledger's `NetworkId` so it can be used in the browser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Network {
+    Undeployed,
+    DevNet,
+    TestNet,
+    MainNet,
+}
+
+impl Network {
+    /// Byte the ledger uses to tag addresses with their network.
+    pub fn id(self) -> u8 {
+        match self {
+            Network::Undeployed => 0,
+            Network::DevNet => 1,
+            Network::TestNet => 2,
+            Network::MainNet => 3,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Network::Undeployed),
+            1 => Some(Network::DevNet),
+            2 => Some(Network::TestNet),
+            3 => Some(Network::MainNet),
+            _ => None,
+        }
+    }
+
+    /// Two hex digit prefix prepended to addresses on this network, `02` for testnet.
+    pub fn prefix(self) -> String {
+        format!("{:02x}", self.id())
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Network::Undeployed => "undeployed",
+            Network::DevNet => "devnet",
+            Network::TestNet => "testnet",
+            Network::MainNet => "mainnet",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "undeployed" | "local" => Ok(Network::Undeployed),
+            "devnet" => Ok(Network::DevNet),
+            "testnet" => Ok(Network::TestNet),
+            "mainnet" => Ok(Network::MainNet),
+            other => Err(format!(
+                "unknown network '{}', expected undeployed, devnet, testnet or mainnet",
+                other
+            )),
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// A JSONPath-style selector over decoded contract state, e.g. `$[0]`, `$[1]['42']` or `$.items[*].owner`.
+///
+/// Field names that do not start with a letter are written quoted, so a map key never reads as an index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    pub fn root() -> Self {
+        Self {
+            segments: Vec::new(),
+        }
+    }
+
+    /// The leading `$` is optional, `counter` is the same as `$.counter`.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        let rest = input.strip_prefix('$').unwrap_or(input);
+        let chars: Vec<char> = rest.chars().collect();
+        let mut segments = Vec::new();
+        let mut pos = 0;
+        // a bare leading name, as in `counter.value`
+        let mut expect_name = !rest.is_empty() && !rest.starts_with(['.', '[']);
+
+        while pos < chars.len() || expect_name {
+            if expect_name {
+                expect_name = false;
+                let start = pos;
+                while pos < chars.len() && chars[pos] != '.' && chars[pos] != '[' {
+                    pos += 1;
+                }
+                let name: String = chars[start..pos].iter().collect();
+                match name.as_str() {
+                    "" => return Err(format!("empty field name in '{}'", input)),
+                    "*" => segments.push(Segment::Wildcard),
+                    _ => segments.push(Segment::Field(name)),
+                }
+                continue;
+            }
+            match chars[pos] {
+                '.' => {
+                    pos += 1;
+                    expect_name = true;
+                }
+                '[' => {
+                    let end = chars[pos..]
+                        .iter()
+                        .position(|c| *c == ']')
+                        .map(|offset| pos + offset)
+                        .ok_or_else(|| format!("unclosed '[' in '{}'", input))?;
+                    let inner: String = chars[pos + 1..end].iter().collect();
+                    let inner = inner.trim();
+                    if inner == "*" {
+                        segments.push(Segment::Wildcard);
+                    } else if let Some(quoted) = inner
+                        .strip_prefix('\'')
+                        .and_then(|s| s.strip_suffix('\''))
+                        .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')))
+                    {
+                        segments.push(Segment::Field(quoted.to_string()));
+                    } else {
+                        let index = inner
+                            .parse::<usize>()
+                            .map_err(|_| format!("invalid index '{}' in '{}'", inner, input))?;
+                        segments.push(Segment::Index(index));
+                    }
+                    pos = end + 1;
+                }
+                c => return Err(format!("unexpected '{}' in '{}'", c, input)),
+            }
+        }
+        Ok(Self { segments })
+    }
+
+    /// Every value the path points at, wildcards can match several.
+    pub fn select<'a>(&self, value: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![value];
+        for segment in &self.segments {
+            current = current
+                .into_iter()
+                .flat_map(|value| -> Vec<&'a Value> {
+                    match (segment, value) {
+                        (Segment::Field(name), Value::Object(map)) => {
+                            map.get(name).into_iter().collect()
+                        }
+                        (Segment::Index(index), Value::Array(items)) => {
+                            items.get(*index).into_iter().collect()
+                        }
+                        (Segment::Wildcard, Value::Object(map)) => map.values().collect(),
+                        (Segment::Wildcard, Value::Array(items)) => items.iter().collect(),
+                        _ => Vec::new(),
+                    }
+                })
+                .collect();
+        }
+        current
+    }
+
+    /// Whether the path begins with a named field, as in `$.counter`, rather than an index.
+    pub fn starts_with_field(&self) -> bool {
+        matches!(self.segments.first(), Some(Segment::Field(_)))
+    }
+
+    pub fn select_first<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.select(value).into_iter().next()
+    }
+
+    pub fn child(&self, name: &str) -> Self {
+        let mut segments = self.segments.clone();
+        segments.push(Segment::Field(name.to_string()));
+        Self { segments }
+    }
+
+    pub fn index(&self, index: usize) -> Self {
+        let mut segments = self.segments.clone();
+        segments.push(Segment::Index(index));
+        Self { segments }
+    }
+
+    /// Whether `other` is this path or lies below it. Wildcards match any single segment.
+    pub fn covers(&self, other: &JsonPath) -> bool {
+        self.segments.len() <= other.segments.len()
+            && self
+                .segments
+                .iter()
+                .zip(&other.segments)
+                .all(|(mine, theirs)| mine == &Segment::Wildcard || mine == theirs)
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.segments {
+            match segment {
+                Segment::Field(name)
+                    if name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                        && name.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+                {
+                    write!(f, ".{}", name)?
+                }
+                Segment::Field(name) => write!(f, "['{}']", name)?,
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+                Segment::Wildcard => write!(f, "[*]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonPath::parse(s)
+    }
+}
+
+impl TryFrom<String> for JsonPath {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        JsonPath::parse(&value)
+    }
+}
+
+impl From<JsonPath> for String {
+    fn from(path: JsonPath) -> Self {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn tells_named_from_positional_roots() {
+        assert!(JsonPath::parse("$.counter").unwrap().starts_with_field());
+        assert!(!JsonPath::parse("$[0].counter").unwrap().starts_with_field());
+        assert!(!JsonPath::root().starts_with_field());
+    }
+
+    #[test]
+    fn parses_fields_indexes_quotes_and_wildcards() {
+        let path = JsonPath::parse(" $.items[2]['odd key'][*].owner ").unwrap();
+        assert_eq!(
+            path.segments,
+            [
+                Segment::Field("items".to_string()),
+                Segment::Index(2),
+                Segment::Field("odd key".to_string()),
+                Segment::Wildcard,
+                Segment::Field("owner".to_string()),
+            ]
+        );
+        assert_eq!(path.to_string(), "$.items[2]['odd key'][*].owner");
+        assert_eq!(
+            JsonPath::parse("counter").unwrap(),
+            JsonPath::root().child("counter")
+        );
+        assert_eq!(JsonPath::parse("$").unwrap(), JsonPath::root());
+        assert_eq!(
+            JsonPath::parse("$[0][\"1\"]").unwrap().to_string(),
+            "$[0]['1']"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        assert!(JsonPath::parse("$.").is_err());
+        assert!(JsonPath::parse("$..a").is_err());
+        assert!(JsonPath::parse("$[0").is_err());
+        assert!(JsonPath::parse("$[x]").is_err());
+        assert!(JsonPath::parse("$[0]x").is_err());
+    }
+
+    #[test]
+    fn selects_every_match() {
+        let state = json!([{ "a": 1 }, { "a": 2 }, { "b": 3 }]);
+        let path = JsonPath::parse("$[*].a").unwrap();
+        assert_eq!(path.select(&state), [&json!(1), &json!(2)]);
+        assert_eq!(JsonPath::parse("$[5]").unwrap().select_first(&state), None);
+        assert_eq!(JsonPath::root().select_first(&state), Some(&state));
+    }
+
+    #[test]
+    fn covers_itself_and_everything_below() {
+        let parent = JsonPath::parse("$[1]").unwrap();
+        assert!(parent.covers(&parent));
+        assert!(parent.covers(&JsonPath::parse("$[1]['42']").unwrap()));
+        assert!(!parent.covers(&JsonPath::parse("$[10]").unwrap()));
+        assert!(!parent.covers(&JsonPath::root()));
+        assert!(JsonPath::parse("$[*].a")
+            .unwrap()
+            .covers(&JsonPath::parse("$[3].a.b").unwrap()));
+    }
+
+    #[test]
+    fn round_trips_through_serde_as_a_string() {
+        let path = JsonPath::parse("$[0].owner").unwrap();
+        let encoded = serde_json::to_string(&path).unwrap();
+        assert_eq!(encoded, "\"$[0].owner\"");
+        assert_eq!(serde_json::from_str::<JsonPath>(&encoded).unwrap(), path);
+        assert!(serde_json::from_str::<JsonPath>("\"$[\"").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::domain::path::JsonPath;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    Exists,
+    Equals(Value),
+    NotEquals(Value),
+    GreaterThan(f64),
+    LessThan(f64),
+    /// The value, rendered as text, contains this string. Handy for hex encoded values.
+    Contains(String),
+}
+
+/// A condition on the value at a path of the decoded state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Predicate {
+    pub path: JsonPath,
+    pub condition: Condition,
+}
+
+impl Predicate {
+    pub fn new(path: JsonPath, condition: Condition) -> Self {
+        Self { path, condition }
+    }
+
+    /// Holds if any value selected by the path satisfies the condition.
+    pub fn holds(&self, state: &Value) -> bool {
+        let selected = self.path.select(state);
+        match &self.condition {
+            Condition::Exists => !selected.is_empty(),
+            Condition::Equals(expected) => selected.iter().any(|v| values_match(v, expected)),
+            Condition::NotEquals(expected) => {
+                !selected.is_empty() && selected.iter().all(|v| !values_match(v, expected))
+            }
+            Condition::GreaterThan(bound) => selected
+                .iter()
+                .any(|v| as_number(v).is_some_and(|n| n > *bound)),
+            Condition::LessThan(bound) => selected
+                .iter()
+                .any(|v| as_number(v).is_some_and(|n| n < *bound)),
+            Condition::Contains(needle) => selected
+                .iter()
+                .any(|v| as_text(v).to_lowercase().contains(&needle.to_lowercase())),
+        }
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.condition {
+            Condition::Exists => write!(f, "{} exists", self.path),
+            Condition::Equals(value) => write!(f, "{} == {}", self.path, value),
+            Condition::NotEquals(value) => write!(f, "{} != {}", self.path, value),
+            Condition::GreaterThan(bound) => write!(f, "{} > {}", self.path, bound),
+            Condition::LessThan(bound) => write!(f, "{} < {}", self.path, bound),
+            Condition::Contains(needle) => write!(f, "{} contains '{}'", self.path, needle),
+        }
+    }
+}
+
+/// Parses a command line or form value as JSON, falling back to a plain string.
+pub fn parse_value(input: &str) -> Value {
+    serde_json::from_str(input).unwrap_or_else(|_| Value::String(input.to_string()))
+}
+
+/// Numbers decoded from the ledger sometimes come back as strings, so compare textual forms too.
+fn values_match(actual: &Value, expected: &Value) -> bool {
+    actual == expected || as_text(actual) == as_text(expected)
+}
+
+pub fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+}
+
+pub fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn holds(path: &str, condition: Condition, state: Value) -> bool {
+        Predicate::new(JsonPath::parse(path).unwrap(), condition).holds(&state)
+    }
+
+    #[test]
+    fn compares_numbers_and_their_textual_forms() {
+        let state = json!([3, "3", "abcdef"]);
+        assert!(holds("$[0]", Condition::Equals(json!(3)), state.clone()));
+        assert!(holds("$[1]", Condition::Equals(json!(3)), state.clone()));
+        assert!(holds("$[0]", Condition::GreaterThan(2.5), state.clone()));
+        assert!(!holds("$[0]", Condition::LessThan(3.0), state.clone()));
+        assert!(!holds("$[2]", Condition::GreaterThan(0.0), state));
+    }
+
+    #[test]
+    fn missing_values_only_satisfy_nothing() {
+        let state = json!([1]);
+        assert!(holds("$[0]", Condition::Exists, state.clone()));
+        assert!(!holds("$[1]", Condition::Exists, state.clone()));
+        assert!(!holds(
+            "$[1]",
+            Condition::NotEquals(json!(2)),
+            state.clone()
+        ));
+        assert!(holds("$[0]", Condition::NotEquals(json!(2)), state));
+    }
+
+    #[test]
+    fn contains_ignores_case() {
+        let state = json!({ "key": "00ABcd" });
+        assert!(holds(
+            "$.key",
+            Condition::Contains("abCD".to_string()),
+            state.clone()
+        ));
+        assert!(!holds(
+            "$.key",
+            Condition::Contains("ff".to_string()),
+            state
+        ));
+    }
+
+    #[test]
+    fn any_wildcard_match_is_enough() {
+        let state = json!([{ "n": 1 }, { "n": 7 }]);
+        assert!(holds("$[*].n", Condition::GreaterThan(5.0), state.clone()));
+        assert!(!holds("$[*].n", Condition::NotEquals(json!(1)), state));
+    }
+
+    #[test]
+    fn parses_json_values_and_falls_back_to_strings() {
+        assert_eq!(parse_value("3"), json!(3));
+        assert_eq!(parse_value("true"), json!(true));
+        assert_eq!(parse_value("[1, 2]"), json!([1, 2]));
+        assert_eq!(parse_value("0xabc"), json!("0xabc"));
+    }
+
+    #[test]
+    fn displays_as_an_expression() {
+        let path = JsonPath::parse("$[0]").unwrap();
+        assert_eq!(
+            Predicate::new(path.clone(), Condition::Equals(json!(3))).to_string(),
+            "$[0] == 3"
+        );
+        assert_eq!(
+            Predicate::new(path, Condition::Contains("ab".to_string())).to_string(),
+            "$[0] contains 'ab'"
+        );
+    }
+}
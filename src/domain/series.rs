@@ -0,0 +1,327 @@
+use std::{fmt, str::FromStr};
+
+use serde_json::Value;
+
+use crate::domain::{alert::size, path::JsonPath, predicate::as_number, ContractEvent};
+
+/// What a chart line plots for every event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeriesSource {
+    /// A number in the state, `$[0]`.
+    Value(JsonPath),
+    /// Entries of a map, set or list, `size($[1])`. Merkle trees count the leaves inserted so far.
+    Size(JsonPath),
+}
+
+impl SeriesSource {
+    pub fn sample(&self, state: &Value) -> Option<f64> {
+        match self {
+            SeriesSource::Value(path) => path.select_first(state).and_then(as_number),
+            SeriesSource::Size(path) => path
+                .select_first(state)
+                .and_then(collection_size)
+                .map(|entries| entries as f64),
+        }
+    }
+}
+
+fn collection_size(value: &Value) -> Option<usize> {
+    merkle_leaves(value).or_else(|| size(value))
+}
+
+/// A merkle tree field decodes to the tree next to the index of its next free leaf,
+/// `[{"merkle_tree": {"height": 10}}, 2]`, so the index is the number of leaves inserted.
+fn merkle_leaves(value: &Value) -> Option<usize> {
+    match value.as_array()?.as_slice() {
+        [tree, next_free] if tree.get("merkle_tree").is_some() => {
+            as_number(next_free).map(|leaves| leaves as usize)
+        }
+        _ => None,
+    }
+}
+
+impl fmt::Display for SeriesSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeriesSource::Value(path) => write!(f, "{}", path),
+            SeriesSource::Size(path) => write!(f, "size({})", path),
+        }
+    }
+}
+
+impl FromStr for SeriesSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let inner = s
+            .strip_prefix("size(")
+            .or_else(|| s.strip_prefix("len("))
+            .and_then(|rest| rest.strip_suffix(')'));
+        match inner {
+            Some(path) => JsonPath::parse(path).map(SeriesSource::Size),
+            None => JsonPath::parse(s).map(SeriesSource::Value),
+        }
+    }
+}
+
+/// Everything in a state that can be charted, numbers as values and collections as sizes, in document order.
+pub fn chartable(state: &Value) -> Vec<SeriesSource> {
+    let mut sources = Vec::new();
+    chartable_at(JsonPath::root(), state, &mut sources);
+    sources
+}
+
+fn chartable_at(path: JsonPath, value: &Value, sources: &mut Vec<SeriesSource>) {
+    let is_root = path == JsonPath::root();
+    if merkle_leaves(value).is_some() {
+        // the height and the leaf index are no use on their own
+        sources.push(SeriesSource::Size(path));
+        return;
+    }
+    match value {
+        Value::Object(entries) => {
+            if !is_root {
+                sources.push(SeriesSource::Size(path.clone()));
+            }
+            for (key, value) in entries {
+                chartable_at(path.child(key), value, sources);
+            }
+        }
+        Value::Array(items) => {
+            if !is_root {
+                sources.push(SeriesSource::Size(path.clone()));
+            }
+            for (index, item) in items.iter().enumerate() {
+                chartable_at(path.index(index), item, sources);
+            }
+        }
+        Value::Number(_) => sources.push(SeriesSource::Value(path)),
+        Value::String(_) if as_number(value).is_some() => sources.push(SeriesSource::Value(path)),
+        _ => {}
+    }
+}
+
+/// What the horizontal axis of a chart shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChartAxis {
+    #[default]
+    BlockHeight,
+    /// Block timestamp in milliseconds since the unix epoch.
+    Time,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChartPoint {
+    /// Position of the event the point was taken from.
+    pub event: usize,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// One point per event that has a block and a value for the source.
+pub fn series(events: &[ContractEvent], source: &SeriesSource, axis: ChartAxis) -> Vec<ChartPoint> {
+    events
+        .iter()
+        .enumerate()
+        .filter_map(|(index, event)| {
+            let block = &event.transaction.as_ref()?.block;
+            let y = source.sample(event.decoded_state.as_ref()?)?;
+            let x = match axis {
+                ChartAxis::BlockHeight => block.height as f64,
+                ChartAxis::Time => block.timestamp as f64,
+            };
+            Some(ChartPoint { event: index, x, y })
+        })
+        .collect()
+}
+
+/// The area of a chart in data coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChartBounds {
+    pub x_min: f64,
+    pub x_max: f64,
+    pub y_min: f64,
+    pub y_max: f64,
+}
+
+impl ChartBounds {
+    /// Smallest bounds around the points, widened where they are all equal so lines do not collapse.
+    pub fn around<'a>(points: impl IntoIterator<Item = &'a ChartPoint>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let mut bounds = Self {
+            x_min: first.x,
+            x_max: first.x,
+            y_min: first.y,
+            y_max: first.y,
+        };
+        for point in points {
+            bounds.x_min = bounds.x_min.min(point.x);
+            bounds.x_max = bounds.x_max.max(point.x);
+            bounds.y_min = bounds.y_min.min(point.y);
+            bounds.y_max = bounds.y_max.max(point.y);
+        }
+        if bounds.x_min == bounds.x_max {
+            bounds.x_min -= 1.0;
+            bounds.x_max += 1.0;
+        }
+        if bounds.y_min == bounds.y_max {
+            bounds.y_min -= 1.0;
+            bounds.y_max += 1.0;
+        }
+        Some(bounds)
+    }
+
+    /// Scales the horizontal range by `factor` around `x`, below 1 zooms in.
+    pub fn zoom_x(self, x: f64, factor: f64) -> Self {
+        Self {
+            x_min: x - (x - self.x_min) * factor,
+            x_max: x + (self.x_max - x) * factor,
+            ..self
+        }
+    }
+
+    pub fn contains_x(&self, x: f64) -> bool {
+        self.x_min <= x && x <= self.x_max
+    }
+}
+
+/// `count` evenly spaced values from `min` to `max`, both included.
+pub fn ticks(min: f64, max: f64, count: usize) -> Vec<f64> {
+    match count {
+        0 => Vec::new(),
+        1 => vec![min],
+        _ => (0..count)
+            .map(|step| min + (max - min) * step as f64 / (count - 1) as f64)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(height: u64, state: Option<Value>) -> ContractEvent {
+        ContractEvent {
+            decoded_state: state,
+            ..ContractEvent::test_call().at_height(height)
+        }
+    }
+
+    fn source(input: &str) -> SeriesSource {
+        input.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_values_and_sizes() {
+        assert_eq!(
+            source(" size($[1]) "),
+            SeriesSource::Size(JsonPath::parse("$[1]").unwrap())
+        );
+        assert_eq!(source("len($[1])"), source("size($[1])"));
+        assert_eq!(
+            source("$[0]"),
+            SeriesSource::Value(JsonPath::parse("$[0]").unwrap())
+        );
+        assert_eq!(source("size($[1]['a'])").to_string(), "size($[1].a)");
+        assert!("size($[)".parse::<SeriesSource>().is_err());
+    }
+
+    #[test]
+    fn samples_numbers_collections_and_merkle_trees() {
+        let state = json!([7, { "1": 1, "2": 2 }, [{ "merkle_tree": { "height": 10 } }, 3], "12"]);
+        assert_eq!(source("$[0]").sample(&state), Some(7.0));
+        assert_eq!(source("$[3]").sample(&state), Some(12.0));
+        assert_eq!(source("size($[1])").sample(&state), Some(2.0));
+        assert_eq!(source("size($[2])").sample(&state), Some(3.0));
+        assert_eq!(source("size($[0])").sample(&state), None);
+        assert_eq!(source("$[1]").sample(&state), None);
+    }
+
+    #[test]
+    fn lists_what_can_be_charted() {
+        let state = json!([7, { "a": [1, "x"] }, [{ "merkle_tree": { "height": 10 } }, 3]]);
+        let listed: Vec<String> = chartable(&state).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            listed,
+            [
+                "$[0]",
+                "size($[1])",
+                "size($[1].a)",
+                "$[1].a[0]",
+                "size($[2])"
+            ]
+        );
+    }
+
+    #[test]
+    fn plots_events_with_a_block_and_a_value() {
+        let mut pending = event(4, Some(json!([4])));
+        pending.transaction = None;
+        let events = [
+            event(1, Some(json!([1]))),
+            event(2, None),
+            event(3, Some(json!(["x"]))),
+            pending,
+            event(5, Some(json!([5]))),
+        ];
+        let points = series(&events, &source("$[0]"), ChartAxis::BlockHeight);
+        assert_eq!(
+            points,
+            [
+                ChartPoint {
+                    event: 0,
+                    x: 1.0,
+                    y: 1.0
+                },
+                ChartPoint {
+                    event: 4,
+                    x: 5.0,
+                    y: 5.0
+                },
+            ]
+        );
+        let times = series(&events, &source("$[0]"), ChartAxis::Time);
+        assert_eq!(times[1].x, 5000.0);
+    }
+
+    #[test]
+    fn bounds_widen_flat_lines_and_zoom_around_a_point() {
+        let points = [
+            ChartPoint {
+                event: 0,
+                x: 10.0,
+                y: 3.0,
+            },
+            ChartPoint {
+                event: 1,
+                x: 30.0,
+                y: 3.0,
+            },
+        ];
+        let bounds = ChartBounds::around(&points).unwrap();
+        assert_eq!(
+            bounds,
+            ChartBounds {
+                x_min: 10.0,
+                x_max: 30.0,
+                y_min: 2.0,
+                y_max: 4.0
+            }
+        );
+        assert_eq!(ChartBounds::around(&[]), None);
+        let zoomed = bounds.zoom_x(20.0, 0.5);
+        assert_eq!((zoomed.x_min, zoomed.x_max), (15.0, 25.0));
+        assert!(zoomed.contains_x(15.0) && !zoomed.contains_x(26.0));
+    }
+
+    #[test]
+    fn spaces_ticks_evenly() {
+        assert_eq!(ticks(0.0, 10.0, 3), [0.0, 5.0, 10.0]);
+        assert_eq!(ticks(2.0, 10.0, 1), [2.0]);
+        assert!(ticks(0.0, 10.0, 0).is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+use serde_json::{Map, Value};
+
+/// Largest atom shown as a JSON number, Compact stores `Uint<64>` and smaller in at most 8 bytes.
+const MAX_NUMBER_BYTES: usize = 8;
+
+/// Contract state as the ledger holds it, mirrored so it can be turned into JSON in the browser as well.
+///
+/// Compact lays a contract's ledger out positionally: the root is an array with one entry per
+/// `ledger` declaration, in declaration order, so the first field is `$[0]`. Field names are not
+/// part of the on-chain state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateNode {
+    Null,
+    /// A value, as the little-endian atoms it is made of.
+    Cell(Vec<Vec<u8>>),
+    /// Entries of a `Map` or `Set`, sets have `Null` values.
+    Map(Vec<(Vec<Vec<u8>>, StateNode)>),
+    Array(Vec<StateNode>),
+    /// Only the height is kept, the index of the next free leaf is a separate cell next to the tree.
+    MerkleTree {
+        height: u8,
+    },
+}
+
+impl StateNode {
+    /// Arrays stay arrays, maps become objects keyed by their rendered keys, cells become numbers when they fit
+    /// and hex strings otherwise.
+    pub fn to_json(&self) -> Value {
+        match self {
+            StateNode::Null => Value::Null,
+            StateNode::Cell(atoms) => cell_json(atoms),
+            StateNode::Map(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key_string(key), value.to_json()))
+                    .collect::<Map<_, _>>(),
+            ),
+            StateNode::Array(items) => Value::Array(items.iter().map(StateNode::to_json).collect()),
+            StateNode::MerkleTree { height } => {
+                serde_json::json!({ "merkle_tree": { "height": height } })
+            }
+        }
+    }
+}
+
+fn cell_json(atoms: &[Vec<u8>]) -> Value {
+    match atoms {
+        [atom] => atom_json(atom),
+        atoms => Value::Array(atoms.iter().map(|atom| atom_json(atom)).collect()),
+    }
+}
+
+fn atom_json(atom: &[u8]) -> Value {
+    if atom.len() <= MAX_NUMBER_BYTES {
+        let number = atom
+            .iter()
+            .rev()
+            .fold(0u64, |number, byte| (number << 8) | u64::from(*byte));
+        Value::Number(number.into())
+    } else {
+        Value::String(hex::encode(atom))
+    }
+}
+
+/// Object keys are strings, numbers are written in decimal so `$[1]['1']` finds key 1.
+fn key_string(atoms: &[Vec<u8>]) -> String {
+    match cell_json(atoms) {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::domain::path::JsonPath;
+
+    /// `ledger round: Counter;` after three increments.
+    fn counter() -> StateNode {
+        StateNode::Array(vec![StateNode::Cell(vec![vec![3]])])
+    }
+
+    /// The bulletin board example: `state`, `message`, `instance` and `poster`, with a post published.
+    fn bulletin_board() -> StateNode {
+        StateNode::Array(vec![
+            StateNode::Cell(vec![vec![1]]),
+            StateNode::Cell(vec![vec![1], b"hello, midnight".to_vec()]),
+            StateNode::Cell(vec![vec![0x2c, 0x01]]),
+            StateNode::Cell(vec![vec![0xab; 32]]),
+        ])
+    }
+
+    /// `ledger balances: Map<Uint<64>, Uint<64>>; ledger members: Set<Bytes<32>>; ledger tree: MerkleTree<10, Field>;`
+    fn registry() -> StateNode {
+        StateNode::Array(vec![
+            StateNode::Map(vec![
+                (vec![vec![1]], StateNode::Cell(vec![vec![0xe8, 0x03]])),
+                (vec![vec![]], StateNode::Cell(vec![vec![]])),
+            ]),
+            StateNode::Map(vec![(vec![vec![0x11; 32]], StateNode::Null)]),
+            StateNode::Array(vec![
+                StateNode::MerkleTree { height: 10 },
+                StateNode::Cell(vec![vec![2]]),
+            ]),
+        ])
+    }
+
+    fn select(state: &StateNode, path: &str) -> Option<Value> {
+        JsonPath::parse(path)
+            .unwrap()
+            .select_first(&state.to_json())
+            .cloned()
+    }
+
+    #[test]
+    fn fields_are_positional() {
+        assert_eq!(counter().to_json(), json!([3]));
+        assert_eq!(select(&counter(), "$[0]"), Some(json!(3)));
+        assert_eq!(select(&counter(), "$.round"), None);
+    }
+
+    #[test]
+    fn cells_decode_little_endian_and_keep_large_values_as_hex() {
+        let state = bulletin_board().to_json();
+        assert_eq!(state[0], json!(1));
+        assert_eq!(state[1], json!([1, hex::encode("hello, midnight")]));
+        assert_eq!(state[2], json!(300));
+        assert_eq!(state[3], json!("ab".repeat(32)));
+    }
+
+    #[test]
+    fn maps_become_objects_keyed_by_their_keys() {
+        let state = registry();
+        assert_eq!(select(&state, "$[0]['1']"), Some(json!(1000)));
+        assert_eq!(select(&state, "$[0]['0']"), Some(json!(0)));
+        assert_eq!(
+            select(&state, &format!("$[1]['{}']", "11".repeat(32))),
+            Some(Value::Null)
+        );
+        assert_eq!(
+            select(&state, "$[2]"),
+            Some(json!([{ "merkle_tree": { "height": 10 } }, 2]))
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{predicate::Predicate, ActionKind, ContractEvent};
+
+/// A contract the server follows on its own, whether or not a browser is watching it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchedContract {
+    pub address: String,
+    /// Milliseconds since the unix epoch.
+    pub added_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: u64,
+    /// Contract the webhook listens to, it only fires while the contract is on the watchlist.
+    pub address: String,
+    pub url: String,
+    /// Action kinds to deliver, every kind when empty.
+    #[serde(default)]
+    pub event_types: Vec<ActionKind>,
+    /// Only deliver events whose decoded state satisfies this predicate.
+    #[serde(default)]
+    pub predicate: Option<Predicate>,
+    /// Payloads are signed with HMAC-SHA256. The secret itself never leaves the server.
+    pub signed: bool,
+    pub created_at: u64,
+}
+
+impl Webhook {
+    pub fn matches(&self, event: &ContractEvent) -> bool {
+        if !self.event_types.is_empty() && !self.event_types.contains(&event.kind()) {
+            return false;
+        }
+        match (&self.predicate, &event.decoded_state) {
+            (None, _) => true,
+            (Some(predicate), Some(state)) => predicate.holds(state),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Registration request for a webhook.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewWebhook {
+    pub address: String,
+    pub url: String,
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub event_types: Vec<ActionKind>,
+    #[serde(default)]
+    pub predicate: Option<Predicate>,
+}
+
+/// One POST to a webhook, successful or not.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+    /// `None` for alerts posted to a rule's webhook sink.
+    pub webhook_id: Option<u64>,
+    pub url: String,
+    pub transaction_hash: Option<String>,
+    /// Starts at 1 and goes up with every retry.
+    pub attempt: u32,
+    /// HTTP status of the response, absent if no response was received.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub at: u64,
+}
+
+impl DeliveryAttempt {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// A payload that could not be delivered after every retry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// `None` for alerts posted to a rule's webhook sink.
+    pub webhook_id: Option<u64>,
+    pub url: String,
+    /// 0 when the payload never went out because too many were already waiting.
+    pub attempts: u32,
+    pub last_error: String,
+    pub payload: serde_json::Value,
+    pub at: u64,
+}
+
+/// Everything the admin page shows about server-side watching and webhooks.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebhookOverview {
+    pub watchlist: Vec<WatchedContract>,
+    pub webhooks: Vec<Webhook>,
+    /// Most recent first.
+    pub deliveries: Vec<DeliveryAttempt>,
+    /// Most recent first.
+    pub dead_letters: Vec<DeadLetter>,
+}
@@ -1,5 +1,7 @@
 pub mod app;
 
+#[cfg(feature = "ssr")]
+pub mod cli;
 #[cfg(feature = "ssr")]
 pub mod core;
 pub mod domain;
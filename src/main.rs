@@ -1,28 +1,33 @@
 #[cfg(feature = "ssr")]
 #[tokio::main]
-async fn main() {
+async fn main() -> std::process::ExitCode {
     use axum::Router;
     use compactsee::app::*;
-    use compactsee::core::{app_state::AppState, contract_indexer::ContractIndexer};
+    use compactsee::core::{
+        alerts::AlertEngine,
+        analytics::Analytics,
+        api,
+        app_state::AppState,
+        auth::{authorize, Auth},
+        chain_head::ChainHeadTracker,
+        config::{ledger_network_id, IndexerEndpoint, LimitConfig, LogConfig, SmtpConfig},
+        contract_indexer::ContractIndexer,
+        event_store::EventStore,
+        health::{self, HealthChecker},
+        limits::ConnectionLimits,
+        metrics,
+        secret_box::SecretBox,
+        shutdown, telemetry,
+        watchlist::Watchlist,
+        webhooks::WebhookRegistry,
+    };
+    use compactsee::domain::network::Network;
     use leptos::logging::log;
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
-    use midnight_node_ledger_helpers::NetworkId;
+    use std::process::ExitCode;
 
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("midnight_node_ledger_helpers=info".parse().unwrap())
-                .add_directive("midnight_ledger_prototype=info".parse().unwrap())
-                .add_directive(tracing::Level::INFO.into()),
-        )
-        .with_target(false)
-        .with_thread_ids(false)
-        .with_thread_names(false)
-        .with_file(false)
-        .with_line_number(true)
-        .with_timer(tracing_subscriber::fmt::time::time())
-        .init();
+    let telemetry = telemetry::init(&LogConfig::from_env());
 
     let conf = get_configuration(None).unwrap();
     let addr = conf.leptos_options.site_addr;
@@ -30,17 +35,86 @@ async fn main() {
     // Generate the list of routes in your Leptos App
     let routes = generate_route_list(App);
 
-    let indexer_ws = "wss://indexer.testnet-02.midnight.network/api/v1/graphql/ws".to_string();
-    let network_id = NetworkId::TestNet; // hard coding to testnet for now. Make this configurable later
-    let contract_indexer = ContractIndexer::new(network_id, indexer_ws, 300);
+    // hard coding to testnet for now. Make this configurable later
+    let endpoint = IndexerEndpoint::known(Network::TestNet).expect("testnet has a public indexer");
+    // events, the watchlist and webhooks are only kept in memory unless a data directory is configured
+    let data_dir = std::env::var("COMPACTSEE_DATA_DIR")
+        .ok()
+        .map(std::path::PathBuf::from);
+    let event_store = match &data_dir {
+        Some(data_dir) => match EventStore::open(data_dir.clone()) {
+            Ok(event_store) => event_store,
+            Err(e) => {
+                tracing::error!(
+                    "Could not open the event store in {} {}",
+                    data_dir.display(),
+                    e
+                );
+                telemetry.shutdown();
+                return ExitCode::FAILURE;
+            }
+        },
+        None => EventStore::in_memory(),
+    };
+    let health = HealthChecker::new(vec![endpoint.clone()]);
+    let contract_indexer = ContractIndexer::new(
+        ledger_network_id(endpoint.network),
+        endpoint.ws_url.clone(),
+        endpoint.http_url,
+        event_store,
+        300,
+    );
+    let chain_head = ChainHeadTracker::spawn(
+        endpoint.network,
+        endpoint.ws_url,
+        contract_indexer.metrics().clone(),
+        contract_indexer.shutdown_token(),
+    );
+    let watchlist = Watchlist::spawn(&contract_indexer, data_dir.as_deref());
+    let secrets = match SecretBox::open(data_dir.as_deref()) {
+        Ok(secrets) => secrets,
+        Err(e) => {
+            tracing::error!("Could not load the secret key {}", e);
+            telemetry.shutdown();
+            return ExitCode::FAILURE;
+        }
+    };
+    let webhooks = WebhookRegistry::open(data_dir.as_deref(), secrets);
+    webhooks.spawn_dispatcher(watchlist.subscribe());
+    let alerts = AlertEngine::open(
+        data_dir.as_deref(),
+        SmtpConfig::from_env(),
+        contract_indexer.event_store().clone(),
+        webhooks.clone(),
+    );
+    alerts.spawn(contract_indexer.feed());
+    let analytics = Analytics::new(contract_indexer.event_store().clone());
+    // reads stay public unless a key is required for them too
+    let require_read_key = std::env::var("COMPACTSEE_REQUIRE_READ_KEY")
+        .is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true"));
+    let auth = Auth::open(data_dir.as_deref(), !require_read_key);
 
     // set up app state
     let app_state = AppState {
         contract_indexer,
+        chain_head,
+        watchlist,
+        webhooks,
+        alerts,
+        analytics,
+        health,
+        auth,
+        limits: ConnectionLimits::new(LimitConfig::from_env()),
         leptos_options,
     };
 
+    let indexer = app_state.contract_indexer.clone();
+    let chain_head = app_state.chain_head.clone();
     let app = Router::new()
+        .nest("/api/v1", api::v1::router())
+        .route("/metrics", axum::routing::get(metrics::serve))
+        .route("/healthz", axum::routing::get(health::healthz))
+        .route("/readyz", axum::routing::get(health::readyz))
         .leptos_routes_with_context(
             &app_state,
             routes,
@@ -54,15 +128,64 @@ async fn main() {
             },
         )
         .fallback(leptos_axum::file_and_error_handler::<AppState, _>(shell))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.auth.clone(),
+            authorize,
+        ))
         .with_state(app_state);
 
     // run our app with hyper
     // `axum::Server` is a re-export of `hyper::Server`
     log!("listening on http://{}", &addr);
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app.into_make_service())
-        .await
-        .unwrap();
+    let shutdown_token = indexer.shutdown_token();
+    let grace_period = shutdown::grace_period();
+    // limits are counted per client address
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown({
+        let shutdown_token = shutdown_token.clone();
+        async move {
+            shutdown::signal().await;
+            tracing::info!(
+                "Shutting down, waiting up to {:?} for sessions to close",
+                grace_period
+            );
+            // new sessions are refused and every subscription tells its browser before closing upstream
+            shutdown_token.cancel();
+        }
+    });
+
+    let exit_code = tokio::select! {
+        exit_code = async {
+            match server.await {
+                Ok(()) => {
+                    shutdown::drain(&indexer, &chain_head).await;
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    tracing::error!("Server failed {}", e);
+                    // the subscriptions still close upstream, without waiting for them
+                    shutdown_token.cancel();
+                    ExitCode::FAILURE
+                }
+            }
+        } => exit_code,
+        _ = async {
+            shutdown_token.cancelled().await;
+            tokio::time::sleep(grace_period).await;
+        } => {
+            tracing::warn!("Grace period elapsed, dropping the remaining connections");
+            ExitCode::SUCCESS
+        }
+    };
+    if let Err(e) = indexer.event_store().flush() {
+        tracing::error!("Could not flush the event store {}", e);
+    }
+    telemetry.shutdown();
+    exit_code
 }
 
 #[cfg(not(feature = "ssr"))]
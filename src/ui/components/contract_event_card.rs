@@ -4,26 +4,32 @@ use crate::domain::ContractEvent;
 #[component]
 pub fn ContractEventCard(
     event: ContractEvent,
-    index: ReadSignal<usize>,
-    set_selected_event: WriteSignal<Option<ContractEvent>>,
+    /// Position of the event in the whole list, which stays the same while filters hide others.
+    index: usize,
+    #[prop(into)] selected: Signal<bool>,
     set_selected_index: WriteSignal<Option<usize>>,
 ) -> impl IntoView {
-    let event_copy = event.clone();
-    let type_name = event_copy.type_name;
+    let type_name = event.type_name;
+    let transaction = event.transaction;
     view! {
         <div
-            class="card bg-base-100 shadow-sm hover:shadow-md transition-shadow cursor-pointer border border-base-300 hover:border-primary"
-            on:click=move|_| {
-                set_selected_index.set(Some(index.get()));
-                set_selected_event.set(Some(event.clone()));
+            class=move || {
+                let border = if selected.get() { "border-primary" } else { "border-base-300" };
+                format!("card bg-base-100 shadow-sm hover:shadow-md transition-shadow cursor-pointer border {} hover:border-primary", border)
             }
+            on:click=move|_| set_selected_index.set(Some(index))
         >
             <div class="card-body p-3">
                 <div class="flex items-center justify-between">
                     <div class="flex items-center gap-2">
-                        <div class="badge badge-primary badge-sm">{move || index.get()}</div>
+                        <div class="badge badge-primary badge-sm">{index}</div>
                         <span class="font-medium text-sm">{type_name}</span>
                     </div>
+                    {transaction.map(|transaction| view! {
+                        <a class="link text-xs" href=format!("/tx/{}", transaction.hash)>
+                            {format!("block {}", transaction.block.height)}
+                        </a>
+                    })}
                 </div>
             </div>
         </div>
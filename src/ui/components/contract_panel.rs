@@ -1,42 +1,387 @@
-use leptos::prelude::*;
+use leptos::{html::Div, prelude::*};
 
 use crate::{
-    domain::ContractEvent,
-    ui::components::{contract_event_card::ContractEventCard, state_view::StateView},
+    domain::{
+        export::{export_events, ExportFormat},
+        filter::{previous_state, EventFilter, FilterUpdate, FilteredEvents, StateSearch},
+        indexer::parse_timestamp,
+        list::{navigate, RowLayout},
+        ActionKind, ContractEvent,
+    },
+    ui::components::{
+        contract_event_card::ContractEventCard, state_chart::StateChart, state_view::StateView,
+    },
 };
 
+const KINDS: [ActionKind; 3] = [ActionKind::Deploy, ActionKind::Call, ActionKind::Update];
+/// Height of a card in pixels until it has been rendered and measured.
+const ROW_ESTIMATE: i32 = 52;
+/// Space between cards in pixels.
+const ROW_GAP: i32 = 8;
+/// Cards rendered above and below the visible ones, so fast scrolling does not show blank space.
+const OVERSCAN: usize = 5;
+/// Vertical padding of the list, the `p-4` on both sides.
+const LIST_PADDING: i32 = 32;
+
 #[component]
-pub fn ContractPanel(contract_events: ReadSignal<Vec<ContractEvent>>) -> impl IntoView {
-    let (selected_event, set_selected_event) = signal(None::<ContractEvent>);
+pub fn ContractPanel(
+    contract_events: ReadSignal<Vec<ContractEvent>>,
+    /// Changes whenever `contract_events` is replaced rather than appended to.
+    #[prop(optional, into)]
+    generation: Signal<u64>,
+    /// The events come from an imported session the server knows nothing about.
+    #[prop(optional, into)]
+    offline: Signal<bool>,
+) -> impl IntoView {
     let (selected_index, set_selected_index) = signal(None::<usize>);
+    let kinds = RwSignal::new(Vec::<ActionKind>::new());
+    let from_block = RwSignal::new(String::new());
+    let to_block = RwSignal::new(String::new());
+    let from_time = RwSignal::new(String::new());
+    let to_time = RwSignal::new(String::new());
+    let query = RwSignal::new(String::new());
+    let filter = Memo::new(move |_| EventFilter {
+        kinds: kinds.get(),
+        from_block: from_block.read().trim().parse().ok(),
+        to_block: to_block.read().trim().parse().ok(),
+        from_time: local_timestamp(&from_time.read()),
+        to_time: local_timestamp(&to_time.read()),
+        search: StateSearch::parse(&query.read()),
+    });
+    // indices into the full list, so cards keep their number while filtered. Only events appended
+    // since the last run are filtered, unless the filter or the list changed.
+    let filtered = StoredValue::new(FilteredEvents::default());
+    let layout = StoredValue::new(RowLayout::new(ROW_ESTIMATE, ROW_GAP));
+    // the list generation and a count of the times the filtered positions changed
+    let revision = Memo::new(move |previous: Option<&(u64, u64)>| {
+        let generation = generation.get();
+        let update = contract_events.with(|events| {
+            filter.with(|filter| {
+                filtered.try_update_value(|filtered| filtered.update(filter, events, generation))
+            })
+        });
+        let (last_generation, revision) = previous.copied().unwrap_or((generation, 0));
+        match update {
+            Some(FilterUpdate::Unchanged) | None => (generation, revision),
+            Some(FilterUpdate::Appended) => (generation, revision + 1),
+            Some(FilterUpdate::Rebuilt) => {
+                layout.update_value(|layout| {
+                    if generation == last_generation {
+                        layout.reset()
+                    } else {
+                        layout.clear()
+                    }
+                });
+                (generation, revision + 1)
+            }
+        }
+    });
+    // bumped when a rendered card turns out to have another height than the one it was laid out with
+    let measured = RwSignal::new(0u64);
+    let laid_out = Memo::new(move |previous: Option<&u64>| {
+        revision.track();
+        measured.track();
+        filtered
+            .with_value(|filtered| layout.update_value(|layout| layout.sync(&filtered.positions)));
+        previous.map_or(0, |previous| previous + 1)
+    });
+
+    let show_chart = RwSignal::new(false);
+    let list = NodeRef::<Div>::new();
+    let scroll_top = RwSignal::new(0);
+    let viewport_height = RwSignal::new(384);
+    let measure = move || {
+        if let Some(list) = list.get() {
+            scroll_top.set(list.scroll_top());
+            viewport_height.set(list.client_height());
+        }
+    };
+    // only the cards on screen are rendered, each placed at its position in the full height list
+    let window = move || {
+        laid_out.track();
+        let rows = layout
+            .with_value(|layout| layout.window(scroll_top.get(), viewport_height.get(), OVERSCAN));
+        let generation = revision.get().0;
+        filtered.with_value(|filtered| {
+            contract_events.with(|events| {
+                let positions = filtered.positions.get(rows.clone()).unwrap_or_default();
+                positions
+                    .iter()
+                    .zip(rows)
+                    .filter_map(|(index, position)| {
+                        Some((generation, position, *index, events.get(*index)?.clone()))
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+    };
+    let on_keydown = move |ev: leptos::ev::KeyboardEvent| {
+        let page = layout
+            .with_value(|layout| {
+                layout
+                    .window(
+                        scroll_top.get_untracked(),
+                        viewport_height.get_untracked(),
+                        0,
+                    )
+                    .len()
+            })
+            .max(1);
+        let Some((position, index)) = filtered.with_value(|filtered| {
+            let position = navigate(
+                &filtered.positions,
+                selected_index.get_untracked(),
+                &ev.key(),
+                page,
+            )?;
+            Some((position, filtered.positions[position]))
+        }) else {
+            return;
+        };
+        ev.prevent_default();
+        set_selected_index.set(Some(index));
+        // keep the selected card on screen
+        if let Some(list) = list.get_untracked() {
+            let (top, bottom) = layout.with_value(|layout| {
+                (
+                    layout.top(position),
+                    layout.top(position) + layout.height(position),
+                )
+            });
+            let height = list.client_height() - LIST_PADDING;
+            if top < list.scroll_top() {
+                list.set_scroll_top(top);
+            } else if bottom > list.scroll_top() + height {
+                list.set_scroll_top(bottom - height);
+            }
+        }
+        measure();
+    };
+    let clear = move |_| {
+        kinds.set(Vec::new());
+        for text in [from_block, to_block, from_time, to_time, query] {
+            text.set(String::new());
+        }
+    };
+    let address = move || {
+        contract_events
+            .read()
+            .last()
+            .map(|event| event.address.clone())
+    };
+    // the events collected here, which can be more or fewer than the server recorded
+    let export = move |format: ExportFormat| {
+        let exported = contract_events.with_untracked(|events| export_events(events, format));
+        let filename = format!(
+            "{}.{}",
+            address().unwrap_or_else(|| "session".to_string()),
+            format.extension()
+        );
+        let saved = exported
+            .map_err(|e| e.to_string())
+            .and_then(|text| download(&filename, format.content_type(), &text));
+        if let Err(e) = saved {
+            leptos::logging::error!("Could not export {} {}", filename, e);
+        }
+    };
     view! {
+        <div class="flex justify-end gap-2 w-[1000px] mb-2">
+            <button
+                class="btn btn-sm"
+                class:btn-active=move || show_chart.get()
+                on:click=move |_| show_chart.update(|shown| *shown = !*shown)
+            >
+                "Chart"
+            </button>
+            <a
+                class="btn btn-sm"
+                class:hidden=move || offline.get() || address().is_none()
+                href=move || format!("/contract/{}/analytics", address().unwrap_or_default())
+            >
+                "Analytics"
+            </a>
+            <div class="dropdown dropdown-end" class:hidden=move || address().is_none()>
+                <div tabindex="0" role="button" class="btn btn-sm">"Export"</div>
+                <ul tabindex="0" class="dropdown-content menu bg-base-100 rounded-box z-10 w-40 p-2 shadow-sm">
+                    {ExportFormat::ALL.into_iter().map(|format| view! {
+                        <li>
+                            <button on:click=move |_| export(format)>
+                                {format.extension().to_uppercase()}
+                            </button>
+                        </li>
+                    }).collect_view()}
+                </ul>
+            </div>
+        </div>
+        <div class="flex flex-wrap items-end gap-2 w-[1000px] mb-2">
+            <div class="join">
+                {KINDS.into_iter().map(|kind| view! {
+                    <input
+                        type="checkbox"
+                        class="join-item btn btn-sm"
+                        aria-label=kind.to_string()
+                        prop:checked=move || kinds.read().contains(&kind)
+                        on:change=move |_| kinds.update(|kinds| {
+                            match kinds.iter().position(|k| *k == kind) {
+                                Some(position) => { kinds.remove(position); }
+                                None => kinds.push(kind),
+                            }
+                        })
+                    />
+                }).collect_view()}
+            </div>
+            <input type="number" min="0" placeholder="From block" class="input input-sm w-28" bind:value=from_block />
+            <input type="number" min="0" placeholder="To block" class="input input-sm w-28" bind:value=to_block />
+            <label class="input input-sm w-52" title="Local time">
+                <span class="label">"From"</span>
+                <input type="datetime-local" bind:value=from_time />
+            </label>
+            <label class="input input-sm w-52" title="Local time">
+                <span class="label">"To"</span>
+                <input type="datetime-local" bind:value=to_time />
+            </label>
+            <input
+                type="search"
+                placeholder="Search state, e.g. 0xabc or $[1] changed"
+                class="input input-sm flex-1 min-w-64"
+                bind:value=query
+            />
+            <Show when=move || !filter.read().is_empty()>
+                <span class="text-sm text-base-content/60">
+                    {move || {
+                        revision.track();
+                        let shown = filtered.with_value(|filtered| filtered.positions.len());
+                        format!("{} of {}", shown, contract_events.read().len())
+                    }}
+                </span>
+                <button class="btn btn-sm btn-ghost" on:click=clear>"Clear"</button>
+            </Show>
+        </div>
         <div class="flex gap-4 h-96 w-[1000px]">
             // Left panel - Event cards
-            <div class="w-1/2 overflow-y-auto border border-base-300 rounded-lg p-4">
-                <div class="space-y-2">
-                    <ForEnumerate
-                        each=move || contract_events.get()
-                        key=|event| event.state.clone() // need to change this
-                        let(index, event) >
-                            <ContractEventCard event=event set_selected_event=set_selected_event set_selected_index=set_selected_index index=index/>
-                    </ForEnumerate>
+            <div
+                node_ref=list
+                tabindex="0"
+                class="w-1/2 overflow-y-auto border border-base-300 rounded-lg p-4 focus:outline-primary"
+                on:scroll=move |_| measure()
+                on:keydown=on_keydown
+            >
+                <div
+                    class="relative"
+                    style:height=move || {
+                        laid_out.track();
+                        format!("{}px", layout.with_value(RowLayout::total))
+                    }
+                >
+                    <For
+                        each=window
+                        key=|(generation, position, index, _)| (*generation, *position, *index)
+                        children=move |(_, position, index, event)| {
+                            let row = NodeRef::<Div>::new();
+                            // cards are as tall as their content, the layout learns it once they are on screen
+                            Effect::new(move |_| {
+                                if let Some(row) = row.get() {
+                                    let height = row.offset_height();
+                                    if layout.try_update_value(|layout| layout.measure(position, index, height)) == Some(true) {
+                                        measured.update(|measured| *measured += 1);
+                                    }
+                                }
+                            });
+                            view! {
+                                <div
+                                    node_ref=row
+                                    class="absolute inset-x-0"
+                                    style:top=move || {
+                                        laid_out.track();
+                                        format!("{}px", layout.with_value(|layout| layout.top(position)))
+                                    }
+                                >
+                                    <ContractEventCard
+                                        event=event
+                                        index=index
+                                        selected=Signal::derive(move || selected_index.get() == Some(index))
+                                        set_selected_index=set_selected_index
+                                    />
+                                </div>
+                            }
+                        }
+                    />
                 </div>
             </div>
 
             // Right panel - State view
             <div class="w-1/2 border border-base-300 rounded-lg p-4">
-                <Show
-                    when=move || { selected_event.get().is_some() }
-                    fallback=move || view! {
+                {move || {
+                    let search = filter.read().search.clone();
+                    contract_events.with(|events| {
+                        let index = selected_index.get()?;
+                        let event = events.get(index)?.clone();
+                        let previous_state = previous_state(events, index).cloned();
+                        Some(view! {
+                            <StateView event=event previous_state=previous_state search=search event_index=index />
+                        }.into_any())
+                    })
+                    .unwrap_or_else(|| view! {
                         <div class="card bg-base-200 h-full">
                             <div class="card-body flex items-center justify-center">
                                 <p class="text-base-content/60">"Select an event to view details"</p>
                             </div>
                         </div>
-                    }>
-                    <StateView state=selected_event.get().unwrap().state event_index=selected_index />
-                </Show>
+                    }.into_any())
+                }}
             </div>
         </div>
+        <Show when=move || show_chart.get()>
+            <StateChart
+                contract_events=contract_events
+                selected_index=selected_index
+                set_selected_index=set_selected_index
+            />
+        </Show>
     } .into_any()
 }
+
+/// `datetime-local` inputs hold local time. The browser knows the offset that applied on that date, which
+/// differs from today's across daylight saving changes.
+#[cfg(feature = "hydrate")]
+fn local_timestamp(input: &str) -> Option<u64> {
+    // only well-formed input, `Date.parse` is lenient about what it accepts
+    parse_timestamp(input)?;
+    let millis = web_sys::js_sys::Date::parse(input.trim());
+    (millis.is_finite() && millis >= 0.0).then_some(millis as u64)
+}
+
+#[cfg(not(feature = "hydrate"))]
+fn local_timestamp(input: &str) -> Option<u64> {
+    parse_timestamp(input)
+}
+
+/// Saves `text` as a file, through an object URL that is released right after the click.
+#[cfg(feature = "hydrate")]
+fn download(filename: &str, content_type: &str, text: &str) -> Result<(), String> {
+    use web_sys::{
+        js_sys::Array,
+        wasm_bindgen::{JsCast, JsValue},
+        Blob, BlobPropertyBag, HtmlAnchorElement, Url,
+    };
+
+    let describe = |e: JsValue| e.as_string().unwrap_or_else(|| format!("{:?}", e));
+    let options = BlobPropertyBag::new();
+    options.set_type(content_type);
+    let parts = Array::of1(&JsValue::from_str(text));
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options).map_err(describe)?;
+    let url = Url::create_object_url_with_blob(&blob).map_err(describe)?;
+    let anchor: HtmlAnchorElement = document()
+        .create_element("a")
+        .map_err(describe)?
+        .unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+    Url::revoke_object_url(&url).map_err(describe)
+}
+
+#[cfg(not(feature = "hydrate"))]
+fn download(filename: &str, _content_type: &str, _text: &str) -> Result<(), String> {
+    Err(format!("{} can only be saved from the browser", filename))
+}
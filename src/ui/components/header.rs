@@ -1,7 +1,25 @@
-use leptos::prelude::*;
+use std::time::Duration;
+
+use leptos::{prelude::*, task::spawn_local};
+
+use crate::domain::indexer::ChainHead;
 
 #[component]
 pub fn Header() -> impl IntoView {
+    let (chain_head, set_chain_head) = signal(None::<ChainHead>);
+
+    if cfg!(feature = "hydrate") {
+        let refresh = move || {
+            spawn_local(async move {
+                if let Ok(head) = get_chain_head().await {
+                    set_chain_head.set(Some(head));
+                }
+            })
+        };
+        refresh();
+        set_interval(refresh, Duration::from_secs(3));
+    }
+
     view! {
         <div class="navbar bg-base-100 shadow-sm">
           <div class="navbar-start">
@@ -12,6 +30,44 @@ pub fn Header() -> impl IntoView {
              </div>
             </a>
           </div>
+          <div class="navbar-end pr-4">
+            {move || chain_head.get().map(|head| view! { <ChainHeadBadge head=head /> })}
+          </div>
         </div>
     }
 }
+
+#[component]
+fn ChainHeadBadge(head: ChainHead) -> impl IntoView {
+    let lagging = head.lagging;
+    let label = match (&head.block, head.seconds_since_block) {
+        (Some(block), Some(seconds)) => format!(
+            "{} block {} · {}s ago",
+            head.network, block.height, seconds
+        ),
+        _ => format!("{} · waiting for blocks", head.network),
+    };
+    let title = head
+        .block
+        .as_ref()
+        .map(|block| block.hash.clone())
+        .unwrap_or_default();
+    view! {
+        <div class="flex items-center gap-2" title=title>
+            <div class="badge badge-soft" class:badge-success=!lagging class:badge-warning=lagging>
+                {label}
+            </div>
+            <Show when=move || lagging fallback=move || view! {}>
+                <span class="text-xs text-warning">"Indexer is lagging behind"</span>
+            </Show>
+        </div>
+    }
+}
+
+#[server]
+pub async fn get_chain_head() -> Result<ChainHead, ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    Ok(app_state.chain_head.status())
+}
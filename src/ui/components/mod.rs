@@ -1,4 +1,6 @@
 pub mod contract_event_card;
 pub mod contract_panel;
 pub mod header;
+pub mod state_chart;
 pub mod state_view;
+pub mod transaction_summary;
@@ -0,0 +1,296 @@
+use leptos::{prelude::*, svg::Svg};
+
+use crate::domain::{
+    indexer::{format_timestamp, DateTime},
+    series::{chartable, series, ticks, ChartAxis, ChartBounds, ChartPoint, SeriesSource},
+    ContractEvent,
+};
+
+const WIDTH: f64 = 1000.0;
+const HEIGHT: f64 = 260.0;
+/// Room for the axis labels around the plot.
+const LEFT: f64 = 64.0;
+const RIGHT: f64 = 16.0;
+const TOP: f64 = 12.0;
+const BOTTOM: f64 = 28.0;
+const PLOT_WIDTH: f64 = WIDTH - LEFT - RIGHT;
+const PLOT_HEIGHT: f64 = HEIGHT - TOP - BOTTOM;
+const COLORS: [&str; 6] = ["#3b82f6", "#ef4444", "#10b981", "#f59e0b", "#8b5cf6", "#ec4899"];
+
+/// Line chart of numeric state fields and collection sizes across every event of the contract.
+#[component]
+pub fn StateChart(
+    contract_events: ReadSignal<Vec<ContractEvent>>,
+    selected_index: ReadSignal<Option<usize>>,
+    set_selected_index: WriteSignal<Option<usize>>,
+) -> impl IntoView {
+    let sources = RwSignal::new(Vec::<SeriesSource>::new());
+    let input = RwSignal::new(String::new());
+    let error = RwSignal::new(None::<String>);
+    let axis = RwSignal::new(ChartAxis::BlockHeight);
+    // horizontal range the user zoomed into, the whole history when `None`
+    let zoom = RwSignal::new(None::<(f64, f64)>);
+    let hovered = RwSignal::new(None::<(usize, ChartPoint)>);
+    let plot = NodeRef::<Svg>::new();
+
+    let options = Memo::new(move |_| {
+        contract_events.with(|events| {
+            events
+                .iter()
+                .rev()
+                .find_map(|event| event.decoded_state.as_ref())
+                .map(chartable)
+                .unwrap_or_default()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        })
+    });
+    let lines = Memo::new(move |_| {
+        let axis = axis.get();
+        sources.with(|sources| {
+            contract_events.with(|events| {
+                sources
+                    .iter()
+                    .map(|source| series(events, source, axis))
+                    .collect::<Vec<_>>()
+            })
+        })
+    });
+    let bounds = Memo::new(move |_| {
+        lines.with(|lines| {
+            let full = ChartBounds::around(lines.iter().flatten())?;
+            let Some((x_min, x_max)) = zoom.get() else {
+                return Some(full);
+            };
+            let zoomed = ChartBounds {
+                x_min,
+                x_max,
+                ..full
+            };
+            // fit the values to what is in view
+            let in_view = ChartBounds::around(
+                lines
+                    .iter()
+                    .flatten()
+                    .filter(|point| zoomed.contains_x(point.x)),
+            );
+            Some(ChartBounds {
+                x_min,
+                x_max,
+                ..in_view.unwrap_or(full)
+            })
+        })
+    });
+
+    let add = move || {
+        let text = input.get_untracked();
+        if text.trim().is_empty() {
+            return;
+        }
+        match text.parse::<SeriesSource>() {
+            Ok(source) => {
+                sources.update(|sources| {
+                    if !sources.contains(&source) {
+                        sources.push(source);
+                    }
+                });
+                input.set(String::new());
+                error.set(None);
+            }
+            Err(e) => error.set(Some(e)),
+        }
+    };
+    // data x coordinate under the pointer, if it is over the plot. Only the browser has a layout to measure.
+    #[cfg(not(feature = "hydrate"))]
+    let pointer_x = move |_client_x: i32| None::<f64>;
+    #[cfg(feature = "hydrate")]
+    let pointer_x = move |client_x: i32| {
+        let rect = plot.get_untracked()?.get_bounding_client_rect();
+        let bounds = bounds.get_untracked()?;
+        let x = (client_x as f64 - rect.left()) * WIDTH / rect.width() - LEFT;
+        (0.0..=PLOT_WIDTH)
+            .contains(&x)
+            .then(|| bounds.x_min + x / PLOT_WIDTH * (bounds.x_max - bounds.x_min))
+    };
+    let on_wheel = move |ev: leptos::ev::WheelEvent| {
+        let (Some(x), Some(bounds)) = (pointer_x(ev.client_x()), bounds.get_untracked()) else {
+            return;
+        };
+        ev.prevent_default();
+        let factor = if ev.delta_y() > 0.0 { 1.25 } else { 0.8 };
+        let zoomed = bounds.zoom_x(x, factor);
+        let full = lines.with_untracked(|lines| ChartBounds::around(lines.iter().flatten()));
+        match full {
+            Some(full) if zoomed.x_min <= full.x_min && zoomed.x_max >= full.x_max => zoom.set(None),
+            _ => zoom.set(Some((zoomed.x_min, zoomed.x_max))),
+        }
+    };
+    // hovering a point shows its event in the state view
+    let on_mousemove = move |ev: leptos::ev::MouseEvent| {
+        let Some(x) = pointer_x(ev.client_x()) else {
+            return;
+        };
+        let nearest = lines.with_untracked(|lines| {
+            lines
+                .iter()
+                .enumerate()
+                .flat_map(|(line, points)| points.iter().map(move |point| (line, *point)))
+                .min_by(|(_, a), (_, b)| (a.x - x).abs().total_cmp(&(b.x - x).abs()))
+        });
+        if let Some((line, point)) = nearest {
+            if hovered.get_untracked().map(|(_, hovered)| hovered.event) != Some(point.event) {
+                set_selected_index.set(Some(point.event));
+            }
+            hovered.set(Some((line, point)));
+        }
+    };
+
+    let axis_label = move |x: f64| match axis.get() {
+        ChartAxis::BlockHeight => format!("{:.0}", x),
+        ChartAxis::Time => {
+            let time = DateTime::from_timestamp(x as u64);
+            format!("{} {}", time.month_day(), time.clock())
+        }
+    };
+    let to_px = move |bounds: ChartBounds, point: &ChartPoint| {
+        (
+            (point.x - bounds.x_min) / (bounds.x_max - bounds.x_min) * PLOT_WIDTH,
+            (1.0 - (point.y - bounds.y_min) / (bounds.y_max - bounds.y_min)) * PLOT_HEIGHT,
+        )
+    };
+
+    view! {
+        <div class="card bg-base-100 border border-base-300 w-[1000px] mt-4">
+            <div class="card-body p-4 gap-2">
+                <div class="flex flex-wrap items-center gap-2">
+                    <input
+                        type="text"
+                        list="chartable-paths"
+                        placeholder="$[0] or size($[1])"
+                        class="input input-sm w-72"
+                        bind:value=input
+                        on:keydown=move |ev| if ev.key() == "Enter" { add() }
+                    />
+                    <datalist id="chartable-paths">
+                        <For each=move || options.get() key=|option| option.clone() let(option)>
+                            <option value=option></option>
+                        </For>
+                    </datalist>
+                    <button class="btn btn-sm" on:click=move |_| add()>"Add"</button>
+                    <div class="join">
+                        <button
+                            class="join-item btn btn-sm"
+                            class:btn-active=move || axis.get() == ChartAxis::BlockHeight
+                            on:click=move |_| { axis.set(ChartAxis::BlockHeight); zoom.set(None); }
+                        >"Block"</button>
+                        <button
+                            class="join-item btn btn-sm"
+                            class:btn-active=move || axis.get() == ChartAxis::Time
+                            on:click=move |_| { axis.set(ChartAxis::Time); zoom.set(None); }
+                        >"Time"</button>
+                    </div>
+                    <button class="btn btn-sm btn-ghost" class:hidden=move || zoom.get().is_none() on:click=move |_| zoom.set(None)>
+                        "Reset zoom"
+                    </button>
+                    <span class="text-sm text-error">{move || error.get()}</span>
+                </div>
+                <div class="flex flex-wrap gap-2">
+                    {move || sources.get().into_iter().enumerate().map(|(line, source)| {
+                        let removed = source.clone();
+                        view! {
+                            <span class="badge gap-1" style:border-color=COLORS[line % COLORS.len()]>
+                                <span style:color=COLORS[line % COLORS.len()]>"●"</span>
+                                {source.to_string()}
+                                <button class="cursor-pointer" on:click=move |_| sources.update(|sources| sources.retain(|s| *s != removed))>"✕"</button>
+                            </span>
+                        }
+                    }).collect_view()}
+                </div>
+                <Show
+                    when=move || bounds.get().is_some()
+                    fallback=|| view! {
+                        <p class="text-sm text-base-content/60">
+                            "Add a numeric path or a collection size to chart it. Scroll over the chart to zoom, hover to step through events."
+                        </p>
+                    }
+                >
+                    {move || {
+                        let bounds = bounds.get()?;
+                        let x_ticks = ticks(bounds.x_min, bounds.x_max, 6);
+                        let y_ticks = ticks(bounds.y_min, bounds.y_max, 5);
+                        Some(view! {
+                            <svg
+                                node_ref=plot
+                                viewBox=format!("0 0 {} {}", WIDTH, HEIGHT)
+                                class="w-full select-none"
+                                on:wheel=on_wheel
+                                on:mousemove=on_mousemove
+                                on:mouseleave=move |_| hovered.set(None)
+                            >
+                                {y_ticks.into_iter().map(|y| {
+                                    let py = TOP + (1.0 - (y - bounds.y_min) / (bounds.y_max - bounds.y_min)) * PLOT_HEIGHT;
+                                    view! {
+                                        <line x1=LEFT x2=WIDTH - RIGHT y1=py y2=py stroke="currentColor" stroke-opacity="0.1" />
+                                        <text x=LEFT - 6.0 y=py + 4.0 text-anchor="end" font-size="11" fill="currentColor">{format_value(y)}</text>
+                                    }
+                                }).collect_view()}
+                                {x_ticks.into_iter().map(|x| {
+                                    let px = LEFT + (x - bounds.x_min) / (bounds.x_max - bounds.x_min) * PLOT_WIDTH;
+                                    view! {
+                                        <text x=px y=HEIGHT - 8.0 text-anchor="middle" font-size="11" fill="currentColor">{axis_label(x)}</text>
+                                    }
+                                }).collect_view()}
+                                // nested svg so zoomed lines are clipped to the plot
+                                <svg x=LEFT y=TOP width=PLOT_WIDTH height=PLOT_HEIGHT>
+                                    {lines.get().into_iter().enumerate().map(|(line, points)| {
+                                        let color = COLORS[line % COLORS.len()];
+                                        let path = points
+                                            .iter()
+                                            .map(|point| {
+                                                let (x, y) = to_px(bounds, point);
+                                                format!("{:.1},{:.1}", x, y)
+                                            })
+                                            .collect::<Vec<_>>()
+                                            .join(" ");
+                                        view! { <polyline points=path fill="none" stroke=color stroke-width="1.5" /> }
+                                    }).collect_view()}
+                                    // the markers follow the selection on their own, hovering does not redraw the lines
+                                    {move || {
+                                        let selected = selected_index.get()?;
+                                        Some(lines.with(|lines| {
+                                            lines.iter().enumerate().filter_map(|(line, points)| {
+                                                let point = points.iter().find(|point| point.event == selected)?;
+                                                let (x, y) = to_px(bounds, point);
+                                                Some(view! { <circle cx=x cy=y r="4" fill=COLORS[line % COLORS.len()] /> })
+                                            }).collect_view()
+                                        }))
+                                    }}
+                                </svg>
+                            </svg>
+                        })
+                    }}
+                </Show>
+                <p class="text-sm h-5">
+                    {move || {
+                        let (line, point) = hovered.get()?;
+                        let source = sources.with(|sources| sources.get(line).map(ToString::to_string))?;
+                        let at = match axis.get() {
+                            ChartAxis::BlockHeight => format!("Block {:.0}", point.x),
+                            ChartAxis::Time => format_timestamp(point.x as u64),
+                        };
+                        Some(format!("Event {} · {} · {} = {}", point.event, at, source, format_value(point.y)))
+                    }}
+                </p>
+            </div>
+        </div>
+    }
+}
+
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
@@ -1,15 +1,65 @@
 use leptos::prelude::*;
 
+use crate::domain::{
+    filter::{changes, state_lines, StateSearch},
+    ContractEvent,
+};
+
 #[component]
-pub fn StateView(state: String, event_index: ReadSignal<Option<usize>>) -> impl IntoView {
+pub fn StateView(
+    event: ContractEvent,
+    /// Decoded state of the event before, to highlight what a `$.path changed` search matched.
+    previous_state: Option<serde_json::Value>,
+    search: Option<StateSearch>,
+    event_index: usize,
+) -> impl IntoView {
+    let lines = match &event.decoded_state {
+        Some(state) => {
+            let changes = changes(previous_state.as_ref(), &event);
+            state_lines(state)
+                .into_iter()
+                .map(|line| {
+                    let marked = search
+                        .as_ref()
+                        .is_some_and(|search| search.highlights(&line, &changes));
+                    view! {
+                        <span class=if marked { "block bg-warning/40" } else { "block" }>
+                            {highlight(search.as_ref(), &line.text)}
+                        </span>
+                    }
+                    .into_any()
+                })
+                .collect_view()
+                .into_any()
+        }
+        None => highlight(search.as_ref(), &event.state),
+    };
     view! {
         <div class="card bg-base-100 h-full">
             <div class="card-header text-center pr-4">
                 <h3 class="card-title text-lg">"Event: "{event_index}</h3>
             </div>
             <div class="card-body p-4 h-0 flex-1 overflow-auto">
-                <pre class="whitespace-pre-wrap break-words text-sm font-mono bg-base-200 p-4 rounded">{state}</pre>
+                <pre class="whitespace-pre-wrap break-words text-sm font-mono bg-base-200 p-4 rounded">{lines}</pre>
             </div>
         </div>
     }
 }
+
+/// The text with the parts a text search matched in `<mark>`.
+fn highlight(search: Option<&StateSearch>, text: &str) -> AnyView {
+    match search {
+        Some(search) => search.split(text),
+        None => vec![(text, false)],
+    }
+    .into_iter()
+    .map(|(piece, matched)| {
+        if matched {
+            view! { <mark>{piece.to_string()}</mark> }.into_any()
+        } else {
+            piece.to_string().into_any()
+        }
+    })
+    .collect_view()
+    .into_any()
+}
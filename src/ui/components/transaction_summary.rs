@@ -0,0 +1,35 @@
+use leptos::prelude::*;
+
+use crate::domain::{indexer::Transaction, ContractEvent};
+
+#[component]
+pub fn TransactionSummary(transaction: Transaction) -> impl IntoView {
+    let hash = transaction.hash.clone();
+    view! {
+        <div class="border border-base-300 rounded-lg p-3">
+            <a class="link link-primary font-mono text-sm break-all" href=format!("/tx/{}", hash)>{hash.clone()}</a>
+            <div class="space-y-1 mt-2">
+                {transaction.contract_actions.into_iter().map(|action| view! {
+                    <ContractActionRow action=action show_state=false />
+                }).collect_view()}
+            </div>
+        </div>
+    }
+}
+
+#[component]
+pub fn ContractActionRow(action: ContractEvent, show_state: bool) -> impl IntoView {
+    let address = action.address.clone();
+    let state = action.display_state();
+    view! {
+        <div class="text-sm">
+            <div class="flex items-center gap-2">
+                <span class="badge badge-primary badge-sm">{action.type_name.clone()}</span>
+                <a class="link font-mono break-all" href=format!("/contract/{}", address)>{address.clone()}</a>
+            </div>
+            {show_state.then(|| view! {
+                <pre class="whitespace-pre-wrap break-words text-xs font-mono bg-base-200 p-2 mt-1 rounded">{state}</pre>
+            })}
+        </div>
+    }
+}
@@ -0,0 +1,980 @@
+use leptos::{prelude::*, task::spawn_local};
+use leptos_router::hooks::use_navigate;
+
+use crate::domain::{
+    alert::{Alert, AlertCondition, AlertOverview, AlertSink, NewAlertRule},
+    auth::{AuthOverview, CreatedApiKey, NewApiKey, Scope},
+    connection::{format_duration, ConnectionOverview, ConnectionStats},
+    indexer::format_timestamp,
+    path::JsonPath,
+    predicate::{parse_value, Condition, Predicate},
+    webhook::{DeadLetter, DeliveryAttempt, NewWebhook, WebhookOverview},
+    ActionKind,
+};
+
+#[component]
+pub fn AdminPage() -> impl IntoView {
+    // bumped after every change so the overview is fetched again
+    let version = RwSignal::new(0u32);
+    let overview = Resource::new(move || version.get(), |_| get_webhook_overview());
+    let alert_overview = Resource::new(move || version.get(), |_| get_alert_overview());
+    let connections = Resource::new(move || version.get(), |_| get_connection_overview());
+    let access = Resource::new(move || version.get(), |_| get_auth_overview());
+    let refresh = move || version.update(|v| *v += 1);
+
+    view! {
+        <div class="flex flex-col items-center gap-4 mt-8">
+            <div class="w-[1000px] flex justify-end">
+                <button class="btn btn-sm" on:click=move |_| refresh()>"Refresh"</button>
+            </div>
+            <Suspense fallback=move || view! { <div class="skeleton h-32 w-96"></div> }>
+                {move || Suspend::new(async move {
+                    match connections.await {
+                        Ok(overview) => view! { <Connections overview=overview on_change=refresh /> }.into_any(),
+                        Err(e) => view! {
+                            <div role="alert" class="alert alert-error alert-soft">{e.to_string()}</div>
+                        }.into_any(),
+                    }
+                })}
+            </Suspense>
+            <NewApiKeyForm on_added=refresh />
+            <Suspense fallback=move || view! { <div class="skeleton h-32 w-96"></div> }>
+                {move || Suspend::new(async move {
+                    match access.await {
+                        Ok(overview) => view! { <Access overview=overview on_change=refresh /> }.into_any(),
+                        Err(e) => view! {
+                            <div role="alert" class="alert alert-error alert-soft">{e.to_string()}</div>
+                        }.into_any(),
+                    }
+                })}
+            </Suspense>
+            <NewWebhookForm on_added=refresh />
+            <Suspense fallback=move || view! { <div class="skeleton h-32 w-96"></div> }>
+                {move || Suspend::new(async move {
+                    match overview.await {
+                        Ok(overview) => view! { <Overview overview=overview on_change=refresh /> }.into_any(),
+                        Err(e) => view! {
+                            <div role="alert" class="alert alert-error alert-soft">{e.to_string()}</div>
+                        }.into_any(),
+                    }
+                })}
+            </Suspense>
+            <NewAlertRuleForm on_added=refresh />
+            <Suspense fallback=move || view! { <div class="skeleton h-32 w-96"></div> }>
+                {move || Suspend::new(async move {
+                    match alert_overview.await {
+                        Ok(overview) => view! { <AlertRules overview=overview on_change=refresh /> }.into_any(),
+                        Err(e) => view! {
+                            <div role="alert" class="alert alert-error alert-soft">{e.to_string()}</div>
+                        }.into_any(),
+                    }
+                })}
+            </Suspense>
+        </div>
+    }
+}
+
+#[component]
+fn Connections(
+    overview: ConnectionOverview,
+    on_change: impl Fn() + Copy + Send + Sync + 'static,
+) -> impl IntoView {
+    let ConnectionOverview {
+        sessions,
+        subscriptions,
+        idle_timeout,
+        at,
+    } = overview;
+    let timeout = RwSignal::new(idle_timeout.to_string());
+    let (error_message, set_error_message) = signal(None::<String>);
+
+    let save_timeout = move |_| {
+        let seconds = match timeout.get_untracked().trim().parse::<u64>() {
+            Ok(seconds) if seconds > 0 => seconds,
+            _ => {
+                set_error_message.set(Some("Enter a number of seconds".to_string()));
+                return;
+            }
+        };
+        spawn_local(async move {
+            match set_idle_timeout(seconds).await {
+                Ok(()) => {
+                    set_error_message.set(None);
+                    on_change();
+                }
+                Err(e) => set_error_message.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    view! {
+        <div class="card bg-base-100 border border-base-300 w-[1000px]">
+            <div class="card-body">
+                <div class="flex items-center justify-between">
+                    <h2 class="card-title">{format!("Sessions ({})", sessions.len())}</h2>
+                    <div class="join">
+                        <input type="number" min="1" class="input input-sm join-item w-28" bind:value=timeout />
+                        <button class="btn btn-sm join-item" on:click=save_timeout>"Set idle timeout (s)"</button>
+                    </div>
+                </div>
+                <Show when=move || error_message.get().is_some() fallback=move || view! {}>
+                    <div role="alert" class="alert alert-error alert-soft">{move || error_message.get()}</div>
+                </Show>
+                <table class="table table-sm">
+                    <thead>
+                        <tr><th>"Id"</th><th>"Origin"</th><th>"Contract"</th><th>"Network"</th><th>"Uptime"</th><th>"Events"</th><th>"Last event"</th><th>"Last error"</th><th></th></tr>
+                    </thead>
+                    <tbody>
+                        {sessions.into_iter().map(|session| {
+                            let id = session.id;
+                            view! {
+                                <tr>
+                                    <td>{session.id}</td>
+                                    <td>{session.origin.to_string()}</td>
+                                    <td class="font-mono break-all">{session.address.clone()}</td>
+                                    <td>{session.network.to_string()}</td>
+                                    <td>{format_duration(at.saturating_sub(session.started_at))}</td>
+                                    <StatsCells stats=session.stats />
+                                    <td>
+                                        <button class="btn btn-xs btn-ghost" on:click=move |_| {
+                                            spawn_local(async move {
+                                                if terminate_session(id).await.is_ok() {
+                                                    on_change();
+                                                }
+                                            });
+                                        }>"Terminate"</button>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view()}
+                    </tbody>
+                </table>
+
+                <h2 class="card-title mt-4">{format!("Indexer subscriptions ({})", subscriptions.len())}</h2>
+                <table class="table table-sm">
+                    <thead>
+                        <tr><th>"Id"</th><th>"Contract"</th><th>"Network"</th><th>"Clients"</th><th>"Uptime"</th><th>"Events"</th><th>"Last event"</th><th>"Last error"</th><th></th></tr>
+                    </thead>
+                    <tbody>
+                        {subscriptions.into_iter().map(|subscription| {
+                            let id = subscription.id;
+                            view! {
+                                <tr>
+                                    <td>{subscription.id}</td>
+                                    <td class="font-mono break-all">
+                                        <a class="link link-primary" href=format!("/contract/{}", subscription.address)>{subscription.address.clone()}</a>
+                                    </td>
+                                    <td>{subscription.network.to_string()}</td>
+                                    <td>{subscription.clients}</td>
+                                    <td>{format_duration(at.saturating_sub(subscription.started_at))}</td>
+                                    <StatsCells stats=subscription.stats />
+                                    <td>
+                                        <button class="btn btn-xs btn-ghost" on:click=move |_| {
+                                            spawn_local(async move {
+                                                if terminate_subscription(id).await.is_ok() {
+                                                    on_change();
+                                                }
+                                            });
+                                        }>"Terminate"</button>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view()}
+                    </tbody>
+                </table>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn StatsCells(stats: ConnectionStats) -> impl IntoView {
+    view! {
+        <td>{stats.events_delivered}</td>
+        <td>{stats.last_event_at.map(format_timestamp)}</td>
+        <td class="text-error break-all">{stats.last_error}</td>
+    }
+}
+
+#[component]
+fn NewApiKeyForm(on_added: impl Fn() + Copy + Send + Sync + 'static) -> impl IntoView {
+    let name = RwSignal::new(String::new());
+    let scope = RwSignal::new(Scope::Read.to_string());
+    let (created, set_created) = signal(None::<CreatedApiKey>);
+    let (error_message, set_error_message) = signal(None::<String>);
+
+    let submit = move |_| {
+        let scope = match scope.get_untracked().parse::<Scope>() {
+            Ok(scope) => scope,
+            Err(e) => {
+                set_error_message.set(Some(e));
+                return;
+            }
+        };
+        let request = NewApiKey {
+            name: name.get_untracked(),
+            scope,
+        };
+        spawn_local(async move {
+            match create_api_key(request).await {
+                Ok(key) => {
+                    set_error_message.set(None);
+                    set_created.set(Some(key));
+                    on_added();
+                }
+                Err(e) => set_error_message.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    view! {
+        <div class="card bg-base-100 border border-base-300 w-[1000px]">
+            <div class="card-body">
+                <h2 class="card-title">"New API key"</h2>
+                <div class="join">
+                    <input type="text" class="input join-item w-full" placeholder="Name, e.g. ci-exporter" bind:value=name />
+                    <select class="select join-item" bind:value=scope>
+                        {Scope::ALL.into_iter().map(|scope| view! {
+                            <option value=scope.to_string()>{scope.to_string()}</option>
+                        }).collect_view()}
+                    </select>
+                    <button class="btn btn-neutral join-item" on:click=submit>"Create key"</button>
+                </div>
+                <Show when=move || error_message.get().is_some() fallback=move || view! {}>
+                    <div role="alert" class="alert alert-error alert-soft">{move || error_message.get()}</div>
+                </Show>
+                {move || created.get().map(|key| view! {
+                    <div role="alert" class="alert alert-success alert-soft flex-col items-start">
+                        <span>{format!("Key '{}' created. Copy it now, it is not shown again:", key.key.name)}</span>
+                        <code class="font-mono break-all select-all">{key.secret.clone()}</code>
+                    </div>
+                })}
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn Access(
+    overview: AuthOverview,
+    on_change: impl Fn() + Copy + Send + Sync + 'static,
+) -> impl IntoView {
+    let AuthOverview {
+        keys,
+        accounts,
+        signed_in_as,
+    } = overview;
+    let username = RwSignal::new(String::new());
+    let password = RwSignal::new(String::new());
+    let (error_message, set_error_message) = signal(None::<String>);
+    let navigate = use_navigate();
+
+    let add = move |_| {
+        let (username_value, password_value) = (username.get_untracked(), password.get_untracked());
+        spawn_local(async move {
+            match add_account(username_value, password_value).await {
+                Ok(()) => {
+                    set_error_message.set(None);
+                    username.set(String::new());
+                    password.set(String::new());
+                    on_change();
+                }
+                Err(e) => set_error_message.set(Some(e.to_string())),
+            }
+        });
+    };
+    let sign_out = move |_| {
+        let navigate = navigate.clone();
+        spawn_local(async move {
+            if logout().await.is_ok() {
+                navigate("/login", Default::default());
+            }
+        });
+    };
+
+    view! {
+        <div class="card bg-base-100 border border-base-300 w-[1000px]">
+            <div class="card-body">
+                <div class="flex items-center justify-between">
+                    <h2 class="card-title">{format!("API keys ({})", keys.len())}</h2>
+                    {signed_in_as.map(|username| view! {
+                        <div class="flex items-center gap-2 text-sm">
+                            {format!("Signed in as {}", username)}
+                            <button class="btn btn-sm btn-ghost" on:click=sign_out>"Sign out"</button>
+                        </div>
+                    })}
+                </div>
+                <table class="table table-sm">
+                    <thead><tr><th>"Id"</th><th>"Name"</th><th>"Scope"</th><th>"Key"</th><th>"Created"</th><th>"Last used"</th><th></th></tr></thead>
+                    <tbody>
+                        {keys.into_iter().map(|key| {
+                            let id = key.id;
+                            view! {
+                                <tr>
+                                    <td>{key.id}</td>
+                                    <td>{key.name.clone()}</td>
+                                    <td><span class="badge badge-soft">{key.scope.to_string()}</span></td>
+                                    <td class="font-mono">{format!("{}…", key.prefix)}</td>
+                                    <td>{format_timestamp(key.created_at)}</td>
+                                    <td>{key.last_used_at.map(format_timestamp)}</td>
+                                    <td>
+                                        <button class="btn btn-xs btn-ghost" on:click=move |_| {
+                                            spawn_local(async move {
+                                                if revoke_api_key(id).await.is_ok() {
+                                                    on_change();
+                                                }
+                                            });
+                                        }>"Revoke"</button>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view()}
+                    </tbody>
+                </table>
+
+                <h2 class="card-title mt-4">{format!("Accounts ({})", accounts.len())}</h2>
+                <table class="table table-sm">
+                    <thead><tr><th>"Username"</th><th>"Created"</th><th></th></tr></thead>
+                    <tbody>
+                        {accounts.into_iter().map(|account| {
+                            let username = account.username.clone();
+                            view! {
+                                <tr>
+                                    <td>{account.username.clone()}</td>
+                                    <td>{format_timestamp(account.created_at)}</td>
+                                    <td>
+                                        <button class="btn btn-xs btn-ghost" on:click=move |_| {
+                                            let username = username.clone();
+                                            spawn_local(async move {
+                                                match remove_account(username).await {
+                                                    Ok(()) => on_change(),
+                                                    Err(e) => set_error_message.set(Some(e.to_string())),
+                                                }
+                                            });
+                                        }>"Remove"</button>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view()}
+                    </tbody>
+                </table>
+                <div class="join">
+                    <input type="text" class="input input-sm join-item" placeholder="Username" bind:value=username />
+                    <input type="password" class="input input-sm join-item" placeholder="Password" bind:value=password />
+                    <button class="btn btn-sm join-item" on:click=add>"Add account"</button>
+                </div>
+                <Show when=move || error_message.get().is_some() fallback=move || view! {}>
+                    <div role="alert" class="alert alert-error alert-soft">{move || error_message.get()}</div>
+                </Show>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn NewWebhookForm(on_added: impl Fn() + Copy + Send + Sync + 'static) -> impl IntoView {
+    let address = RwSignal::new(String::new());
+    let url = RwSignal::new(String::new());
+    let secret = RwSignal::new(String::new());
+    let event_types = RwSignal::new(String::new());
+    let predicate_path = RwSignal::new(String::new());
+    let predicate_value = RwSignal::new(String::new());
+    let (error_message, set_error_message) = signal(None::<String>);
+
+    let submit = move |_| {
+        let event_types = match event_types
+            .get_untracked()
+            .split(',')
+            .filter(|kind| !kind.trim().is_empty())
+            .map(str::parse::<ActionKind>)
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(event_types) => event_types,
+            Err(e) => {
+                set_error_message.set(Some(e));
+                return;
+            }
+        };
+        let predicate = match predicate_path.get_untracked().trim() {
+            "" => None,
+            path => match JsonPath::parse(path) {
+                Ok(path) => Some(Predicate::new(
+                    path,
+                    match predicate_value.get_untracked().trim() {
+                        "" => Condition::Exists,
+                        value => Condition::Equals(parse_value(value)),
+                    },
+                )),
+                Err(e) => {
+                    set_error_message.set(Some(e));
+                    return;
+                }
+            },
+        };
+        let secret = Some(secret.get_untracked()).filter(|secret| !secret.is_empty());
+        let request = NewWebhook {
+            address: address.get_untracked(),
+            url: url.get_untracked(),
+            secret,
+            event_types,
+            predicate,
+        };
+        spawn_local(async move {
+            match add_webhook(request).await {
+                Ok(()) => {
+                    set_error_message.set(None);
+                    on_added();
+                }
+                Err(e) => set_error_message.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    view! {
+        <div class="card bg-base-100 border border-base-300 w-[1000px]">
+            <div class="card-body">
+                <h2 class="card-title">"New webhook"</h2>
+                <div class="grid grid-cols-2 gap-2">
+                    <input type="text" class="input w-full" placeholder="Contract address" bind:value=address />
+                    <input type="text" class="input w-full" placeholder="https://example.com/hook" bind:value=url />
+                    <input type="password" class="input w-full" placeholder="HMAC secret (optional)" bind:value=secret />
+                    <input type="text" class="input w-full" placeholder="Action kinds, e.g. call,update (optional)" bind:value=event_types />
+                    <input type="text" class="input w-full" placeholder="State path, e.g. $[0] (optional)" bind:value=predicate_path />
+                    <input type="text" class="input w-full" placeholder="Equals value (optional)" bind:value=predicate_value />
+                </div>
+                <Show when=move || error_message.get().is_some() fallback=move || view! {}>
+                    <div role="alert" class="alert alert-error alert-soft">{move || error_message.get()}</div>
+                </Show>
+                <div class="card-actions justify-end">
+                    <button class="btn btn-neutral" on:click=submit>"Add webhook"</button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn Overview(
+    overview: WebhookOverview,
+    on_change: impl Fn() + Copy + Send + Sync + 'static,
+) -> impl IntoView {
+    let WebhookOverview {
+        watchlist,
+        webhooks,
+        deliveries,
+        dead_letters,
+    } = overview;
+
+    view! {
+        <div class="card bg-base-100 border border-base-300 w-[1000px]">
+            <div class="card-body">
+                <h2 class="card-title">{format!("Watchlist ({})", watchlist.len())}</h2>
+                <table class="table table-sm">
+                    <thead><tr><th>"Contract"</th><th>"Since"</th><th></th></tr></thead>
+                    <tbody>
+                        {watchlist.into_iter().map(|contract| {
+                            let address = contract.address.clone();
+                            view! {
+                                <tr>
+                                    <td class="font-mono break-all">
+                                        <a class="link link-primary" href=format!("/contract/{}", contract.address)>{contract.address.clone()}</a>
+                                    </td>
+                                    <td>{format_timestamp(contract.added_at)}</td>
+                                    <td>
+                                        <button class="btn btn-xs btn-ghost" on:click=move |_| {
+                                            let address = address.clone();
+                                            spawn_local(async move {
+                                                if unwatch_contract(address).await.is_ok() {
+                                                    on_change();
+                                                }
+                                            });
+                                        }>"Unwatch"</button>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view()}
+                    </tbody>
+                </table>
+
+                <h2 class="card-title mt-4">{format!("Webhooks ({})", webhooks.len())}</h2>
+                <table class="table table-sm">
+                    <thead><tr><th>"Id"</th><th>"Contract"</th><th>"URL"</th><th>"Filter"</th><th></th></tr></thead>
+                    <tbody>
+                        {webhooks.into_iter().map(|webhook| {
+                            let id = webhook.id;
+                            let signed = webhook.signed;
+                            let kinds = if webhook.event_types.is_empty() {
+                                "all actions".to_string()
+                            } else {
+                                webhook.event_types.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+                            };
+                            let filter = match &webhook.predicate {
+                                Some(predicate) => format!("{} where {}", kinds, predicate),
+                                None => kinds,
+                            };
+                            view! {
+                                <tr>
+                                    <td>{webhook.id}</td>
+                                    <td class="font-mono break-all">{webhook.address.clone()}</td>
+                                    <td class="break-all">
+                                        {webhook.url.clone()}
+                                        <Show when=move || signed fallback=move || view! {}>
+                                            <span class="badge badge-soft badge-info ml-2">"signed"</span>
+                                        </Show>
+                                    </td>
+                                    <td>{filter}</td>
+                                    <td>
+                                        <button class="btn btn-xs btn-ghost" on:click=move |_| {
+                                            spawn_local(async move {
+                                                if remove_webhook(id).await.is_ok() {
+                                                    on_change();
+                                                }
+                                            });
+                                        }>"Remove"</button>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view()}
+                    </tbody>
+                </table>
+
+                <h2 class="card-title mt-4">"Recent deliveries"</h2>
+                <table class="table table-sm">
+                    <thead><tr><th>"Time"</th><th>"For"</th><th>"Transaction"</th><th>"Attempt"</th><th>"Result"</th></tr></thead>
+                    <tbody>
+                        {deliveries.into_iter().map(|attempt| view! { <DeliveryRow attempt=attempt /> }).collect_view()}
+                    </tbody>
+                </table>
+
+                <h2 class="card-title mt-4">{format!("Dead letters ({})", dead_letters.len())}</h2>
+                <div class="space-y-2">
+                    {dead_letters.into_iter().map(|letter| view! { <DeadLetterRow letter=letter /> }).collect_view()}
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn DeliveryRow(attempt: DeliveryAttempt) -> impl IntoView {
+    let succeeded = attempt.succeeded();
+    let result = match (&attempt.status, &attempt.error) {
+        (Some(status), None) => status.to_string(),
+        (_, Some(error)) => error.clone(),
+        (None, None) => String::new(),
+    };
+    view! {
+        <tr>
+            <td>{format_timestamp(attempt.at)}</td>
+            <td>{sent_for(attempt.webhook_id)}</td>
+            <td class="font-mono break-all">
+                {attempt.transaction_hash.clone().map(|hash| view! {
+                    <a class="link link-primary" href=format!("/tx/{}", hash)>{hash.clone()}</a>
+                })}
+            </td>
+            <td>{attempt.attempt}</td>
+            <td class:text-success=succeeded class:text-error=!succeeded>{result}</td>
+        </tr>
+    }
+}
+
+/// What a delivery was for, a webhook or an alert rule's webhook sink.
+fn sent_for(webhook_id: Option<u64>) -> String {
+    match webhook_id {
+        Some(id) => format!("webhook {}", id),
+        None => "alert".to_string(),
+    }
+}
+
+#[component]
+fn DeadLetterRow(letter: DeadLetter) -> impl IntoView {
+    let payload = serde_json::to_string_pretty(&letter.payload).unwrap_or_default();
+    view! {
+        <details class="collapse collapse-arrow border border-base-300">
+            <summary class="collapse-title text-sm">
+                {format!(
+                    "{} · {} · {} attempts · {}",
+                    format_timestamp(letter.at),
+                    sent_for(letter.webhook_id),
+                    letter.attempts,
+                    letter.last_error
+                )}
+            </summary>
+            <div class="collapse-content">
+                <pre class="text-xs overflow-x-auto">{payload}</pre>
+            </div>
+        </details>
+    }
+}
+
+#[component]
+fn NewAlertRuleForm(on_added: impl Fn() + Copy + Send + Sync + 'static) -> impl IntoView {
+    let address = RwSignal::new(String::new());
+    let kind = RwSignal::new("decreased".to_string());
+    let path = RwSignal::new(String::new());
+    let amount = RwSignal::new(String::new());
+    let webhook_url = RwSignal::new(String::new());
+    let email = RwSignal::new(String::new());
+    let (error_message, set_error_message) = signal(None::<String>);
+
+    let submit = move |_| {
+        let parse_path = || JsonPath::parse(&path.get_untracked());
+        let parse_amount = || {
+            amount
+                .get_untracked()
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| "Enter a whole number".to_string())
+        };
+        let condition = match kind.get_untracked().as_str() {
+            "decreased" => parse_path().map(|path| AlertCondition::Decreased { path }),
+            "grew_by" => parse_path().and_then(|path| {
+                Ok(AlertCondition::GrewBy {
+                    path,
+                    entries: parse_amount()? as usize,
+                })
+            }),
+            "no_calls_for" => parse_amount().map(|seconds| AlertCondition::NoCallsFor { seconds }),
+            _ => Ok(AlertCondition::Updated),
+        };
+        let condition = match condition {
+            Ok(condition) => condition,
+            Err(e) => {
+                set_error_message.set(Some(e));
+                return;
+            }
+        };
+        let mut sinks = vec![AlertSink::Log];
+        if !webhook_url.get_untracked().trim().is_empty() {
+            sinks.push(AlertSink::Webhook {
+                url: webhook_url.get_untracked().trim().to_string(),
+            });
+        }
+        if !email.get_untracked().trim().is_empty() {
+            sinks.push(AlertSink::Email {
+                to: email.get_untracked().trim().to_string(),
+            });
+        }
+        let request = NewAlertRule {
+            address: address.get_untracked(),
+            condition,
+            sinks,
+        };
+        spawn_local(async move {
+            match add_alert_rule(request).await {
+                Ok(()) => {
+                    set_error_message.set(None);
+                    on_added();
+                }
+                Err(e) => set_error_message.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    view! {
+        <div class="card bg-base-100 border border-base-300 w-[1000px]">
+            <div class="card-body">
+                <h2 class="card-title">"New alert rule"</h2>
+                <div class="grid grid-cols-2 gap-2">
+                    <input type="text" class="input w-full" placeholder="Contract address" bind:value=address />
+                    <select class="select w-full" bind:value=kind>
+                        <option value="decreased">"Field decreased"</option>
+                        <option value="grew_by">"Collection grew by more than N entries"</option>
+                        <option value="no_calls_for">"No calls for N seconds"</option>
+                        <option value="updated">"Contract updated (verifier keys changed)"</option>
+                    </select>
+                    <input type="text" class="input w-full" placeholder="State path, e.g. $[1]" bind:value=path />
+                    <input type="text" class="input w-full" placeholder="N" bind:value=amount />
+                    <input type="text" class="input w-full" placeholder="Webhook URL (optional)" bind:value=webhook_url />
+                    <input type="text" class="input w-full" placeholder="Email (optional)" bind:value=email />
+                </div>
+                <Show when=move || error_message.get().is_some() fallback=move || view! {}>
+                    <div role="alert" class="alert alert-error alert-soft">{move || error_message.get()}</div>
+                </Show>
+                <div class="card-actions justify-end">
+                    <button class="btn btn-neutral" on:click=submit>"Add rule"</button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn AlertRules(
+    overview: AlertOverview,
+    on_change: impl Fn() + Copy + Send + Sync + 'static,
+) -> impl IntoView {
+    let AlertOverview { rules, alerts } = overview;
+
+    view! {
+        <div class="card bg-base-100 border border-base-300 w-[1000px]">
+            <div class="card-body">
+                <h2 class="card-title">{format!("Alert rules ({})", rules.len())}</h2>
+                <table class="table table-sm">
+                    <thead><tr><th>"Id"</th><th>"Contract"</th><th>"Condition"</th><th>"Sinks"</th><th></th></tr></thead>
+                    <tbody>
+                        {rules.into_iter().map(|rule| {
+                            let id = rule.id;
+                            let sinks = rule.sinks.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+                            view! {
+                                <tr>
+                                    <td>{rule.id}</td>
+                                    <td class="font-mono break-all">{rule.address.clone()}</td>
+                                    <td>{rule.condition.to_string()}</td>
+                                    <td class="break-all">{sinks}</td>
+                                    <td>
+                                        <button class="btn btn-xs btn-ghost" on:click=move |_| {
+                                            spawn_local(async move {
+                                                if remove_alert_rule(id).await.is_ok() {
+                                                    on_change();
+                                                }
+                                            });
+                                        }>"Remove"</button>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view()}
+                    </tbody>
+                </table>
+
+                <h2 class="card-title mt-4">"Recent alerts"</h2>
+                <table class="table table-sm">
+                    <thead><tr><th>"Time"</th><th>"Rule"</th><th>"Contract"</th><th>"Alert"</th></tr></thead>
+                    <tbody>
+                        {alerts.into_iter().map(|alert| view! { <AlertRow alert=alert /> }).collect_view()}
+                    </tbody>
+                </table>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn AlertRow(alert: Alert) -> impl IntoView {
+    view! {
+        <tr>
+            <td>{format_timestamp(alert.fired_at)}</td>
+            <td>{alert.rule_id}</td>
+            <td class="font-mono break-all">
+                <a class="link link-primary" href=format!("/contract/{}", alert.address)>{alert.address.clone()}</a>
+            </td>
+            <td>
+                {alert.message.clone()}
+                {alert.transaction_hash.clone().map(|hash| view! {
+                    <a class="link link-primary font-mono ml-2" href=format!("/tx/{}", hash)>{hash.clone()}</a>
+                })}
+            </td>
+        </tr>
+    }
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn get_webhook_overview() -> Result<WebhookOverview, ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    Ok(WebhookOverview {
+        watchlist: app_state.watchlist.list(),
+        webhooks: app_state.webhooks.list(),
+        deliveries: app_state.webhooks.deliveries(),
+        dead_letters: app_state.webhooks.dead_letters(),
+    })
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn add_webhook(request: NewWebhook) -> Result<(), ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    app_state
+        .add_webhook(request)
+        .await
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+    Ok(())
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn remove_webhook(id: u64) -> Result<(), ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    app_state.webhooks.remove(id);
+    Ok(())
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn unwatch_contract(address: String) -> Result<(), ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    let address = app_state
+        .contract_indexer
+        .parse_address(&address)
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+    app_state.watchlist.remove(&address);
+    Ok(())
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn get_alert_overview() -> Result<AlertOverview, ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    Ok(AlertOverview {
+        rules: app_state.alerts.rules(),
+        alerts: app_state.alerts.alerts(),
+    })
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn add_alert_rule(request: NewAlertRule) -> Result<(), ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    app_state
+        .add_alert_rule(request)
+        .await
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+    Ok(())
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn remove_alert_rule(id: u64) -> Result<(), ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    app_state.alerts.remove_rule(id);
+    Ok(())
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn get_connection_overview() -> Result<ConnectionOverview, ServerFnError> {
+    use crate::core::{app_state::app_state, event_store::now_millis};
+
+    let app_state = app_state()?;
+    let indexer = &app_state.contract_indexer;
+    Ok(ConnectionOverview {
+        sessions: indexer.connections().sessions(),
+        subscriptions: indexer.connections().subscriptions(),
+        idle_timeout: indexer.timeout(),
+        at: now_millis(),
+    })
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn terminate_session(id: u64) -> Result<(), ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    if !app_state.contract_indexer.connections().terminate_session(id) {
+        return Err(ServerFnError::ServerError(format!("No session {}", id)));
+    }
+    Ok(())
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn terminate_subscription(id: u64) -> Result<(), ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    if !app_state
+        .contract_indexer
+        .connections()
+        .terminate_subscription(id)
+    {
+        return Err(ServerFnError::ServerError(format!("No subscription {}", id)));
+    }
+    Ok(())
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn set_idle_timeout(seconds: u64) -> Result<(), ServerFnError> {
+    use crate::core::app_state::app_state;
+    use tracing::info;
+
+    let app_state = app_state()?;
+    if seconds == 0 {
+        return Err(ServerFnError::ServerError(
+            "The idle timeout must be at least a second".to_string(),
+        ));
+    }
+    app_state.contract_indexer.set_timeout(seconds);
+    info!("Idle timeout set to {} seconds", seconds);
+    Ok(())
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn get_auth_overview() -> Result<AuthOverview, ServerFnError> {
+    use crate::core::app_state::app_state;
+    use axum::http::HeaderMap;
+
+    let app_state = app_state()?;
+    let headers: HeaderMap = leptos_axum::extract().await?;
+    Ok(AuthOverview {
+        keys: app_state.auth.keys(),
+        accounts: app_state.auth.accounts(),
+        signed_in_as: app_state.auth.signed_in(&headers),
+    })
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn create_api_key(request: NewApiKey) -> Result<CreatedApiKey, ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    app_state
+        .auth
+        .create_key(request)
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn revoke_api_key(id: u64) -> Result<(), ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    if !app_state.auth.revoke_key(id) {
+        return Err(ServerFnError::ServerError(format!("No API key {}", id)));
+    }
+    Ok(())
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn add_account(username: String, password: String) -> Result<(), ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    app_state
+        .auth
+        .add_account(username, password)
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+    Ok(())
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn remove_account(username: String) -> Result<(), ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    app_state
+        .auth
+        .remove_account(&username)
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))
+}
+
+#[server(prefix = "/admin/api")]
+pub async fn logout() -> Result<(), ServerFnError> {
+    use crate::core::{app_state::app_state, auth::cleared_session_cookie};
+    use axum::http::{header, HeaderMap, HeaderValue};
+
+    let app_state = app_state()?;
+    let headers: HeaderMap = leptos_axum::extract().await?;
+    app_state.auth.logout(&headers);
+    let response = expect_context::<leptos_axum::ResponseOptions>();
+    response.insert_header(
+        header::SET_COOKIE,
+        HeaderValue::from_str(&cleared_session_cookie())
+            .map_err(|e| ServerFnError::ServerError(e.to_string()))?,
+    );
+    Ok(())
+}
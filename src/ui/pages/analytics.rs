@@ -0,0 +1,261 @@
+use leptos::prelude::*;
+use leptos_router::hooks::use_params_map;
+
+use crate::domain::{
+    analytics::{BlockActivity, ContractAnalytics, Interval, Period},
+    indexer::{format_timestamp, DateTime},
+};
+
+/// Blocks listed with the most transactions on the contract.
+const TOP_BLOCKS: usize = 10;
+const CHART_WIDTH: f64 = 1000.0;
+const CHART_HEIGHT: f64 = 160.0;
+
+#[component]
+pub fn AnalyticsPage() -> impl IntoView {
+    let params = use_params_map();
+    let interval = RwSignal::new(Interval::Hour);
+    let analytics = Resource::new(
+        move || {
+            (
+                params.read().get("address").unwrap_or_default(),
+                interval.get(),
+            )
+        },
+        |(address, interval)| get_contract_analytics(address, interval),
+    );
+
+    view! {
+        <div class="flex flex-col items-center gap-4 mt-8">
+            <div class="w-[1000px] flex items-center justify-between">
+                <a class="link" href=move || format!("/contract/{}", params.read().get("address").unwrap_or_default())>
+                    "Back to contract"
+                </a>
+                <div class="join">
+                    {Interval::ALL.into_iter().map(|option| view! {
+                        <button
+                            class="join-item btn btn-sm"
+                            class:btn-active=move || interval.get() == option
+                            on:click=move |_| interval.set(option)
+                        >
+                            {match option {
+                                Interval::Hour => "Hourly",
+                                Interval::Day => "Daily",
+                            }}
+                        </button>
+                    }).collect_view()}
+                </div>
+            </div>
+            <Suspense fallback=move || view! { <div class="skeleton h-32 w-96"></div> }>
+                {move || Suspend::new(async move {
+                    match analytics.await {
+                        Ok(analytics) if analytics.events == 0 => view! {
+                            <div role="alert" class="alert alert-info alert-soft w-[1000px]">
+                                "The server has not recorded any events for this contract yet. Open it on the home page or add it to the watchlist to start collecting them."
+                            </div>
+                        }.into_any(),
+                        Ok(analytics) => view! { <AnalyticsDetails analytics=analytics /> }.into_any(),
+                        Err(e) => view! {
+                            <div role="alert" class="alert alert-error alert-soft">{e.to_string()}</div>
+                        }.into_any(),
+                    }
+                })}
+            </Suspense>
+        </div>
+    }
+}
+
+#[component]
+fn AnalyticsDetails(analytics: ContractAnalytics) -> impl IntoView {
+    let period_label = move |start: u64| match analytics.interval {
+        Interval::Hour => {
+            let start = DateTime::from_timestamp(start);
+            format!("{} {}", start.date(), start.clock())
+        }
+        Interval::Day => DateTime::from_timestamp(start).date(),
+    };
+    let max_calls = analytics
+        .entry_points
+        .first()
+        .map(|entry_point| entry_point.calls)
+        .unwrap_or(1);
+    let mut top_blocks = analytics.blocks.clone();
+    top_blocks.sort_by_key(|block| std::cmp::Reverse(block.transactions));
+    top_blocks.truncate(TOP_BLOCKS);
+    let transactions: u64 = analytics.blocks.iter().map(|block| block.transactions).sum();
+
+    view! {
+        <div class="card bg-base-100 border border-base-300 w-[1000px]">
+            <div class="card-body">
+                <h2 class="card-title font-mono break-all">{analytics.address.clone()}</h2>
+                <div class="stats stats-horizontal border border-base-300">
+                    <div class="stat">
+                        <div class="stat-title">"Events"</div>
+                        <div class="stat-value text-2xl">{analytics.events}</div>
+                    </div>
+                    <div class="stat">
+                        <div class="stat-title">"Calls"</div>
+                        <div class="stat-value text-2xl">{analytics.actions.calls}</div>
+                    </div>
+                    <div class="stat">
+                        <div class="stat-title">"Deploys"</div>
+                        <div class="stat-value text-2xl">{analytics.actions.deploys}</div>
+                    </div>
+                    <div class="stat">
+                        <div class="stat-title">"Updates"</div>
+                        <div class="stat-value text-2xl">{analytics.actions.updates}</div>
+                    </div>
+                    <div class="stat">
+                        <div class="stat-title">"Blocks"</div>
+                        <div class="stat-value text-2xl">{analytics.blocks.len()}</div>
+                        <div class="stat-desc">{format!("{} transactions", transactions)}</div>
+                    </div>
+                    <div class="stat">
+                        <div class="stat-title">"Fees paid"</div>
+                        <div class="stat-value text-2xl">{analytics.fees.paid.clone()}</div>
+                        <div class="stat-desc">
+                            {format!("{} estimated, {} transactions", analytics.fees.estimated, analytics.fees.transactions)}
+                        </div>
+                    </div>
+                </div>
+                <p class="text-sm text-base-content/60">
+                    {analytics.first_event_at.map(format_timestamp)}" – "{analytics.last_event_at.map(format_timestamp)}
+                </p>
+
+                <h3 class="font-semibold mt-4">{format!("Actions per {}", analytics.interval)}</h3>
+                <ActivityChart periods=analytics.periods.clone() label=period_label />
+
+                <div class="grid grid-cols-2 gap-6 mt-4">
+                    <div>
+                        <h3 class="font-semibold mb-2">"Entry points"</h3>
+                        {if analytics.entry_points.is_empty() {
+                            view! { <p class="text-sm text-base-content/60">"No calls with a known entry point."</p> }.into_any()
+                        } else {
+                            view! {
+                                <table class="table table-sm">
+                                    <tbody>
+                                        {analytics.entry_points.into_iter().map(|entry_point| view! {
+                                            <tr>
+                                                <td class="font-mono">{entry_point.entry_point}</td>
+                                                <td class="w-1/2">
+                                                    <progress class="progress progress-primary" value=entry_point.calls max=max_calls></progress>
+                                                </td>
+                                                <td class="text-right">{entry_point.calls}</td>
+                                            </tr>
+                                        }).collect_view()}
+                                    </tbody>
+                                </table>
+                            }.into_any()
+                        }}
+                    </div>
+                    <div>
+                        <h3 class="font-semibold mb-2">"Busiest periods"</h3>
+                        <table class="table table-sm">
+                            <thead>
+                                <tr><th>"Period"</th><th>"Calls"</th><th>"Deploys"</th><th>"Updates"</th></tr>
+                            </thead>
+                            <tbody>
+                                {analytics.busiest.into_iter().map(|period| view! {
+                                    <tr>
+                                        <td>{period_label(period.start)}</td>
+                                        <td>{period.actions.calls}</td>
+                                        <td>{period.actions.deploys}</td>
+                                        <td>{period.actions.updates}</td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    </div>
+                </div>
+
+                <h3 class="font-semibold mt-4">"Transactions per block"</h3>
+                <BusiestBlocks blocks=top_blocks />
+            </div>
+        </div>
+    }
+}
+
+/// Bars of the actions in every period with activity, calls in the primary colour on top of the rest.
+#[component]
+fn ActivityChart(
+    periods: Vec<Period>,
+    label: impl Fn(u64) -> String + Copy + Send + Sync + 'static,
+) -> impl IntoView {
+    let highest = periods
+        .iter()
+        .map(|period| period.actions.total())
+        .max()
+        .unwrap_or(1)
+        .max(1) as f64;
+    let width = CHART_WIDTH / periods.len().max(1) as f64;
+    let (hovered, set_hovered) = signal(None::<Period>);
+    let first = periods.first().map(|period| label(period.start));
+    let last = periods.last().map(|period| label(period.start));
+    view! {
+        <svg viewBox=format!("0 0 {} {}", CHART_WIDTH, CHART_HEIGHT) class="w-full" on:mouseleave=move |_| set_hovered.set(None)>
+            {periods.into_iter().enumerate().map(|(position, period)| {
+                let x = position as f64 * width;
+                let total = period.actions.total() as f64 / highest * CHART_HEIGHT;
+                let calls = period.actions.calls as f64 / highest * CHART_HEIGHT;
+                view! {
+                    // full height so thin bars are easy to hover
+                    <rect x=x y="0" width=width height=CHART_HEIGHT fill="transparent" on:mouseenter=move |_| set_hovered.set(Some(period)) />
+                    <rect x=x y=CHART_HEIGHT - total width=(width - 1.0).max(1.0) height=total fill="currentColor" fill-opacity="0.25" pointer-events="none" />
+                    <rect x=x y=CHART_HEIGHT - calls width=(width - 1.0).max(1.0) height=calls class="fill-primary" pointer-events="none" />
+                }
+            }).collect_view()}
+        </svg>
+        <div class="flex justify-between text-xs text-base-content/60">
+            <span>{first}</span>
+            <span>
+                {move || hovered.get().map(|period| format!(
+                    "{}: {} calls, {} deploys, {} updates",
+                    label(period.start),
+                    period.actions.calls,
+                    period.actions.deploys,
+                    period.actions.updates,
+                ))}
+            </span>
+            <span>{last}</span>
+        </div>
+    }
+}
+
+#[component]
+fn BusiestBlocks(blocks: Vec<BlockActivity>) -> impl IntoView {
+    view! {
+        <table class="table table-sm">
+            <thead>
+                <tr><th>"Block"</th><th>"Time"</th><th>"Transactions"</th></tr>
+            </thead>
+            <tbody>
+                {blocks.into_iter().map(|block| view! {
+                    <tr>
+                        <td><a class="link link-primary" href=format!("/block/{}", block.height)>{block.height}</a></td>
+                        <td>{format_timestamp(block.timestamp)}</td>
+                        <td>{block.transactions}</td>
+                    </tr>
+                }).collect_view()}
+            </tbody>
+        </table>
+    }
+}
+
+#[server]
+pub async fn get_contract_analytics(
+    address: String,
+    interval: Interval,
+) -> Result<ContractAnalytics, ServerFnError> {
+    use crate::core::app_state::app_state;
+
+    let app_state = app_state()?;
+    let address = app_state
+        .contract_indexer
+        .parse_address(&address)
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+    app_state
+        .analytics
+        .contract(address, interval)
+        .await
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))
+}
@@ -0,0 +1,94 @@
+use leptos::prelude::*;
+use leptos_router::hooks::use_params_map;
+
+use crate::{
+    domain::indexer::{format_timestamp, Block},
+    ui::components::transaction_summary::TransactionSummary,
+};
+
+#[component]
+pub fn BlockPage() -> impl IntoView {
+    let params = use_params_map();
+    let block = Resource::new(
+        move || params.read().get("height_or_hash").unwrap_or_default(),
+        get_block,
+    );
+
+    view! {
+        <div class="flex flex-col items-center gap-4 mt-8">
+            <Suspense fallback=move || view! { <div class="skeleton h-32 w-96"></div> }>
+                {move || Suspend::new(async move {
+                    match block.await {
+                        Ok(Some(block)) => view! { <BlockDetails block=block /> }.into_any(),
+                        Ok(None) => view! {
+                            <div role="alert" class="alert alert-warning alert-soft">"Block not found."</div>
+                        }.into_any(),
+                        Err(e) => view! {
+                            <div role="alert" class="alert alert-error alert-soft">{e.to_string()}</div>
+                        }.into_any(),
+                    }
+                })}
+            </Suspense>
+        </div>
+    }
+}
+
+#[component]
+fn BlockDetails(block: Block) -> impl IntoView {
+    let parent = block.parent.clone();
+    view! {
+        <div class="card bg-base-100 border border-base-300 w-[1000px]">
+            <div class="card-body">
+                <h2 class="card-title">"Block "{block.height}</h2>
+                <div class="grid grid-cols-[max-content_1fr] gap-x-4 gap-y-1 text-sm">
+                    <span class="text-base-content/60">"Hash"</span>
+                    <span class="font-mono break-all">{block.hash.clone()}</span>
+                    <span class="text-base-content/60">"Time"</span>
+                    <span>{format_timestamp(block.timestamp)}</span>
+                    <span class="text-base-content/60">"Protocol"</span>
+                    <span>{block.protocol_version}</span>
+                    <span class="text-base-content/60">"Author"</span>
+                    <span class="font-mono break-all">{block.author.clone().unwrap_or_default()}</span>
+                    <span class="text-base-content/60">"Parent"</span>
+                    <span>
+                        {parent.map(|parent| view! {
+                            <a class="link link-primary" href=format!("/block/{}", parent.height)>{parent.height}</a>
+                        })}
+                    </span>
+                </div>
+                <h3 class="font-semibold mt-4">{format!("Transactions ({})", block.transactions.len())}</h3>
+                <div class="space-y-2">
+                    {block.transactions.into_iter().map(|transaction| view! {
+                        <TransactionSummary transaction=transaction />
+                    }).collect_view()}
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[server]
+pub async fn get_block(height_or_hash: String) -> Result<Option<Block>, ServerFnError> {
+    use crate::core::{
+        app_state::app_state,
+        graphql::{BlockOffset, BlockQuery},
+    };
+    use midnight_node_ledger_helpers::DefaultDB;
+
+    let app_state = app_state()?;
+    let indexer = &app_state.contract_indexer;
+    let query = BlockQuery::at(BlockOffset::parse(&height_or_hash)).with_transactions();
+    let mut block = indexer
+        .graphql()
+        .block(&query)
+        .await
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+    if let Some(block) = block.as_mut() {
+        for transaction in block.transactions.iter_mut() {
+            indexer
+                .decode_transaction::<DefaultDB>(transaction)
+                .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+        }
+    }
+    Ok(block)
+}
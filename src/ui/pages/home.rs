@@ -1,24 +1,44 @@
 use crate::{
-    domain::{ContractEvent, Event},
-    ui::components::contract_panel::ContractPanel,
+    domain::{
+        address::ContractAddress,
+        alert::Alert,
+        export::{parse_session, ExportedEvent},
+        network::Network,
+        ContractEvent, Event,
+    },
+    ui::components::{contract_panel::ContractPanel, header::get_chain_head},
 };
 use leptos::{prelude::*, task::spawn_local};
+use leptos_router::hooks::use_params_map;
 use server_fn::{codec::JsonEncoding, BoxedStream, ServerFnError, Websocket};
 
 #[component]
 pub fn HomePage() -> impl IntoView {
     use futures::{channel::mpsc, StreamExt};
     let contract_address = RwSignal::new("".to_string());
-    let (mut tx, rx) = mpsc::channel::<String>(100);
+    let (tx, rx) = mpsc::channel::<String>(100);
     let (connected, is_connected) = signal(false);
     let (show_progress_bar, set_show_progress_bar) = signal(false);
     let (error_message, set_error_message) = signal(None::<String>);
     let (timeleft, set_timeleft) = signal(None::<u64>);
+    let (dropped_events, set_dropped_events) = signal(0u64);
     let (show_about, set_show_about) = signal(true);
+    let (alerts, set_alerts) = signal(Vec::<Alert>::new());
+    // name of the exported session being browsed, which means no indexer is involved
+    let (imported_session, set_imported_session) = signal(None::<String>);
+    // network the server is connected to, so addresses for another one are refused before connecting
+    let (network, set_network) = signal(None::<Network>);
 
     let (contract_events, set_contract_events) = signal(Vec::<ContractEvent>::new());
+    // bumped whenever the events are replaced rather than appended to
+    let (events_generation, set_events_generation) = signal(0u64);
 
     if cfg!(feature = "hydrate") {
+        spawn_local(async move {
+            if let Ok(head) = get_chain_head().await {
+                set_network.set(Some(head.network));
+            }
+        });
         spawn_local(async move {
             match connect_to_contract(rx.map(|s| Ok(s)).into()).await {
                 Ok(mut messages) => {
@@ -39,6 +59,22 @@ pub fn HomePage() -> impl IntoView {
                                 Event::TimeLeft(timeleft) => {
                                     set_timeleft.set(Some(timeleft));
                                 }
+                                Event::Lagged { dropped } => {
+                                    set_dropped_events.update(|total| *total += dropped);
+                                }
+                                Event::Error(e) => {
+                                    set_show_progress_bar.set(false);
+                                    set_error_message.set(Some(e.to_string()));
+                                }
+                                Event::Alert(alert) => {
+                                    set_alerts.update(|alerts| alerts.insert(0, alert));
+                                }
+                                Event::ServerShuttingDown => {
+                                    set_show_progress_bar.set(false);
+                                    set_error_message.set(Some(
+                                        "The server is restarting. Refresh the page in a moment to reconnect.".to_string(),
+                                    ));
+                                }
                             },
                             Err(e) => {
                                 is_connected.set(true); // we want to disable this since most likely the websocket is closed. Force the refresh
@@ -59,12 +95,31 @@ pub fn HomePage() -> impl IntoView {
         });
     }
 
-    let contract_connect = move |_| {
+    let contract_connect = move |mut tx: mpsc::Sender<String>| {
+        let address =
+            ContractAddress::parse(&contract_address.get_untracked()).and_then(|address| {
+                match network.get_untracked() {
+                    Some(network) => address.for_network(network),
+                    None => Ok(address),
+                }
+            });
+        let address = match address {
+            Ok(address) => address,
+            Err(e) => {
+                set_error_message.set(Some(e.to_string()));
+                return;
+            }
+        };
         set_show_progress_bar.set(true);
         set_error_message.set(None);
         set_show_about.set(false);
+        if imported_session.get_untracked().is_some() {
+            set_imported_session.set(None);
+            set_contract_events.set(Vec::new());
+            set_events_generation.update(|generation| *generation += 1);
+        }
 
-        match tx.try_send(contract_address.get()) {
+        match tx.try_send(address.to_string()) {
             Ok(_) => {}
             Err(e) => {
                 set_show_progress_bar.set(false);
@@ -73,19 +128,94 @@ pub fn HomePage() -> impl IntoView {
         }
     };
 
+    // browsing an imported session leaves the controls enabled, so another file or a live contract can follow
+    #[cfg(feature = "hydrate")]
+    let import_session = move |file: web_sys::File| {
+        let name = file.name();
+        spawn_local(async move {
+            let text = wasm_bindgen_futures::JsFuture::from(file.text())
+                .await
+                .ok()
+                .and_then(|text| text.as_string());
+            match text.as_deref().map(parse_session) {
+                Some(Ok(events)) => {
+                    set_contract_events.set(events.into_iter().map(ExportedEvent::into_event).collect());
+                    set_events_generation.update(|generation| *generation += 1);
+                    set_imported_session.set(Some(name));
+                    set_error_message.set(None);
+                    set_show_about.set(false);
+                    set_show_progress_bar.set(false);
+                }
+                Some(Err(e)) => set_error_message.set(Some(format!("Could not import {}: {}", name, e))),
+                None => set_error_message.set(Some(format!("Could not read {}", name))),
+            }
+        });
+    };
+
+    // `/contract/:address` connects straight away, e.g. when following a link from the block explorer
+    let initial_address = use_params_map().read_untracked().get("address");
+    {
+        let tx = tx.clone();
+        Effect::new(move |_| {
+            if let Some(address) = initial_address.clone() {
+                contract_address.set(address);
+                contract_connect(tx.clone());
+            }
+        });
+    }
+
     view! {
-        <div class="flex flex-col items-center justify-center gap-4 mt-8">
+        <div
+            class="flex flex-col items-center justify-center gap-4 mt-8"
+            on:dragover=move |ev| ev.prevent_default()
+            on:drop=move |ev| {
+                ev.prevent_default();
+                if connected.get_untracked() {
+                    return;
+                }
+                #[cfg(feature = "hydrate")]
+                if let Some(file) = ev.data_transfer().and_then(|data| data.files()).and_then(|files| files.get(0)) {
+                    import_session(file);
+                }
+            }
+        >
             <div class="flex flex-row gap-2">
                 <input type="text" class="input w-80 md:w-96" disabled=connected placeholder="Enter Contract Address" bind:value=contract_address />
-                <button on:click=contract_connect disabled=connected class="btn btn-neutral">"Connect"</button>
+                <button on:click=move |_| contract_connect(tx.clone()) disabled=connected class="btn btn-neutral">"Connect"</button>
+                <label class="btn btn-ghost" class:btn-disabled=connected title="Or drop an exported session anywhere on the page">
+                    "Open session"
+                    <input
+                        type="file"
+                        class="hidden"
+                        accept=".json,.ndjson,.jsonl"
+                        disabled=connected
+                        on:change=move |_ev| {
+                            #[cfg(feature = "hydrate")]
+                            {
+                                let input: web_sys::HtmlInputElement = event_target(&_ev);
+                                if let Some(file) = input.files().and_then(|files| files.get(0)) {
+                                    import_session(file);
+                                }
+                            }
+                        }
+                    />
+                </label>
             </div>
 
+            <Show
+                when=move || imported_session.get().is_some()
+                fallback=move || view! {}>
+                <div class="badge badge-soft badge-info">
+                    {move || format!("Browsing {} offline", imported_session.get().unwrap_or_default())}
+                </div>
+            </Show>
+
             <Show
                 when=move || { show_about.get() }
                 fallback=move || view! {}>
                 <blockquote class="alert not-italic items-start text-xs leading-loose *:m-0!">
                     <p><svg class="size-4 ms-2 inline-block text-info" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><g fill="currentColor" stroke-linejoin="miter" stroke-linecap="butt"><circle cx="12" cy="12" r="10" fill="none" stroke="currentColor" stroke-linecap="square" stroke-miterlimit="10" stroke-width="2"></circle><path d="m12,17v-5.5c0-.276-.224-.5-.5-.5h-1.5" fill="none" stroke="currentColor" stroke-linecap="square" stroke-miterlimit="10" stroke-width="2"></path><circle cx="12" cy="7.25" r="1.25" fill="currentColor" stroke-width="2"></circle></g></svg></p>
-                    <p>"👋 Enter your testnet contract address and we will listen for any contract events for 5 minutes."</p>
+                    <p>"👋 Enter your testnet contract address and we will listen for any contract events for 5 minutes. To look at an exported session instead, drop the JSON or NDJSON file here."</p>
                 </blockquote>
 
             </Show>
@@ -111,8 +241,32 @@ pub fn HomePage() -> impl IntoView {
                 </div>
             </Show>
 
-            <div class="max-w-4xl mx-auto" class:hidden=move || !connected.get() >
-                <ContractPanel contract_events=contract_events />
+            <Show
+                when=move || { dropped_events.get() > 0 }
+                fallback=move || view! {}>
+                <div role="alert" class="alert alert-warning alert-soft">
+                    <span>{move || format!("{} events were skipped because the browser could not keep up.", dropped_events.get())}</span>
+                </div>
+            </Show>
+
+            <div class="flex flex-col gap-2 w-full max-w-4xl">
+                <For each=move || alerts.get() key=|alert| (alert.rule_id, alert.fired_at) let:alert>
+                    <div role="alert" class="alert alert-warning alert-soft">
+                        <span class="font-semibold">{alert.condition.to_string()}</span>
+                        <span>{alert.message.clone()}</span>
+                        {alert.transaction_hash.clone().map(|hash| view! {
+                            <a class="link link-primary font-mono" href=format!("/tx/{}", hash)>{hash.clone()}</a>
+                        })}
+                    </div>
+                </For>
+            </div>
+
+            <div class="max-w-4xl mx-auto" class:hidden=move || !connected.get() && imported_session.get().is_none()>
+                <ContractPanel
+                    contract_events=contract_events
+                    generation=events_generation
+                    offline=Signal::derive(move || imported_session.get().is_some())
+                />
             </div>
 
             <div class="flex w-52 flex-col gap-4" class:hidden=move || !show_progress_bar.get()>
@@ -129,26 +283,151 @@ pub fn HomePage() -> impl IntoView {
 pub async fn connect_to_contract(
     input: BoxedStream<String, ServerFnError>,
 ) -> Result<BoxedStream<Event, ServerFnError>, ServerFnError> {
-    use crate::core::app_state::AppState;
-    use futures::{channel::mpsc, StreamExt};
+    use crate::{
+        core::{app_state::app_state, delivery},
+        domain::{connection::SessionOrigin, SubscriptionError},
+    };
+    use axum::{extract::ConnectInfo, http::HeaderMap};
+    use futures::StreamExt;
     use midnight_node_ledger_helpers::DefaultDB;
-    use tracing::info;
+    use std::net::SocketAddr;
+    use tracing::{debug, field, info, info_span, Instrument, Span};
 
     let mut input = input;
-    let (tx, rx) = mpsc::channel(100);
-    let app_state = use_context::<AppState>().ok_or::<ServerFnError>(
-        ServerFnError::ServerError("Could not extract app state".to_string()),
-    )?;
-
-    tokio::spawn(async move {
-        if let Some(Ok(contract_address)) = input.next().await {
-            info!("got contract address");
-            let _ = app_state
-                .contract_indexer
-                .subscribe_to_contract::<DefaultDB>(contract_address, tx)
-                .await;
+    let (tx, rx) = delivery::channel(delivery::DEFAULT_CAPACITY);
+    let app_state = app_state()?;
+
+    if app_state.contract_indexer.is_shutting_down() {
+        return Err(ServerFnError::ServerError(
+            SubscriptionError::ShuttingDown.to_string(),
+        ));
+    }
+
+    let headers: HeaderMap = leptos_axum::extract().await?;
+    let ConnectInfo(peer): ConnectInfo<SocketAddr> = leptos_axum::extract().await?;
+    let client = app_state.limits.client_ip(&headers, peer);
+    let permit = match app_state
+        .limits
+        .check_origin(&headers)
+        .and_then(|()| app_state.limits.open_session(client))
+    {
+        Ok(permit) => permit,
+        Err(e) => {
+            info!("Refused session from {} {}", client, e);
+            // the stream ends right after the error, since the sender is dropped here
+            let _ = tx.send(Event::Error(SubscriptionError::LimitExceeded(e)));
+            return Ok(rx.map(|event| Ok(event)).into());
         }
-    });
+    };
+
+    let metrics = app_state.contract_indexer.metrics().clone();
+    let session = app_state
+        .contract_indexer
+        .connections()
+        .session(SessionOrigin::Browser, app_state.contract_indexer.network());
+    let delivered = session.clone();
+    let span = info_span!(
+        "session",
+        id = session.id(),
+        origin = %SessionOrigin::Browser,
+        network = %app_state.contract_indexer.network(),
+        address = field::Empty,
+    );
+    let task = async move {
+        let _permit = permit;
+        let _active = app_state
+            .contract_indexer
+            .metrics()
+            .session(SessionOrigin::Browser);
+        let disconnect = tx.clone();
+        let follow = async {
+            // keep reading until the browser sends an address we can subscribe to
+            loop {
+                let address = tokio::select! {
+                    address = input.next() => match address {
+                        Some(Ok(address)) => address,
+                        _ => break,
+                    },
+                    // a running subscription tells the browser itself, after closing upstream
+                    _ = app_state.contract_indexer.shutting_down() => {
+                        let _ = tx.send(Event::ServerShuttingDown);
+                        break;
+                    }
+                };
+                debug!("Got contract address");
+                let contract_address = match app_state.contract_indexer.parse_address(&address) {
+                    Ok(contract_address) => contract_address,
+                    Err(e) => {
+                        let error = SubscriptionError::InvalidAddress(e);
+                        session.failed(&error);
+                        if tx.send(Event::Error(error)).is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                let _subscription = match app_state.limits.subscribe(client) {
+                    Ok(permit) => permit,
+                    Err(e) => {
+                        let error = SubscriptionError::LimitExceeded(e);
+                        session.failed(&error);
+                        if tx.send(Event::Error(error)).is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                match app_state
+                    .contract_indexer
+                    .find_contract::<DefaultDB>(&contract_address)
+                    .await
+                {
+                    Ok(latest) => {
+                        if tx.send(Event::ContractEvent(latest)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        session.failed(&e);
+                        if tx.send(Event::Error(e)).is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+                session.set_address(&contract_address);
+                Span::current().record("address", field::display(&contract_address));
+                // alerts on the contract share the session, until the subscription ends
+                let forward_alerts = app_state.alerts.forward_to(&contract_address, tx.clone());
+                tokio::select! {
+                    result = app_state
+                        .contract_indexer
+                        .subscribe_to_contract::<DefaultDB>(contract_address.clone(), tx) => {
+                        if let Err(e) = result {
+                            session.failed(&e);
+                        }
+                    }
+                    _ = forward_alerts => {}
+                }
+                break;
+            }
+        };
+        tokio::select! {
+            _ = follow => {}
+            _ = session.terminated() => {
+                info!("Session {} terminated by an administrator", session.id());
+                let _ = disconnect.send(Event::Disconnect);
+            }
+        }
+    };
+    tokio::spawn(task.instrument(span));
 
-    Ok(rx.map(|event| Ok(event)).into())
+    Ok(rx
+        .inspect(move |event| match event {
+            Event::ContractEvent(_) => delivered.delivered(),
+            Event::Lagged { dropped } => metrics.events_dropped(*dropped),
+            _ => {}
+        })
+        .map(|event| Ok(event))
+        .into())
 }
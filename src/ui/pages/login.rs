@@ -0,0 +1,69 @@
+use leptos::{prelude::*, task::spawn_local};
+use leptos_router::hooks::use_navigate;
+
+#[component]
+pub fn LoginPage() -> impl IntoView {
+    let username = RwSignal::new(String::new());
+    let password = RwSignal::new(String::new());
+    let (error_message, set_error_message) = signal(None::<String>);
+    let navigate = use_navigate();
+
+    let submit = move |ev: leptos::ev::SubmitEvent| {
+        ev.prevent_default();
+        let navigate = navigate.clone();
+        let (username, password) = (username.get_untracked(), password.get_untracked());
+        spawn_local(async move {
+            match login(username, password).await {
+                Ok(()) => navigate("/admin", Default::default()),
+                Err(e) => set_error_message.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    view! {
+        <div class="flex flex-col items-center gap-4 mt-8">
+            <form class="card bg-base-100 border border-base-300 w-96" on:submit=submit>
+                <div class="card-body">
+                    <h2 class="card-title">"Sign in"</h2>
+                    <input type="text" class="input w-full" placeholder="Username" autocomplete="username" bind:value=username />
+                    <input type="password" class="input w-full" placeholder="Password" autocomplete="current-password" bind:value=password />
+                    <Show when=move || error_message.get().is_some() fallback=move || view! {}>
+                        <div role="alert" class="alert alert-error alert-soft">{move || error_message.get()}</div>
+                    </Show>
+                    <div class="card-actions justify-end">
+                        <button type="submit" class="btn btn-neutral">"Sign in"</button>
+                    </div>
+                </div>
+            </form>
+        </div>
+    }
+}
+
+/// Signs an account in to the admin page by setting the session cookie. Served outside `/api`, so it stays
+/// reachable when reads need a key.
+#[server(prefix = "/auth")]
+pub async fn login(username: String, password: String) -> Result<(), ServerFnError> {
+    use crate::core::{app_state::app_state, auth::session_cookie};
+    use axum::{
+        extract::ConnectInfo,
+        http::{header, HeaderMap, HeaderValue},
+    };
+    use std::net::SocketAddr;
+
+    let app_state = app_state()?;
+    let headers: HeaderMap = leptos_axum::extract().await?;
+    let ConnectInfo(peer): ConnectInfo<SocketAddr> = leptos_axum::extract().await?;
+    let client = app_state.limits.client_ip(&headers, peer);
+    let token = app_state
+        .auth
+        .login(client, &username, &password)
+        .await
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+    let response = expect_context::<leptos_axum::ResponseOptions>();
+    response.insert_header(
+        header::SET_COOKIE,
+        HeaderValue::from_str(&session_cookie(&token))
+            .map_err(|e| ServerFnError::ServerError(e.to_string()))?,
+    );
+    Ok(())
+}
@@ -1 +1,6 @@
+pub mod admin;
+pub mod analytics;
+pub mod block;
 pub mod home;
+pub mod login;
+pub mod transaction;
@@ -0,0 +1,93 @@
+use leptos::prelude::*;
+use leptos_router::hooks::use_params_map;
+
+use crate::{
+    domain::indexer::{format_timestamp, Transaction},
+    ui::components::transaction_summary::ContractActionRow,
+};
+
+#[component]
+pub fn TransactionPage() -> impl IntoView {
+    let params = use_params_map();
+    let transaction = Resource::new(
+        move || params.read().get("hash").unwrap_or_default(),
+        get_transaction,
+    );
+
+    view! {
+        <div class="flex flex-col items-center gap-4 mt-8">
+            <Suspense fallback=move || view! { <div class="skeleton h-32 w-96"></div> }>
+                {move || Suspend::new(async move {
+                    match transaction.await {
+                        Ok(Some(transaction)) => view! { <TransactionDetails transaction=transaction /> }.into_any(),
+                        Ok(None) => view! {
+                            <div role="alert" class="alert alert-warning alert-soft">"Transaction not found."</div>
+                        }.into_any(),
+                        Err(e) => view! {
+                            <div role="alert" class="alert alert-error alert-soft">{e.to_string()}</div>
+                        }.into_any(),
+                    }
+                })}
+            </Suspense>
+        </div>
+    }
+}
+
+#[component]
+fn TransactionDetails(transaction: Transaction) -> impl IntoView {
+    let raw_size = transaction.raw.as_ref().map(|raw| raw.len() / 2).unwrap_or_default();
+    let block = transaction.block.clone();
+    view! {
+        <div class="card bg-base-100 border border-base-300 w-[1000px]">
+            <div class="card-body">
+                <h2 class="card-title">"Transaction"</h2>
+                <div class="grid grid-cols-[max-content_1fr] gap-x-4 gap-y-1 text-sm">
+                    <span class="text-base-content/60">"Hash"</span>
+                    <span class="font-mono break-all">{transaction.hash.clone()}</span>
+                    <span class="text-base-content/60">"Block"</span>
+                    <a class="link link-primary" href=format!("/block/{}", block.height)>{block.height}</a>
+                    <span class="text-base-content/60">"Time"</span>
+                    <span>{format_timestamp(block.timestamp)}</span>
+                    <span class="text-base-content/60">"Protocol"</span>
+                    <span>{transaction.protocol_version}</span>
+                    <span class="text-base-content/60">"Size"</span>
+                    <span>{format!("{} bytes", raw_size)}</span>
+                    <span class="text-base-content/60">"Identifiers"</span>
+                    <span class="font-mono break-all">{transaction.identifiers.join(", ")}</span>
+                </div>
+                {transaction.decoded.map(|decoded| view! {
+                    <details class="collapse collapse-arrow bg-base-200 mt-4">
+                        <summary class="collapse-title font-semibold">"Decoded transaction"</summary>
+                        <pre class="collapse-content text-xs overflow-x-auto">{decoded}</pre>
+                    </details>
+                })}
+                <h3 class="font-semibold mt-4">{format!("Contract actions ({})", transaction.contract_actions.len())}</h3>
+                <div class="space-y-2">
+                    {transaction.contract_actions.into_iter().map(|action| view! {
+                        <ContractActionRow action=action show_state=true />
+                    }).collect_view()}
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[server]
+pub async fn get_transaction(hash: String) -> Result<Option<Transaction>, ServerFnError> {
+    use crate::core::{app_state::app_state, graphql::TransactionQuery};
+    use midnight_node_ledger_helpers::DefaultDB;
+
+    let app_state = app_state()?;
+    let indexer = &app_state.contract_indexer;
+    let mut transaction = indexer
+        .graphql()
+        .transaction(&TransactionQuery::by_hash(hash))
+        .await
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+    if let Some(transaction) = transaction.as_mut() {
+        indexer
+            .decode_transaction::<DefaultDB>(transaction)
+            .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+    }
+    Ok(transaction)
+}